@@ -0,0 +1,424 @@
+//! Non-interactive agent: answers a single question, querying logs as needed.
+//! Used by `zeteo ask` for scripts and on-call bots.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::providers::{AiProvider, ChatRequest, Message, ResponseFormat, Tool};
+use crate::tools::{LogQueryResult, ToolExecutor, DEFAULT_TOOL_CONCURRENCY};
+
+/// Tool-call rounds before the model has to answer with what it has
+const MAX_TOOL_ROUNDS: usize = 8;
+
+/// Extra attempts when the structured reply doesn't validate
+const MAX_REPAIR_ATTEMPTS: usize = 1;
+
+/// Lower than the TUI default: automation wants repeatable answers
+const DEFAULT_TEMPERATURE: f32 = 0.2;
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Final answer of `zeteo ask --json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IncidentReport {
+    pub summary: String,
+    pub suspected_root_cause: Option<String>,
+    pub affected_services: Vec<String>,
+    /// IDs of log entries returned by `query_logs` that support the conclusion
+    pub evidence_log_ids: Vec<String>,
+    /// 0.0 (guess) to 1.0 (certain)
+    pub confidence: f64,
+}
+
+impl IncidentReport {
+    /// JSON Schema of the report, written to be accepted by OpenAI strict mode
+    /// (every property required, no additional properties)
+    pub fn schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "summary": {
+                    "type": "string",
+                    "description": "What is happening, in two or three sentences"
+                },
+                "suspected_root_cause": {
+                    "type": ["string", "null"],
+                    "description": "Most likely cause, or null if the logs don't point to one"
+                },
+                "affected_services": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "Services showing errors or degraded behaviour"
+                },
+                "evidence_log_ids": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "description": "The \"id\" of each log entry from query_logs that supports the conclusion"
+                },
+                "confidence": {
+                    "type": "number",
+                    "description": "Confidence in the root cause, from 0.0 to 1.0"
+                }
+            },
+            "required": [
+                "summary",
+                "suspected_root_cause",
+                "affected_services",
+                "evidence_log_ids",
+                "confidence"
+            ],
+            "additionalProperties": false
+        })
+    }
+
+    /// Parse and check a model reply. `known_ids` are the log IDs the model
+    /// was actually shown; citing anything else is rejected.
+    pub fn parse(content: &str, known_ids: &HashSet<String>) -> Result<Self> {
+        let json = extract_json_object(content).context("Reply contains no JSON object")?;
+        let report: IncidentReport =
+            serde_json::from_str(json).context("Reply does not match the report schema")?;
+
+        if report.summary.trim().is_empty() {
+            anyhow::bail!("\"summary\" must not be empty");
+        }
+        if !(0.0..=1.0).contains(&report.confidence) {
+            anyhow::bail!(
+                "\"confidence\" must be between 0.0 and 1.0, got {}",
+                report.confidence
+            );
+        }
+
+        let unknown: Vec<&str> = report
+            .evidence_log_ids
+            .iter()
+            .filter(|id| !known_ids.contains(*id))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            anyhow::bail!(
+                "\"evidence_log_ids\" contains IDs that were not in any query result: {}",
+                unknown.join(", ")
+            );
+        }
+
+        Ok(report)
+    }
+}
+
+/// The outermost `{...}` in a reply, tolerating code fences and prose around it
+pub(crate) fn extract_json_object(content: &str) -> Option<&str> {
+    let start = content.find('{')?;
+    let end = content.rfind('}')?;
+    (end > start).then(|| &content[start..=end])
+}
+
+/// Conversation of one investigation
+struct Investigation {
+    messages: Vec<Message>,
+    answer: String,
+    /// IDs of every log entry returned by a tool
+    log_ids: HashSet<String>,
+}
+
+pub struct Agent {
+    provider: Arc<dyn AiProvider>,
+    tool_executor: Option<ToolExecutor>,
+    backend_name: Option<String>,
+    temperature: f32,
+    max_tokens: u32,
+    tool_concurrency: usize,
+}
+
+impl Agent {
+    pub fn new(
+        provider: Arc<dyn AiProvider>,
+        tool_executor: Option<ToolExecutor>,
+        backend_name: Option<String>,
+    ) -> Self {
+        Self {
+            provider,
+            tool_executor,
+            backend_name,
+            temperature: DEFAULT_TEMPERATURE,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            tool_concurrency: DEFAULT_TOOL_CONCURRENCY,
+        }
+    }
+
+    pub fn with_tool_concurrency(mut self, limit: usize) -> Self {
+        self.tool_concurrency = limit;
+        self
+    }
+
+    /// Override the defaults with a provider profile's settings
+    pub fn with_sampling(mut self, temperature: Option<f32>, max_tokens: Option<u32>) -> Self {
+        self.temperature = temperature.unwrap_or(self.temperature);
+        self.max_tokens = max_tokens.unwrap_or(self.max_tokens);
+        self
+    }
+
+    /// Answer in markdown
+    pub async fn ask(&self, question: &str) -> Result<String> {
+        Ok(self.investigate(question).await?.answer)
+    }
+
+    /// Investigate, then have the model restate its findings as an [`IncidentReport`]
+    pub async fn ask_json(&self, question: &str) -> Result<IncidentReport> {
+        let Investigation {
+            mut messages,
+            log_ids,
+            ..
+        } = self.investigate(question).await?;
+
+        messages.push(text_message(
+            "user",
+            format!(
+                "Now report your findings as a single JSON object matching this JSON Schema, \
+                 with no other text:\n{}\n\nOnly cite log IDs that appeared in query results.",
+                serde_json::to_string_pretty(&IncidentReport::schema())?
+            ),
+        ));
+
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .provider
+                .chat(ChatRequest {
+                    messages: messages.clone(),
+                    temperature: Some(self.temperature),
+                    max_tokens: Some(self.max_tokens),
+                    tools: None,
+                    response_format: Some(ResponseFormat {
+                        name: "incident_report".to_string(),
+                        schema: IncidentReport::schema(),
+                    }),
+                })
+                .await?;
+
+            match IncidentReport::parse(&response.content, &log_ids) {
+                Ok(report) => return Ok(report),
+                Err(e) if attempt < MAX_REPAIR_ATTEMPTS => {
+                    attempt += 1;
+                    messages.push(text_message("assistant", response.content));
+                    messages.push(text_message(
+                        "user",
+                        format!(
+                            "That reply is invalid: {:#}. Reply with only the corrected JSON object.",
+                            e
+                        ),
+                    ));
+                }
+                Err(e) => {
+                    return Err(e.context(format!(
+                        "Model did not produce a valid report. Last reply:\n{}",
+                        response.content
+                    )))
+                }
+            }
+        }
+    }
+
+    async fn investigate(&self, question: &str) -> Result<Investigation> {
+        let backend = self
+            .tool_executor
+            .as_ref()
+            .filter(|executor| executor.has_backend())
+            .map(|_| self.backend_name.as_deref().unwrap_or("logs"));
+        let tool_definitions = self
+            .tool_executor
+            .as_ref()
+            .map(|executor| executor.definitions())
+            .unwrap_or_default();
+        let mcp_tools = self
+            .tool_executor
+            .as_ref()
+            .and_then(|executor| executor.mcp())
+            .map(|mcp| mcp.definitions())
+            .unwrap_or_default();
+
+        let mut messages = vec![
+            text_message("system", system_prompt(backend, &mcp_tools)),
+            text_message("user", question.to_string()),
+        ];
+        let mut log_ids = HashSet::new();
+
+        for round in 0..=MAX_TOOL_ROUNDS {
+            let tools = match &self.tool_executor {
+                Some(_) if round < MAX_TOOL_ROUNDS => Some(tool_definitions.clone()),
+                _ => None,
+            };
+
+            let response = self
+                .provider
+                .chat(ChatRequest {
+                    messages: messages.clone(),
+                    temperature: Some(self.temperature),
+                    max_tokens: Some(self.max_tokens),
+                    tools,
+                    response_format: None,
+                })
+                .await?;
+
+            let tool_calls = response.tool_calls.unwrap_or_default();
+            let Some(executor) = self
+                .tool_executor
+                .as_ref()
+                .filter(|_| !tool_calls.is_empty() && round < MAX_TOOL_ROUNDS)
+            else {
+                messages.push(text_message("assistant", response.content.clone()));
+                return Ok(Investigation {
+                    messages,
+                    answer: response.content,
+                    log_ids,
+                });
+            };
+
+            messages.push(Message {
+                role: "assistant".to_string(),
+                content: response.content,
+                tool_calls: Some(tool_calls.clone()),
+                tool_call_id: None,
+            });
+
+            let results = executor
+                .execute_all(&tool_calls, self.tool_concurrency, None)
+                .await;
+            for (call, result) in tool_calls.iter().zip(results) {
+                if let Ok(parsed) = serde_json::from_str::<LogQueryResult>(&result) {
+                    log_ids.extend(parsed.logs.into_iter().map(|log| log.id));
+                }
+                messages.push(Message {
+                    role: "tool".to_string(),
+                    content: result,
+                    tool_calls: None,
+                    tool_call_id: Some(call.id.clone()),
+                });
+            }
+        }
+
+        unreachable!("the last round is sent without tools")
+    }
+}
+
+fn text_message(role: &str, content: String) -> Message {
+    Message {
+        role: role.to_string(),
+        content,
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+/// System prompt for log analysis. `backend` names the log backend when
+/// tools are available.
+pub fn system_prompt(backend: Option<&str>, mcp_tools: &[Tool]) -> String {
+    let mut msg = String::from(
+        "You are Zeteo, an AI assistant specialized in log analysis and observability.\n\n",
+    );
+
+    if let Some(backend) = backend {
+        msg.push_str(&format!(
+            "## Your Capabilities\n\
+            You have access to a log backend ({backend}) and can query logs to help users investigate issues, \
+            find errors, analyze patterns, and troubleshoot problems.\n\n\
+            ## Available Tools\n\n\
+            ### 1. query_logs\n\
+            Search and retrieve logs from the backend.\n\
+            - **query** (required): Search string. Use '*' for all logs, or terms like 'error', 'timeout', 'failed'.\n\
+            - **max_results** (optional): Number of results (default: 50, max: 200). Start with 20-50 for initial queries.\n\
+            - **level** (optional): Filter by severity - must be exactly one of: ERROR, WARN, INFO, DEBUG\n\
+            - **service** (optional): Filter by service name (use list_services first if unsure).\n\
+            - **start_time** (optional): Relative time like '1h', '30m', '2d' or ISO 8601 format.\n\
+            - **end_time** (optional): Defaults to now.\n\n\
+            ### 2. list_services\n\
+            Get available service names. No parameters required. Call this first if you need to filter by service.\n\n\
+            ### 3. get_log_stats\n\
+            Get aggregated statistics (counts by level, service distribution).\n\
+            - **start_time** (optional): Start of time range.\n\
+            - **end_time** (optional): End of time range.\n\n\
+            ## Tool Usage Guidelines\n\n\
+            1. **Start broad, then narrow**: Begin with a general query, then refine based on results.\n\
+            2. **Use appropriate time ranges**: Default to '1h' for recent issues, '24h' for patterns, '7d' for trends.\n\
+            3. **Check services first**: If filtering by service, call list_services to get valid names.\n\
+            4. **Combine filters wisely**: Use level + query together for targeted results.\n\
+            5. **Handle empty results**: If no results, try broadening the query or time range.\n\n\
+            ## Response Format\n\n\
+            - Summarize findings clearly with key insights first.\n\
+            - Highlight error patterns, anomalies, or concerning trends.\n\
+            - Provide actionable recommendations when issues are found.\n\
+            - Format log snippets in code blocks for readability.\n\
+            - If results are truncated, suggest how to narrow the search.",
+        ));
+    } else {
+        msg.push_str(
+            "You can help with general questions about observability, logging best practices, \
+            and troubleshooting strategies.\n\n\
+            **Note**: No log backend is currently configured. To enable log analysis, \
+            configure a backend (kibana, openobserve, or elasticsearch) in your config file.",
+        );
+    }
+
+    if !mcp_tools.is_empty() {
+        msg.push_str(
+            "\n\n## Tools from MCP servers\n\n\
+            These tools are provided by the user's MCP servers. Each name starts with the server \
+            it belongs to. Use them alongside the logs, for example to check recent deploys or \
+            feature flag changes around the time errors started.\n",
+        );
+        for tool in mcp_tools {
+            msg.push_str(&format!(
+                "- **{}**: {}\n",
+                tool.function.name, tool.function.description
+            ));
+        }
+    }
+
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_fenced_report() {
+        let reply = "```json\n{\"summary\": \"Checkout is timing out on payment calls.\", \
+            \"suspected_root_cause\": \"payment-db connection pool exhausted\", \
+            \"affected_services\": [\"checkout\", \"payment\"], \
+            \"evidence_log_ids\": [\"a1\"], \"confidence\": 0.7}\n```";
+
+        let report = IncidentReport::parse(reply, &ids(&["a1", "b2"])).unwrap();
+        assert_eq!(report.affected_services, vec!["checkout", "payment"]);
+        assert_eq!(report.evidence_log_ids, vec!["a1"]);
+        assert_eq!(report.confidence, 0.7);
+    }
+
+    #[test]
+    fn test_invalid_reports_are_rejected() {
+        let known = ids(&["a1"]);
+        let report = |confidence: &str, evidence: &str| {
+            format!(
+                r#"{{"summary": "s", "suspected_root_cause": null, "affected_services": [],
+                    "evidence_log_ids": {}, "confidence": {}}}"#,
+                evidence, confidence
+            )
+        };
+
+        assert!(IncidentReport::parse(&report("0.5", r#"["a1"]"#), &known).is_ok());
+
+        let err = IncidentReport::parse(&report("85", "[]"), &known).unwrap_err();
+        assert!(err.to_string().contains("between 0.0 and 1.0"));
+
+        let err = IncidentReport::parse(&report("0.5", r#"["zz"]"#), &known).unwrap_err();
+        assert!(err.to_string().contains("zz"));
+
+        assert!(IncidentReport::parse("The logs look fine.", &known).is_err());
+        assert!(IncidentReport::parse(r#"{"summary": "missing fields"}"#, &known).is_err());
+    }
+}
@@ -5,6 +5,7 @@ use serde_json::json;
 use std::collections::HashMap;
 
 use super::{LogBackendClient, LogEntry, LogQuery};
+use crate::config::ElasticsearchConfig;
 
 pub struct ElasticsearchClient {
     url: String,
@@ -36,6 +37,16 @@ impl ElasticsearchClient {
         })
     }
 
+    pub fn from_config(config: &ElasticsearchConfig) -> Result<Self> {
+        Self::new(
+            config.url.clone(),
+            config.username.clone(),
+            config.password.clone(),
+            config.index_pattern.clone(),
+            config.verify_ssl,
+        )
+    }
+
     fn build_query(&self, query: &LogQuery) -> serde_json::Value {
         let mut must = vec![];
 
@@ -107,6 +118,7 @@ impl ElasticsearchClient {
         let source = hit.get("_source")?;
 
         Some(LogEntry {
+            id: hit.get("_id").and_then(|v| v.as_str()).map(String::from),
             timestamp: source
                 .get("@timestamp")
                 .or_else(|| source.get("timestamp"))
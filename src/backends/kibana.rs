@@ -5,6 +5,7 @@ use serde_json::json;
 use std::collections::HashMap;
 
 use super::{LogBackendClient, LogEntry, LogQuery};
+use crate::config::KibanaConfig;
 
 pub struct KibanaClient {
     url: String,
@@ -36,6 +37,16 @@ impl KibanaClient {
         })
     }
 
+    pub fn from_config(config: &KibanaConfig) -> Result<Self> {
+        Self::new(
+            config.url.clone(),
+            config.auth_token.clone(),
+            config.index_pattern.clone(),
+            config.version.clone(),
+            config.verify_ssl,
+        )
+    }
+
     fn build_search_body(&self, query: &LogQuery) -> serde_json::Value {
         let mut filters: Vec<serde_json::Value> = vec![];
 
@@ -222,6 +233,7 @@ impl KibanaClient {
             .map(String::from);
 
         Some(LogEntry {
+            id: hit.get("_id").and_then(|v| v.as_str()).map(String::from),
             timestamp,
             level,
             message,
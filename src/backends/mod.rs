@@ -1,11 +1,13 @@
 pub mod elasticsearch;
 pub mod kibana;
 pub mod openobserve;
+pub mod registry;
 
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tokio::sync::mpsc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogQuery {
@@ -21,6 +23,9 @@ pub struct LogQuery {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
+    /// Document ID in the backend, when it has one
+    #[serde(default)]
+    pub id: Option<String>,
     pub timestamp: String,
     pub level: String,
     pub message: String,
@@ -37,4 +42,17 @@ pub trait LogBackendClient: Send + Sync {
     async fn health_check(&self) -> Result<bool>;
     #[allow(dead_code)]
     fn backend_name(&self) -> &str;
+
+    /// Whether the backend pushes new entries through `tail` instead of
+    /// being polled
+    fn supports_tail(&self) -> bool {
+        false
+    }
+
+    /// Send entries matching `query` from `query.start_time` on to `sink` as
+    /// they arrive, until `sink` is closed. For backends with a streaming API
+    /// such as Loki's tail websocket; they also override `supports_tail`.
+    async fn tail(&self, _query: &LogQuery, _sink: mpsc::Sender<LogEntry>) -> Result<()> {
+        anyhow::bail!("{} has no live tail API", self.backend_name())
+    }
 }
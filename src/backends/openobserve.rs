@@ -5,6 +5,7 @@ use serde_json::json;
 use std::collections::HashMap;
 
 use super::{LogBackendClient, LogEntry, LogQuery};
+use crate::config::OpenObserveConfig;
 
 pub struct OpenObserveClient {
     url: String,
@@ -39,6 +40,17 @@ impl OpenObserveClient {
         })
     }
 
+    pub fn from_config(config: &OpenObserveConfig) -> Result<Self> {
+        Self::new(
+            config.url.clone(),
+            config.username.clone(),
+            config.password.clone(),
+            config.organization.clone(),
+            config.stream.clone(),
+            config.verify_ssl,
+        )
+    }
+
     fn build_sql_query(&self, query: &LogQuery) -> String {
         let mut conditions = vec![];
 
@@ -118,6 +130,7 @@ impl OpenObserveClient {
         };
 
         Some(LogEntry {
+            id: None,
             timestamp: record
                 .get("_timestamp")
                 .and_then(|v| v.as_i64())
@@ -0,0 +1,206 @@
+//! Log backend constructors keyed by the `type` of a backend in config.json.
+//! The built-in backends are registered from the start; other crates add
+//! their own with [`register_backend`] and configure them like any other:
+//!
+//! ```json
+//! "backends": { "loki": { "type": "loki", "url": "http://loki:3100" } }
+//! ```
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use super::{
+    elasticsearch::ElasticsearchClient, kibana::KibanaClient, openobserve::OpenObserveClient,
+    LogBackendClient,
+};
+use crate::config::{Config, LogBackend};
+
+/// Builds a client from a backend's entry in config.json, `type` included
+pub type BackendConstructor =
+    Arc<dyn Fn(&Value) -> Result<Arc<dyn LogBackendClient>> + Send + Sync>;
+
+static CONSTRUCTORS: LazyLock<RwLock<HashMap<String, BackendConstructor>>> = LazyLock::new(|| {
+    let builtin: [(&str, BackendConstructor); 3] = [
+        ("elasticsearch", typed(ElasticsearchClient::from_config)),
+        ("openobserve", typed(OpenObserveClient::from_config)),
+        ("kibana", typed(KibanaClient::from_config)),
+    ];
+    RwLock::new(
+        builtin
+            .into_iter()
+            .map(|(name, constructor)| (name.to_string(), constructor))
+            .collect(),
+    )
+});
+
+/// A constructor that parses the settings into `C` first
+fn typed<C, B>(build: fn(&C) -> Result<B>) -> BackendConstructor
+where
+    C: DeserializeOwned + 'static,
+    B: LogBackendClient + 'static,
+{
+    Arc::new(move |settings: &Value| {
+        let config: C = serde_json::from_value(settings.clone())?;
+        Ok(Arc::new(build(&config)?) as Arc<dyn LogBackendClient>)
+    })
+}
+
+/// Make backends of `backend_type` available to config.json, replacing any
+/// constructor already registered for it
+#[allow(dead_code)]
+pub fn register_backend<F>(backend_type: &str, constructor: F)
+where
+    F: Fn(&Value) -> Result<Arc<dyn LogBackendClient>> + Send + Sync + 'static,
+{
+    CONSTRUCTORS
+        .write()
+        .unwrap()
+        .insert(backend_type.to_string(), Arc::new(constructor));
+}
+
+/// Registered backend types, sorted
+pub fn backend_types() -> Vec<String> {
+    let mut types: Vec<String> = CONSTRUCTORS.read().unwrap().keys().cloned().collect();
+    types.sort();
+    types
+}
+
+/// Build the client for a backend from config.json
+pub fn build_backend(backend: &LogBackend) -> Result<Arc<dyn LogBackendClient>> {
+    let backend_type = backend.backend_type();
+    if backend_type.is_empty() {
+        return Err(anyhow!("Backend has no \"type\""));
+    }
+
+    let constructor = CONSTRUCTORS
+        .read()
+        .unwrap()
+        .get(backend_type)
+        .cloned()
+        .ok_or_else(|| {
+            anyhow!(
+                "Unknown backend type '{}'. Known types: {}",
+                backend_type,
+                backend_types().join(", ")
+            )
+        })?;
+
+    let settings = serde_json::to_value(backend)?;
+    constructor(&settings).with_context(|| format!("Invalid {} backend", backend_type))
+}
+
+/// Build the client for the backend configured under `name`
+pub fn build_named_backend(config: &Config, name: &str) -> Result<Arc<dyn LogBackendClient>> {
+    let backend = config
+        .backends
+        .get(name)
+        .ok_or_else(|| anyhow!("Backend '{}' not found in config", name))?;
+    build_backend(backend).with_context(|| format!("Backend '{}'", name))
+}
+
+/// The first backend that can be built, with its name, trying the usual
+/// names before the rest of config.json in alphabetical order
+pub fn find_backend(config: &Config) -> Option<(Arc<dyn LogBackendClient>, String)> {
+    const PREFERRED: [&str; 3] = ["openobserve", "kibana", "elasticsearch"];
+    let mut others: Vec<&str> = config
+        .backends
+        .keys()
+        .map(String::as_str)
+        .filter(|name| !PREFERRED.contains(name))
+        .collect();
+    others.sort();
+
+    PREFERRED.into_iter().chain(others).find_map(|name| {
+        let client = build_named_backend(config, name).ok()?;
+        Some((client, name.to_string()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backends::{LogEntry, LogQuery};
+    use async_trait::async_trait;
+
+    struct Fixed(String);
+
+    #[async_trait]
+    impl LogBackendClient for Fixed {
+        async fn query_logs(&self, _query: &LogQuery) -> Result<Vec<LogEntry>> {
+            Ok(Vec::new())
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn backend_name(&self) -> &str {
+            &self.0
+        }
+    }
+
+    fn parse(json: Value) -> LogBackend {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_builds_builtin_and_registered_backends() {
+        let kibana = parse(serde_json::json!({"type": "kibana", "url": "http://kibana:5601"}));
+        assert!(build_backend(&kibana).is_ok());
+
+        let custom = parse(serde_json::json!({"type": "test-custom", "url": "http://loki:3100"}));
+        let err = build_backend(&custom).err().unwrap().to_string();
+        assert!(
+            err.contains("Unknown backend type 'test-custom'"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("elasticsearch, kibana, openobserve"),
+            "{}",
+            err
+        );
+
+        register_backend("test-custom", |settings| {
+            let url = settings["url"].as_str().context("missing url")?;
+            Ok(Arc::new(Fixed(url.to_string())) as Arc<dyn LogBackendClient>)
+        });
+        assert!(backend_types().contains(&"test-custom".to_string()));
+        let client = build_backend(&custom).unwrap();
+        assert_eq!(client.backend_name(), "http://loki:3100");
+    }
+
+    #[test]
+    fn test_find_backend_skips_broken_ones() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "servers": {},
+            "backends": {
+                "zeta": {"type": "kibana", "url": "http://kibana:5601"},
+                "alpha": {"type": "elasticsearch", "url": "http://es:9200"},
+                "openobserve": {"type": "openobserve", "url": "http://oo:5080"}
+            }
+        }))
+        .unwrap();
+
+        // openobserve comes first but lacks its credentials
+        let (_, name) = find_backend(&config).unwrap();
+        assert_eq!(name, "alpha");
+        assert!(build_named_backend(&config, "zeta").is_ok());
+        let err = build_named_backend(&config, "loki").err().unwrap();
+        assert!(err.to_string().contains("'loki' not found"), "{}", err);
+    }
+
+    #[test]
+    fn test_invalid_builtin_settings_are_reported() {
+        let broken = parse(serde_json::json!({"type": "openobserve", "url": "http://oo:5080"}));
+        let err = format!("{:#}", build_backend(&broken).err().unwrap());
+        assert!(err.contains("Invalid openobserve backend"), "{}", err);
+        assert!(err.contains("missing field `username`"), "{}", err);
+
+        let untyped = parse(serde_json::json!({"url": "http://oo:5080"}));
+        assert!(build_backend(&untyped).is_err());
+    }
+}
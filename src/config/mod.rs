@@ -9,51 +9,359 @@ pub struct Config {
     pub servers: HashMap<String, McpServer>,
     #[serde(default)]
     pub backends: HashMap<String, LogBackend>,
+    #[serde(default)]
+    pub providers: HashMap<String, ProviderConfig>,
+    /// Provider profile used when `--provider` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_provider: Option<String>,
+    /// Token prices keyed by model name (or model name prefix)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub pricing: HashMap<String, ModelPrice>,
+    /// Tool calls from one model turn that may run at once (default 4)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_concurrency: Option<usize>,
+    /// Where conversations are saved for `/resume`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<SessionConfig>,
+}
+
+/// Session storage settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionConfig {
+    /// Defaults to `redis` when Redis is reachable at `redis_url`, else `file`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage: Option<SessionStorageKind>,
+    /// Directory for the file storage; defaults to `<data dir>/zeteo/sessions`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    /// Defaults to `REDIS_URL`, then `redis://localhost:6379`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redis_url: Option<String>,
+    /// Have a model title and summarize each conversation after its first
+    /// answer (default true). Turn off where conversations may not be sent
+    /// to a model for anything but answering.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_title: Option<bool>,
+    /// Provider profile for titles; defaults to the one in use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_provider: Option<String>,
+    /// Model for titles, such as a small cheap one; defaults to the
+    /// profile's model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_model: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStorageKind {
+    /// JSON files under the data directory
+    File,
+    /// A Redis server; sessions expire after seven days
+    Redis,
+    /// Don't save conversations
+    None,
+}
+
+/// Prices in USD per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    /// Price of prompt tokens served from cache; defaults to `input`
+    pub cached_input: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpServer {
+    /// Program to spawn for a local server; unused when `url` is set
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub command: String,
+    #[serde(default)]
     pub args: Vec<String>,
     #[serde(default)]
     pub env: HashMap<String, String>,
+    /// Endpoint of a remote server (Streamable HTTP, or HTTP+SSE)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Extra HTTP headers for a remote server
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+    /// Environment variable holding a bearer token for a remote server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer_token_env: Option<String>,
 }
 
+impl McpServer {
+    /// Headers for every request to a remote server, including the bearer token
+    pub fn http_headers(&self) -> Result<HashMap<String, String>> {
+        let mut headers = self.headers.clone();
+        if let Some(var) = &self.bearer_token_env {
+            let token = std::env::var(var)
+                .with_context(|| format!("Environment variable {} is not set", var))?;
+            headers.insert("Authorization".to_string(), format!("Bearer {}", token));
+        }
+        Ok(headers)
+    }
+}
+
+/// A log backend entry in config.json, selected by its `type`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum LogBackend {
-    Elasticsearch {
-        url: String,
-        username: Option<String>,
-        password: Option<String>,
-        #[serde(default = "default_index_pattern")]
-        index_pattern: String,
-        #[serde(default)]
-        verify_ssl: bool,
-    },
-    OpenObserve {
-        url: String,
-        username: String,
-        password: String,
-        #[serde(default = "default_organization")]
-        organization: String,
-        #[serde(default = "default_stream")]
-        stream: String,
-        #[serde(default)]
-        verify_ssl: bool,
+    Elasticsearch(ElasticsearchConfig),
+    OpenObserve(OpenObserveConfig),
+    Kibana(KibanaConfig),
+    /// Any other type, kept as written for a backend registered through
+    /// `backends::registry`. A built-in type whose settings don't parse also
+    /// lands here, so building it reports the actual problem.
+    #[serde(untagged)]
+    Custom(serde_json::Value),
+}
+
+impl LogBackend {
+    /// The `type` this entry names in config.json
+    pub fn backend_type(&self) -> &str {
+        match self {
+            LogBackend::Elasticsearch(_) => "elasticsearch",
+            LogBackend::OpenObserve(_) => "openobserve",
+            LogBackend::Kibana(_) => "kibana",
+            LogBackend::Custom(settings) => settings
+                .get("type")
+                .and_then(|t| t.as_str())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Index pattern (or OpenObserve stream) queried when none is given
+    pub fn index_pattern(&self) -> Option<&str> {
+        match self {
+            LogBackend::Elasticsearch(config) => Some(&config.index_pattern),
+            LogBackend::OpenObserve(config) => Some(&config.stream),
+            LogBackend::Kibana(config) => Some(&config.index_pattern),
+            LogBackend::Custom(settings) => settings.get("index_pattern").and_then(|p| p.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElasticsearchConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    #[serde(default = "default_index_pattern")]
+    pub index_pattern: String,
+    #[serde(default)]
+    pub verify_ssl: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenObserveConfig {
+    pub url: String,
+    pub username: String,
+    pub password: String,
+    #[serde(default = "default_organization")]
+    pub organization: String,
+    #[serde(default = "default_stream")]
+    pub stream: String,
+    #[serde(default)]
+    pub verify_ssl: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KibanaConfig {
+    pub url: String,
+    pub auth_token: Option<String>,
+    #[serde(default = "default_index_pattern")]
+    pub index_pattern: String,
+    #[serde(default)]
+    pub verify_ssl: bool,
+    #[serde(default = "default_kibana_version")]
+    pub version: String,
+}
+
+/// An AI provider profile defined in config.json
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    #[serde(rename = "type")]
+    pub provider_type: ProviderType,
+    #[serde(default, alias = "base_url")]
+    pub endpoint: Option<String>,
+    pub model: Option<String>,
+    /// Additional models offered by the `/model` picker
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// API key stored directly in the config file
+    pub api_key: Option<String>,
+    /// Environment variable holding the API key
+    pub api_key_env: Option<String>,
+    /// Shell command printing the API key (e.g. a secrets manager lookup)
+    pub api_key_command: Option<String>,
+    /// Header carrying the API key; `Authorization` sends it as a bearer token
+    #[serde(default = "default_api_key_header")]
+    pub api_key_header: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Azure OpenAI deployment name
+    pub deployment: Option<String>,
+    /// Azure OpenAI REST API version
+    pub api_version: Option<String>,
+    /// Azure OpenAI authentication. When unset the API key is used, or Entra ID
+    /// from the `AZURE_*` environment variables if there is no key.
+    pub auth: Option<AzureAuthConfig>,
+    /// Vertex AI project ID
+    pub project: Option<String>,
+    /// Vertex AI region
+    pub location: Option<String>,
+    /// Google credentials JSON used instead of Application Default Credentials
+    pub credentials_file: Option<String>,
+    /// Profiles tried in order when this one keeps failing
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<String>,
+    /// Retries after the first attempt on transient errors (429, 5xx,
+    /// timeouts); 0 turns them off
+    pub max_retries: Option<u32>,
+}
+
+/// How an Azure OpenAI profile authenticates. Unset IDs fall back to the
+/// `AZURE_TENANT_ID` / `AZURE_CLIENT_ID` environment variables.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AzureAuthConfig {
+    ApiKey,
+    /// Entra ID app registration with a client secret
+    ClientSecret {
+        tenant_id: Option<String>,
+        client_id: Option<String>,
+        client_secret: Option<String>,
+        /// Environment variable holding the secret (default `AZURE_CLIENT_SECRET`)
+        client_secret_env: Option<String>,
     },
-    Kibana {
-        url: String,
-        auth_token: Option<String>,
-        #[serde(default = "default_index_pattern")]
-        index_pattern: String,
-        #[serde(default)]
-        verify_ssl: bool,
-        #[serde(default = "default_kibana_version")]
-        version: String,
+    /// AKS workload identity federated token
+    WorkloadIdentity {
+        tenant_id: Option<String>,
+        client_id: Option<String>,
+        /// Defaults to `AZURE_FEDERATED_TOKEN_FILE`
+        token_file: Option<String>,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProviderType {
+    Openai,
+    Anthropic,
+    Google,
+    Azure,
+    Vertex,
+    Ollama,
+    OpenaiCompatible,
+}
+
+impl ProviderType {
+    /// Parse a provider type from its config name (e.g. "openai-compatible")
+    pub fn from_name(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_string())).ok()
+    }
+
+    /// Environment variable consulted for the API key when a profile sets none
+    pub fn default_api_key_env(&self) -> Option<&'static str> {
+        match self {
+            ProviderType::Openai => Some("OPENAI_API_KEY"),
+            ProviderType::Anthropic => Some("ANTHROPIC_API_KEY"),
+            ProviderType::Google => Some("GOOGLE_API_KEY"),
+            ProviderType::Azure => Some("AZURE_OPENAI_API_KEY"),
+            ProviderType::Vertex | ProviderType::Ollama | ProviderType::OpenaiCompatible => None,
+        }
+    }
+}
+
+impl ProviderConfig {
+    /// A profile with only the type set, relying on environment variables
+    pub fn for_type(provider_type: ProviderType) -> Self {
+        ProviderConfig {
+            provider_type,
+            endpoint: None,
+            model: None,
+            models: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            api_key: None,
+            api_key_env: None,
+            api_key_command: None,
+            api_key_header: default_api_key_header(),
+            headers: HashMap::new(),
+            deployment: None,
+            api_version: None,
+            auth: None,
+            project: None,
+            location: None,
+            credentials_file: None,
+            fallback: Vec::new(),
+            max_retries: None,
+        }
+    }
+
+    /// Resolve the API key from, in order: the config value, the named
+    /// environment variable, the credential command, the provider's default
+    /// environment variable.
+    pub fn resolve_api_key(&self) -> Result<Option<String>> {
+        if let Some(key) = &self.api_key {
+            return Ok(Some(key.clone()));
+        }
+
+        if let Some(var) = &self.api_key_env {
+            return Ok(std::env::var(var).ok());
+        }
+
+        if let Some(command) = &self.api_key_command {
+            return run_credential_command(command).map(Some);
+        }
+
+        Ok(self
+            .provider_type
+            .default_api_key_env()
+            .and_then(|var| std::env::var(var).ok()))
+    }
+}
+
+fn run_credential_command(command: &str) -> Result<String> {
+    let output = if cfg!(windows) {
+        std::process::Command::new("cmd")
+            .args(["/C", command])
+            .output()
+    } else {
+        std::process::Command::new("sh")
+            .args(["-c", command])
+            .output()
+    }
+    .with_context(|| format!("Failed to run credential command '{}'", command))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "Credential command '{}' failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let key = String::from_utf8(output.stdout)
+        .context("Invalid UTF-8 in credential command output")?
+        .trim()
+        .to_string();
+
+    if key.is_empty() {
+        anyhow::bail!("Credential command '{}' printed nothing", command);
+    }
+
+    Ok(key)
+}
+
+fn default_api_key_header() -> String {
+    "Authorization".to_string()
+}
+
 fn default_index_pattern() -> String {
     "logs-*".to_string()
 }
@@ -122,6 +430,18 @@ impl Config {
         Ok(config_dir.join("zeteo").join("config.json"))
     }
 
+    /// Price for `model`: an exact entry, else the longest entry the model
+    /// name starts with (so "gpt-4o" covers "gpt-4o-2024-08-06").
+    pub fn price_for(&self, model: &str) -> Option<&ModelPrice> {
+        self.pricing.get(model).or_else(|| {
+            self.pricing
+                .iter()
+                .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map(|(_, price)| price)
+        })
+    }
+
     /// Returns all possible config paths for documentation/debugging
     #[allow(dead_code)]
     pub fn all_config_paths() -> Vec<PathBuf> {
@@ -160,44 +480,55 @@ impl Config {
                 command: "npx".to_string(),
                 args: vec!["-y".to_string(), "otel-mcp-server".to_string()],
                 env,
+                url: None,
+                headers: HashMap::new(),
+                bearer_token_env: None,
             },
         );
 
         backends.insert(
             "elasticsearch".to_string(),
-            LogBackend::Elasticsearch {
+            LogBackend::Elasticsearch(ElasticsearchConfig {
                 url: "http://localhost:9200".to_string(),
                 username: Some("elastic".to_string()),
                 password: Some("changeme".to_string()),
                 index_pattern: "logs-*".to_string(),
                 verify_ssl: false,
-            },
+            }),
         );
 
         backends.insert(
             "openobserve".to_string(),
-            LogBackend::OpenObserve {
+            LogBackend::OpenObserve(OpenObserveConfig {
                 url: "http://localhost:5080".to_string(),
                 username: "admin@example.com".to_string(),
                 password: "changeme".to_string(),
                 organization: "default".to_string(),
                 stream: "default".to_string(),
                 verify_ssl: false,
-            },
+            }),
         );
 
         backends.insert(
             "kibana".to_string(),
-            LogBackend::Kibana {
+            LogBackend::Kibana(KibanaConfig {
                 url: "http://localhost:5601".to_string(),
                 auth_token: None,
                 index_pattern: "logs-*".to_string(),
                 verify_ssl: false,
                 version: "7.10.2".to_string(),
-            },
+            }),
         );
 
-        Config { servers, backends }
+        Config {
+            servers,
+            backends,
+            providers: HashMap::new(),
+            default_provider: None,
+            pricing: HashMap::new(),
+            tool_concurrency: None,
+            sessions: None,
+        }
     }
 }
 
@@ -239,22 +570,127 @@ mod tests {
         assert!(deserialized.backends.contains_key("kibana"));
     }
 
+    #[test]
+    fn test_openai_compatible_provider_config() {
+        let json = r#"{
+            "servers": {},
+            "providers": {
+                "local-vllm": {
+                    "type": "openai-compatible",
+                    "base_url": "http://localhost:8000/v1",
+                    "model": "meta-llama/Llama-3.1-8B-Instruct",
+                    "api_key_header": "x-api-key",
+                    "headers": {"X-Team": "sre"}
+                }
+            }
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        let provider = config.providers.get("local-vllm").unwrap();
+
+        assert_eq!(provider.provider_type, ProviderType::OpenaiCompatible);
+        assert_eq!(
+            provider.endpoint.as_deref(),
+            Some("http://localhost:8000/v1")
+        );
+        assert_eq!(provider.api_key_header, "x-api-key");
+        assert_eq!(provider.headers.get("X-Team").unwrap(), "sre");
+        assert!(provider.resolve_api_key().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_provider_profile_settings() {
+        let json = r#"{
+            "servers": {},
+            "default_provider": "fast",
+            "providers": {
+                "fast": {
+                    "type": "azure",
+                    "endpoint": "https://example.openai.azure.com",
+                    "deployment": "gpt-4o-mini",
+                    "temperature": 0.2,
+                    "max_tokens": 1024,
+                    "api_key": "inline-key"
+                },
+                "local": {"type": "ollama", "model": "qwen2.5"},
+                "aks": {
+                    "type": "azure",
+                    "api_version": "2024-10-21",
+                    "auth": {"type": "workload_identity", "client_id": "app-id"}
+                }
+            }
+        }"#;
+
+        let config: Config = serde_json::from_str(json).unwrap();
+        assert_eq!(config.default_provider.as_deref(), Some("fast"));
+
+        let fast = config.providers.get("fast").unwrap();
+        assert_eq!(fast.provider_type, ProviderType::Azure);
+        assert_eq!(fast.temperature, Some(0.2));
+        assert_eq!(fast.max_tokens, Some(1024));
+        assert_eq!(
+            fast.resolve_api_key().unwrap().as_deref(),
+            Some("inline-key")
+        );
+
+        let local = config.providers.get("local").unwrap();
+        assert_eq!(local.provider_type, ProviderType::Ollama);
+        assert_eq!(local.model.as_deref(), Some("qwen2.5"));
+        assert_eq!(local.temperature, None);
+
+        let aks = config.providers.get("aks").unwrap();
+        assert_eq!(aks.api_version.as_deref(), Some("2024-10-21"));
+        assert!(matches!(
+            &aks.auth,
+            Some(AzureAuthConfig::WorkloadIdentity { client_id: Some(id), tenant_id: None, .. }) if id == "app-id"
+        ));
+    }
+
+    #[test]
+    fn test_provider_type_from_name() {
+        assert_eq!(
+            ProviderType::from_name("openai-compatible"),
+            Some(ProviderType::OpenaiCompatible)
+        );
+        assert_eq!(
+            ProviderType::from_name("vertex"),
+            Some(ProviderType::Vertex)
+        );
+        assert_eq!(ProviderType::from_name("unknown"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_api_key_from_command() {
+        let mut profile = ProviderConfig::for_type(ProviderType::Openai);
+        profile.api_key_command = Some("echo sk-from-command".to_string());
+        assert_eq!(
+            profile.resolve_api_key().unwrap().as_deref(),
+            Some("sk-from-command")
+        );
+
+        profile.api_key_command = Some("exit 3".to_string());
+        assert!(profile.resolve_api_key().is_err());
+    }
+
     #[test]
     fn test_backend_types() {
         let config = Config::default_config();
 
-        if let Some(LogBackend::Elasticsearch { url, .. }) = config.backends.get("elasticsearch") {
+        if let Some(LogBackend::Elasticsearch(ElasticsearchConfig { url, .. })) =
+            config.backends.get("elasticsearch")
+        {
             assert_eq!(url, "http://localhost:9200");
         } else {
             panic!("Elasticsearch backend not found or wrong type");
         }
 
-        if let Some(LogBackend::OpenObserve {
+        if let Some(LogBackend::OpenObserve(OpenObserveConfig {
             url,
             organization,
             stream,
             ..
-        }) = config.backends.get("openobserve")
+        })) = config.backends.get("openobserve")
         {
             assert_eq!(url, "http://localhost:5080");
             assert_eq!(organization, "default");
@@ -263,11 +699,37 @@ mod tests {
             panic!("OpenObserve backend not found or wrong type");
         }
 
-        if let Some(LogBackend::Kibana { url, version, .. }) = config.backends.get("kibana") {
+        if let Some(LogBackend::Kibana(KibanaConfig { url, version, .. })) =
+            config.backends.get("kibana")
+        {
             assert_eq!(url, "http://localhost:5601");
             assert_eq!(version, "7.10.2");
         } else {
             panic!("Kibana backend not found or wrong type");
         }
     }
+
+    #[test]
+    fn test_unknown_backend_types_are_kept() {
+        let backends: HashMap<String, LogBackend> = serde_json::from_str(
+            r#"{
+                "loki": {"type": "loki", "url": "http://loki:3100"},
+                "kibana": {"type": "kibana", "url": "http://kibana:5601"}
+            }"#,
+        )
+        .unwrap();
+
+        let loki = &backends["loki"];
+        assert!(matches!(loki, LogBackend::Custom(_)));
+        assert_eq!(loki.backend_type(), "loki");
+        assert_eq!(
+            serde_json::to_value(loki).unwrap(),
+            serde_json::json!({"type": "loki", "url": "http://loki:3100"})
+        );
+
+        let kibana = &backends["kibana"];
+        assert_eq!(kibana.backend_type(), "kibana");
+        assert_eq!(kibana.index_pattern(), Some("logs-*"));
+        assert_eq!(serde_json::to_value(kibana).unwrap()["type"], "kibana");
+    }
 }
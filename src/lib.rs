@@ -1,7 +1,12 @@
+pub mod agent;
 pub mod backends;
 pub mod config;
 pub mod logs;
 pub mod mcp;
 pub mod providers;
+pub mod report;
+pub mod retry;
 pub mod session;
+pub mod sse;
 pub mod tools;
+pub mod usage;
@@ -0,0 +1,539 @@
+//! Writing query results to files. Logs are fetched a page at a time, newest
+//! first, and each page is written out before the next one is requested, so
+//! exports of any size run in constant memory.
+
+use anyhow::{bail, Context, Result};
+use parquet::basic::Compression;
+use parquet::data_type::{ByteArray, ByteArrayType};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use super::tail::{entry_key, format_timestamp, parse_timestamp};
+use crate::backends::{LogBackendClient, LogEntry, LogQuery};
+
+pub const DEFAULT_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// One JSON object per line
+    Jsonl,
+    /// RFC 4180 CSV with a header row; labels are a JSON object
+    Csv,
+    /// Apache Parquet, one row group per page
+    Parquet,
+    /// A Markdown table
+    Markdown,
+}
+
+impl ExportFormat {
+    /// The format a file name's extension stands for
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "jsonl" | "ndjson" | "json" => Some(Self::Jsonl),
+            "csv" => Some(Self::Csv),
+            "parquet" | "pq" => Some(Self::Parquet),
+            "md" | "markdown" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// A destination that takes logs a page at a time
+pub trait ExportWriter: Send {
+    fn write(&mut self, entries: &[LogEntry]) -> Result<()>;
+    /// Write any trailer and flush
+    fn finish(self: Box<Self>) -> Result<()>;
+}
+
+/// A writer for `format` on top of `out`
+pub fn export_writer<W: Write + Send + 'static>(
+    format: ExportFormat,
+    out: W,
+) -> Result<Box<dyn ExportWriter>> {
+    Ok(match format {
+        ExportFormat::Jsonl => Box::new(JsonlWriter(out)),
+        ExportFormat::Csv => Box::new(CsvWriter::new(out)?),
+        ExportFormat::Parquet => Box::new(ParquetWriter::new(out)?),
+        ExportFormat::Markdown => Box::new(MarkdownWriter::new(out)?),
+    })
+}
+
+/// Write every log matching `query` to `writer`, newest first, stopping after
+/// `limit` logs if given. Returns how many were written.
+pub async fn export_logs(
+    client: &dyn LogBackendClient,
+    query: &LogQuery,
+    limit: Option<usize>,
+    page_size: usize,
+    writer: &mut dyn ExportWriter,
+) -> Result<usize> {
+    let mut request = query.clone();
+    let mut written = 0;
+    // Logs at the timestamp the last page ended on, which the next page
+    // starts on again
+    let mut boundary: HashSet<String> = HashSet::new();
+    let mut boundary_time = None;
+
+    loop {
+        let wanted = limit.map_or(page_size, |limit| (limit - written).min(page_size));
+        if wanted == 0 {
+            break;
+        }
+        request.max_results = page_size;
+        let page = client.query_logs(&request).await?;
+        let full = page.len() >= page_size;
+
+        let oldest = page
+            .iter()
+            .filter_map(|entry| parse_timestamp(&entry.timestamp))
+            .min();
+        let fresh: Vec<LogEntry> = page
+            .into_iter()
+            .filter(|entry| !boundary.contains(&entry_key(entry)))
+            .take(wanted)
+            .collect();
+        writer.write(&fresh)?;
+        written += fresh.len();
+
+        let Some(oldest) = oldest.filter(|_| full) else {
+            break;
+        };
+        if boundary_time.is_some_and(|time| oldest >= time) {
+            bail!(
+                "More than {} logs share the timestamp {}; use a larger page size",
+                page_size,
+                format_timestamp(oldest)
+            );
+        }
+        boundary = fresh
+            .iter()
+            .filter(|entry| parse_timestamp(&entry.timestamp) == Some(oldest))
+            .map(entry_key)
+            .collect();
+        boundary_time = Some(oldest);
+        // One microsecond on so that an exclusive end still covers `oldest`
+        request.end_time = Some(format_timestamp(oldest + chrono::Duration::microseconds(1)));
+    }
+
+    Ok(written)
+}
+
+/// Write `entries` to the file at `path` in `format`
+pub fn export_to_file(entries: &[LogEntry], path: &Path, format: ExportFormat) -> Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = export_writer(format, std::io::BufWriter::new(file))?;
+    writer.write(entries)?;
+    writer.finish()
+}
+
+struct JsonlWriter<W>(W);
+
+impl<W: Write + Send> ExportWriter for JsonlWriter<W> {
+    fn write(&mut self, entries: &[LogEntry]) -> Result<()> {
+        for entry in entries {
+            serde_json::to_writer(&mut self.0, entry)?;
+            self.0.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.0.flush()?;
+        Ok(())
+    }
+}
+
+const COLUMNS: [&str; 7] = [
+    "id",
+    "timestamp",
+    "level",
+    "service",
+    "trace_id",
+    "message",
+    "labels",
+];
+
+/// Labels as a JSON object with sorted keys, or empty when there are none
+fn labels_json(entry: &LogEntry) -> String {
+    if entry.labels.is_empty() {
+        return String::new();
+    }
+    let sorted: std::collections::BTreeMap<_, _> = entry.labels.iter().collect();
+    serde_json::to_string(&sorted).unwrap_or_default()
+}
+
+struct CsvWriter<W: Write>(csv::Writer<W>);
+
+impl<W: Write> CsvWriter<W> {
+    fn new(out: W) -> Result<Self> {
+        let mut writer = csv::WriterBuilder::new()
+            .terminator(csv::Terminator::CRLF)
+            .from_writer(out);
+        writer.write_record(COLUMNS)?;
+        Ok(Self(writer))
+    }
+}
+
+impl<W: Write + Send> ExportWriter for CsvWriter<W> {
+    fn write(&mut self, entries: &[LogEntry]) -> Result<()> {
+        for entry in entries {
+            self.0.write_record([
+                entry.id.as_deref().unwrap_or_default(),
+                &entry.timestamp,
+                &entry.level,
+                entry.service.as_deref().unwrap_or_default(),
+                entry.trace_id.as_deref().unwrap_or_default(),
+                &entry.message,
+                &labels_json(entry),
+            ])?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.0.flush()?;
+        Ok(())
+    }
+}
+
+struct MarkdownWriter<W>(W);
+
+impl<W: Write> MarkdownWriter<W> {
+    fn new(mut out: W) -> Result<Self> {
+        writeln!(
+            out,
+            "| Timestamp | Level | Service | Message | Trace ID | Labels |"
+        )?;
+        writeln!(out, "|---|---|---|---|---|---|")?;
+        Ok(Self(out))
+    }
+}
+
+/// Text that can sit in a Markdown table cell
+fn markdown_cell(text: &str) -> String {
+    text.trim()
+        .replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+impl<W: Write + Send> ExportWriter for MarkdownWriter<W> {
+    fn write(&mut self, entries: &[LogEntry]) -> Result<()> {
+        for entry in entries {
+            let mut labels: Vec<String> = entry
+                .labels
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            labels.sort();
+            writeln!(
+                self.0,
+                "| {} | {} | {} | {} | {} | {} |",
+                markdown_cell(&entry.timestamp),
+                markdown_cell(&entry.level),
+                markdown_cell(entry.service.as_deref().unwrap_or_default()),
+                markdown_cell(&entry.message),
+                markdown_cell(entry.trace_id.as_deref().unwrap_or_default()),
+                markdown_cell(&labels.join(", ")),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<()> {
+        self.0.flush()?;
+        Ok(())
+    }
+}
+
+const PARQUET_SCHEMA: &str = "
+    message log_entry {
+        OPTIONAL BYTE_ARRAY id (UTF8);
+        REQUIRED BYTE_ARRAY timestamp (UTF8);
+        REQUIRED BYTE_ARRAY level (UTF8);
+        OPTIONAL BYTE_ARRAY service (UTF8);
+        OPTIONAL BYTE_ARRAY trace_id (UTF8);
+        REQUIRED BYTE_ARRAY message (UTF8);
+        OPTIONAL BYTE_ARRAY labels (JSON);
+    }
+";
+
+struct ParquetWriter<W: Write + Send>(SerializedFileWriter<W>);
+
+impl<W: Write + Send> ParquetWriter<W> {
+    fn new(out: W) -> Result<Self> {
+        let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        Ok(Self(SerializedFileWriter::new(
+            out,
+            schema,
+            Arc::new(properties),
+        )?))
+    }
+}
+
+/// Values and definition levels of a column; levels only for optional ones
+fn parquet_column(
+    entries: &[LogEntry],
+    value: impl Fn(&LogEntry) -> Option<String>,
+) -> (Vec<ByteArray>, Vec<i16>) {
+    let mut values = Vec::new();
+    let mut levels = Vec::with_capacity(entries.len());
+    for entry in entries {
+        match value(entry) {
+            Some(value) => {
+                values.push(ByteArray::from(value.into_bytes()));
+                levels.push(1);
+            }
+            None => levels.push(0),
+        }
+    }
+    (values, levels)
+}
+
+impl<W: Write + Send> ExportWriter for ParquetWriter<W> {
+    fn write(&mut self, entries: &[LogEntry]) -> Result<()> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        // In schema order, with whether the column is optional
+        let columns = [
+            (parquet_column(entries, |e| e.id.clone()), true),
+            (
+                parquet_column(entries, |e| Some(e.timestamp.clone())),
+                false,
+            ),
+            (parquet_column(entries, |e| Some(e.level.clone())), false),
+            (parquet_column(entries, |e| e.service.clone()), true),
+            (parquet_column(entries, |e| e.trace_id.clone()), true),
+            (parquet_column(entries, |e| Some(e.message.clone())), false),
+            (
+                parquet_column(entries, |e| Some(labels_json(e)).filter(|l| !l.is_empty())),
+                true,
+            ),
+        ];
+
+        let mut row_group = self.0.next_row_group()?;
+        for ((values, levels), optional) in columns {
+            let mut column = row_group
+                .next_column()?
+                .context("Parquet schema has fewer columns than expected")?;
+            let levels = optional.then_some(levels.as_slice());
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&values, levels, None)?;
+            column.close()?;
+        }
+        row_group.close()?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.0.close()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Shared buffer, so the output can be read after the writer is finished
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn entry(id: &str, timestamp: &str, message: &str) -> LogEntry {
+        LogEntry {
+            id: Some(id.to_string()),
+            timestamp: timestamp.to_string(),
+            level: "ERROR".to_string(),
+            message: message.to_string(),
+            service: Some("checkout".to_string()),
+            trace_id: None,
+            labels: HashMap::from([("region".to_string(), "eu".to_string())]),
+        }
+    }
+
+    fn write(format: ExportFormat, entries: &[LogEntry]) -> Buffer {
+        let buffer = Buffer::default();
+        let mut writer = export_writer(format, buffer.clone()).unwrap();
+        writer.write(entries).unwrap();
+        writer.finish().unwrap();
+        buffer
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let logs = [entry(
+            "1",
+            "2024-05-01T10:00:00Z",
+            "payment failed, \"card\"\nretrying",
+        )];
+        assert_eq!(
+            write(ExportFormat::Csv, &logs).text(),
+            "id,timestamp,level,service,trace_id,message,labels\r\n\
+             1,2024-05-01T10:00:00Z,ERROR,checkout,,\"payment failed, \"\"card\"\"\nretrying\",\"{\"\"region\"\":\"\"eu\"\"}\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_and_jsonl() {
+        let logs = [entry("1", "2024-05-01T10:00:00Z", "a | b\nc")];
+        let markdown = write(ExportFormat::Markdown, &logs).text();
+        assert!(markdown.ends_with(
+            "| 2024-05-01T10:00:00Z | ERROR | checkout | a \\| b<br>c |  | region=eu |\n"
+        ));
+
+        let jsonl = write(ExportFormat::Jsonl, &logs).text();
+        let parsed: LogEntry = serde_json::from_str(jsonl.trim_end()).unwrap();
+        assert_eq!(parsed.message, "a | b\nc");
+        assert_eq!(jsonl.lines().count(), 1);
+    }
+
+    #[test]
+    fn test_parquet_round_trip() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let mut logs = vec![entry("1", "2024-05-01T10:00:00Z", "first")];
+        let mut second = entry("2", "2024-05-01T10:00:01Z", "second");
+        second.service = None;
+        second.labels.clear();
+        logs.push(second);
+
+        let path = std::env::temp_dir().join(format!(
+            "zeteo-export-{}.parquet",
+            uuid::Uuid::new_v4().simple()
+        ));
+        let file = std::fs::File::create(&path).unwrap();
+        let mut writer = export_writer(ExportFormat::Parquet, file).unwrap();
+        writer.write(&logs[..1]).unwrap();
+        writer.write(&logs[1..]).unwrap();
+        writer.finish().unwrap();
+
+        let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(reader.metadata().num_row_groups(), 2);
+        let rows: Vec<String> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap().to_string())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].contains("message: \"first\""), "{}", rows[0]);
+        assert!(rows[1].contains("service: null"), "{}", rows[1]);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Backend answering newest first within the time range, like the real ones
+    struct Memory(Vec<LogEntry>);
+
+    #[async_trait::async_trait]
+    impl LogBackendClient for Memory {
+        async fn query_logs(&self, query: &LogQuery) -> Result<Vec<LogEntry>> {
+            let end = query.end_time.as_deref().and_then(parse_timestamp);
+            let mut entries: Vec<LogEntry> = self
+                .0
+                .iter()
+                .filter(|e| end.is_none_or(|end| parse_timestamp(&e.timestamp).unwrap() <= end))
+                .cloned()
+                .collect();
+            entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+            entries.truncate(query.max_results);
+            Ok(entries)
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn backend_name(&self) -> &str {
+            "memory"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_pages_through_all_logs_once() {
+        // Two logs share each second, so pages end in the middle of one
+        let logs: Vec<LogEntry> = (0..9)
+            .map(|i| {
+                let timestamp = format!("2024-05-01T10:00:{:02}Z", i / 2);
+                entry(&i.to_string(), &timestamp, "message")
+            })
+            .collect();
+        let backend = Memory(logs);
+        let query = LogQuery {
+            query: "*".to_string(),
+            max_results: 0,
+            start_time: None,
+            end_time: None,
+            level: None,
+            service: None,
+            index_pattern: None,
+        };
+
+        let buffer = Buffer::default();
+        let mut writer = export_writer(ExportFormat::Jsonl, buffer.clone()).unwrap();
+        let written = export_logs(&backend, &query, None, 3, writer.as_mut())
+            .await
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(written, 9);
+        let mut ids: Vec<String> = buffer
+            .text()
+            .lines()
+            .map(|line| serde_json::from_str::<LogEntry>(line).unwrap().id.unwrap())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, (0..9).map(|i| i.to_string()).collect::<Vec<_>>());
+
+        let mut writer = export_writer(ExportFormat::Jsonl, Buffer::default()).unwrap();
+        let limited = export_logs(&backend, &query, Some(4), 3, writer.as_mut())
+            .await
+            .unwrap();
+        assert_eq!(limited, 4);
+
+        let mut writer = export_writer(ExportFormat::Jsonl, Buffer::default()).unwrap();
+        let err = export_logs(&backend, &query, None, 1, writer.as_mut()).await;
+        assert!(err.unwrap_err().to_string().contains("larger page size"));
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out/logs.PARQUET")),
+            Some(ExportFormat::Parquet)
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("logs.md")),
+            Some(ExportFormat::Markdown)
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("logs")), None);
+    }
+}
@@ -1,15 +1,20 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use colored::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
+use tokio::sync::mpsc;
 
-use crate::backends::{
-    elasticsearch::ElasticsearchClient, kibana::KibanaClient, openobserve::OpenObserveClient,
-    LogBackendClient, LogQuery,
-};
-use crate::config::{Config, LogBackend};
+use crate::backends::registry::{build_named_backend, find_backend};
+use crate::backends::{LogBackendClient, LogQuery};
+use crate::config::Config;
 use crate::mcp::McpClient;
+use export::ExportFormat;
+use tail::{TailEvent, TailOptions};
+
+pub mod export;
+pub mod tail;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntry {
@@ -22,6 +27,33 @@ pub struct LogEntry {
     pub labels: HashMap<String, String>,
 }
 
+impl From<crate::backends::LogEntry> for LogEntry {
+    fn from(log: crate::backends::LogEntry) -> Self {
+        LogEntry {
+            timestamp: log.timestamp,
+            level: log.level,
+            message: log.message,
+            service: log.service,
+            trace_id: log.trace_id,
+            labels: log.labels,
+        }
+    }
+}
+
+impl From<LogEntry> for crate::backends::LogEntry {
+    fn from(log: LogEntry) -> Self {
+        crate::backends::LogEntry {
+            id: None,
+            timestamp: log.timestamp,
+            level: log.level,
+            message: log.message,
+            service: log.service,
+            trace_id: log.trace_id,
+            labels: log.labels,
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Default)]
 pub struct LogFilter {
@@ -41,6 +73,25 @@ pub struct LogAggregation {
     pub time_range: Option<(String, String)>,
 }
 
+/// The log backend for `zeteo tail` and `zeteo export`: the requested one or
+/// the first configured
+pub fn create_log_backend(backend: Option<String>) -> Result<Arc<dyn LogBackendClient>> {
+    let config = Config::load().ok();
+    let Some(config) = config.as_ref() else {
+        anyhow::bail!("No log backend configured. Add one to config.json.");
+    };
+    match backend {
+        Some(name) => build_named_backend(config, &name.to_lowercase()),
+        None => find_backend(config)
+            .map(|(client, _)| client)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No log backend configured. Add one to config.json or pass --backend."
+                )
+            }),
+    }
+}
+
 #[allow(dead_code)]
 pub struct LogExplorer {
     mcp_server: Option<String>,
@@ -58,57 +109,18 @@ impl LogExplorer {
         }
     }
 
+    /// Explore logs through an already built backend client
+    pub fn with_client(client: Arc<dyn LogBackendClient>) -> Self {
+        LogExplorer {
+            mcp_server: None,
+            mcp_client: None,
+            backend_client: Some(client),
+        }
+    }
+
     pub fn with_backend(backend_name: String) -> Result<Self> {
         let config = Config::load()?;
-
-        let backend = config
-            .backends
-            .get(&backend_name)
-            .ok_or_else(|| anyhow::anyhow!("Backend '{}' not found in config", backend_name))?;
-
-        let client: Arc<dyn LogBackendClient> = match backend {
-            LogBackend::Elasticsearch {
-                url,
-                username,
-                password,
-                index_pattern,
-                verify_ssl,
-            } => Arc::new(ElasticsearchClient::new(
-                url.clone(),
-                username.clone(),
-                password.clone(),
-                index_pattern.clone(),
-                *verify_ssl,
-            )?),
-            LogBackend::OpenObserve {
-                url,
-                username,
-                password,
-                organization,
-                stream,
-                verify_ssl,
-            } => Arc::new(OpenObserveClient::new(
-                url.clone(),
-                username.clone(),
-                password.clone(),
-                organization.clone(),
-                stream.clone(),
-                *verify_ssl,
-            )?),
-            LogBackend::Kibana {
-                url,
-                auth_token,
-                index_pattern,
-                verify_ssl,
-                version,
-            } => Arc::new(KibanaClient::new(
-                url.clone(),
-                auth_token.clone(),
-                index_pattern.clone(),
-                version.clone(),
-                *verify_ssl,
-            )?),
-        };
+        let client = build_named_backend(&config, &backend_name)?;
 
         Ok(LogExplorer {
             mcp_server: None,
@@ -118,19 +130,14 @@ impl LogExplorer {
     }
 
     /// Initialize the MCP client connection
-    pub fn with_mcp_client(mut self) -> Result<Self> {
+    pub async fn with_mcp_client(mut self) -> Result<Self> {
         let config = Config::load()?;
 
         if let Some(mcp_server) = &self.mcp_server {
             if let Some(server_config) = config.servers.get(mcp_server) {
-                let mut client = McpClient::new(
-                    &server_config.command,
-                    &server_config.args,
-                    &server_config.env,
-                    mcp_server.clone(),
-                )?;
+                let client = McpClient::connect(mcp_server, server_config)?;
 
-                client.initialize()?;
+                client.initialize().await?;
 
                 self.mcp_client = Some(client);
             }
@@ -152,21 +159,11 @@ impl LogExplorer {
             };
 
             let backend_logs = backend_client.query_logs(&log_query).await?;
-            return Ok(backend_logs
-                .into_iter()
-                .map(|log| LogEntry {
-                    timestamp: log.timestamp,
-                    level: log.level,
-                    message: log.message,
-                    service: log.service,
-                    trace_id: log.trace_id,
-                    labels: log.labels,
-                })
-                .collect());
+            return Ok(backend_logs.into_iter().map(LogEntry::from).collect());
         }
 
         if let Some(client) = &self.mcp_client {
-            match client.query_logs(query, max_results) {
+            match client.query_logs(query, max_results).await {
                 Ok(result) => {
                     if let Some(logs_array) = result.get("logs").and_then(|v| v.as_array()) {
                         let logs: Vec<LogEntry> = logs_array
@@ -211,17 +208,7 @@ impl LogExplorer {
             };
 
             let backend_logs = backend_client.query_logs(&log_query).await?;
-            let mut logs: Vec<LogEntry> = backend_logs
-                .into_iter()
-                .map(|log| LogEntry {
-                    timestamp: log.timestamp,
-                    level: log.level,
-                    message: log.message,
-                    service: log.service,
-                    trace_id: log.trace_id,
-                    labels: log.labels,
-                })
-                .collect();
+            let mut logs: Vec<LogEntry> = backend_logs.into_iter().map(LogEntry::from).collect();
 
             if let Some(contains) = &filter.contains {
                 logs.retain(|log| {
@@ -265,35 +252,63 @@ impl LogExplorer {
         Ok(logs)
     }
 
-    pub async fn stream_logs<F>(&self, query: &str, callback: F) -> Result<()>
+    /// Print new logs matching `query` and `filter` as they arrive, until
+    /// `callback` returns false for one
+    pub async fn stream_logs<F>(
+        &self,
+        query: &str,
+        filter: &LogFilter,
+        options: TailOptions,
+        callback: F,
+    ) -> Result<()>
     where
         F: Fn(&LogEntry) -> bool,
     {
-        println!("{}", "Starting log stream...".cyan());
-        println!("Query: {}", query.green());
-        println!("Press Ctrl+C to stop streaming");
-
-        let mut _offset = 0;
-        let batch_size = 10;
+        let backend_client = self
+            .backend_client
+            .clone()
+            .context("Streaming logs needs a log backend")?;
+
+        eprintln!("{}", "Starting log stream...".cyan());
+        eprintln!("Query: {}", query.green());
+        eprintln!("Press Ctrl+C to stop streaming");
+
+        let log_query = LogQuery {
+            query: query.to_string(),
+            max_results: options.batch_size,
+            start_time: filter.start_time.clone(),
+            end_time: None,
+            level: filter.level.clone(),
+            service: filter.service.clone(),
+            index_pattern: None,
+        };
+        let contains = filter.contains.as_ref().map(|c| c.to_lowercase());
 
-        loop {
-            let logs = self.search_logs(query, batch_size).await?;
+        let (events_tx, mut events_rx) = mpsc::channel(256);
+        let tail = tokio::spawn(tail::tail(backend_client, log_query, options, events_tx));
 
-            if logs.is_empty() {
-                tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        while let Some(event) = events_rx.recv().await {
+            let log = match event {
+                TailEvent::Entry(log) => LogEntry::from(log),
+                TailEvent::Warning(warning) => {
+                    eprintln!("{}", warning.yellow());
+                    continue;
+                }
+            };
+            if contains
+                .as_ref()
+                .is_some_and(|c| !log.message.to_lowercase().contains(c))
+            {
                 continue;
             }
-
-            for log in &logs {
-                if !callback(log) {
-                    return Ok(());
-                }
-                self.display_single_log(log);
+            if !callback(&log) {
+                break;
             }
-
-            _offset += logs.len();
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+            self.display_single_log(&log);
         }
+
+        tail.abort();
+        Ok(())
     }
 
     pub fn aggregate_logs(&self, logs: &[LogEntry]) -> LogAggregation {
@@ -345,24 +360,9 @@ impl LogExplorer {
     }
 
     pub fn export_logs_csv(&self, logs: &[LogEntry], filename: &str) -> Result<()> {
-        use std::fs::File;
-        use std::io::Write;
-
-        let mut file = File::create(filename)?;
-
-        writeln!(file, "timestamp,level,message,service,trace_id")?;
-
-        for log in logs {
-            let service = log.service.as_deref().unwrap_or("");
-            let trace_id = log.trace_id.as_deref().unwrap_or("");
-            let message = log.message.replace(",", ";").replace("\n", " ");
-
-            writeln!(
-                file,
-                "{},{},{},{},{}",
-                log.timestamp, log.level, message, service, trace_id
-            )?;
-        }
+        let entries: Vec<crate::backends::LogEntry> =
+            logs.iter().cloned().map(Into::into).collect();
+        export::export_to_file(&entries, Path::new(filename), ExportFormat::Csv)?;
 
         println!(
             "{}",
@@ -0,0 +1,449 @@
+//! Live tail of a log backend. New entries are fetched from the newest one
+//! seen so far, ordered by timestamp and then document ID, and each entry is
+//! delivered once even when polls overlap or entries arrive late.
+
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::backends::{LogBackendClient, LogEntry, LogQuery};
+
+/// Requests made to catch up after a poll returns a full batch, before the
+/// remaining entries are reported as skipped
+const MAX_CATCH_UP_PAGES: usize = 5;
+
+#[derive(Debug, Clone)]
+pub struct TailOptions {
+    /// Entries from before the tail started to show first
+    pub backlog: usize,
+    /// Wait between polls for backends without a native tail API
+    pub interval: Duration,
+    /// Most entries fetched per request
+    pub batch_size: usize,
+    /// How far behind the newest entry to keep looking for late arrivals
+    pub overlap: Duration,
+}
+
+impl Default for TailOptions {
+    fn default() -> Self {
+        Self {
+            backlog: 10,
+            interval: Duration::from_secs(2),
+            batch_size: 200,
+            overlap: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TailEvent {
+    Entry(LogEntry),
+    /// Something went wrong; the tail carries on
+    Warning(String),
+}
+
+/// Where a tail has got to and which entries near that point were delivered
+pub struct TailCursor {
+    newest: Option<DateTime<Utc>>,
+    /// Keys of delivered entries within `overlap` of `newest`
+    seen: HashMap<String, DateTime<Utc>>,
+    overlap: chrono::Duration,
+}
+
+impl TailCursor {
+    pub fn new(overlap: Duration) -> Self {
+        Self {
+            newest: None,
+            seen: HashMap::new(),
+            overlap: chrono::Duration::from_std(overlap).unwrap_or(chrono::Duration::zero()),
+        }
+    }
+
+    /// Start of the window the next poll has to cover
+    pub fn start_time(&self) -> Option<String> {
+        self.newest
+            .map(|newest| format_timestamp(newest - self.overlap))
+    }
+
+    /// Move the cursor to `time` unless it is already past it
+    pub fn advance_to(&mut self, time: DateTime<Utc>) {
+        if self.newest.is_none_or(|newest| time > newest) {
+            self.newest = Some(time);
+        }
+    }
+
+    /// Entries not delivered before, oldest first and by document ID for the
+    /// same timestamp. Entries older than the overlap window are dropped.
+    pub fn accept(&mut self, entries: Vec<LogEntry>) -> Vec<LogEntry> {
+        let floor = self.newest.map(|newest| newest - self.overlap);
+        let mut fresh: Vec<(DateTime<Utc>, LogEntry)> = Vec::new();
+        for entry in entries {
+            let time = parse_timestamp(&entry.timestamp)
+                .or(self.newest)
+                .unwrap_or_else(Utc::now);
+            if floor.is_some_and(|floor| time < floor) {
+                continue;
+            }
+            if self.seen.insert(entry_key(&entry), time).is_none() {
+                fresh.push((time, entry));
+            }
+        }
+        fresh.sort_by(|(a_time, a), (b_time, b)| a_time.cmp(b_time).then_with(|| a.id.cmp(&b.id)));
+
+        if let Some((time, _)) = fresh.last() {
+            self.advance_to(*time);
+        }
+        if let Some(floor) = self.newest.map(|newest| newest - self.overlap) {
+            self.seen.retain(|_, time| *time >= floor);
+        }
+        fresh.into_iter().map(|(_, entry)| entry).collect()
+    }
+}
+
+/// Identity of an entry for de-duplication: its document ID, or its contents
+/// for backends without IDs
+pub(super) fn entry_key(entry: &LogEntry) -> String {
+    match &entry.id {
+        Some(id) => id.clone(),
+        None => format!(
+            "{}\u{1f}{}\u{1f}{}",
+            entry.timestamp,
+            entry.service.as_deref().unwrap_or_default(),
+            entry.message
+        ),
+    }
+}
+
+/// Parse a backend timestamp: RFC 3339, ISO 8601 without an offset (taken as
+/// UTC), or Unix epoch seconds, milliseconds or microseconds
+pub fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    let timestamp = timestamp.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(timestamp) {
+        return Some(time.with_timezone(&Utc));
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(time.and_utc());
+    }
+    let epoch: i64 = timestamp.parse().ok()?;
+    match timestamp.trim_start_matches('-').len() {
+        16.. => DateTime::from_timestamp_micros(epoch),
+        13.. => DateTime::from_timestamp_millis(epoch),
+        _ => DateTime::from_timestamp(epoch, 0),
+    }
+}
+
+pub(super) fn format_timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Tail `query` on `client`, sending what arrives to `sink` until it is closed.
+/// Starts with the last `options.backlog` entries, then uses the backend's
+/// native tail API if it has one and polls otherwise.
+pub async fn tail(
+    client: Arc<dyn LogBackendClient>,
+    query: LogQuery,
+    options: TailOptions,
+    sink: mpsc::Sender<TailEvent>,
+) {
+    let mut cursor = TailCursor::new(options.overlap);
+
+    let mut request = query.clone();
+    request.max_results = options.backlog.max(1);
+    match client.query_logs(&request).await {
+        Ok(entries) => {
+            let entries = cursor.accept(entries);
+            let skip = entries.len().saturating_sub(options.backlog);
+            for entry in entries.into_iter().skip(skip) {
+                if sink.send(TailEvent::Entry(entry)).await.is_err() {
+                    return;
+                }
+            }
+        }
+        Err(e) => {
+            let warning = format!("Failed to fetch recent logs: {:#}", e);
+            if sink.send(TailEvent::Warning(warning)).await.is_err() {
+                return;
+            }
+        }
+    }
+    if cursor.newest.is_none() {
+        // Nothing to go on yet: only what arrives from now on is new
+        cursor.advance_to(Utc::now());
+    }
+
+    if client.supports_tail() && !native_tail(&client, &query, &mut cursor, &sink).await {
+        return;
+    }
+
+    loop {
+        let events = match poll(&client, &query, &options, &mut cursor).await {
+            Ok((entries, complete)) => {
+                let mut events: Vec<TailEvent> =
+                    entries.into_iter().map(TailEvent::Entry).collect();
+                if !complete {
+                    events.push(TailEvent::Warning(
+                        "Logs are arriving faster than they can be fetched; some were skipped"
+                            .to_string(),
+                    ));
+                }
+                events
+            }
+            Err(e) => vec![TailEvent::Warning(format!("Failed to fetch logs: {:#}", e))],
+        };
+        for event in events {
+            if sink.send(event).await.is_err() {
+                return;
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(options.interval) => {}
+            _ = sink.closed() => return,
+        }
+    }
+}
+
+/// Forward the backend's own tail through the cursor. Returns whether the
+/// tail should carry on by polling.
+async fn native_tail(
+    client: &Arc<dyn LogBackendClient>,
+    query: &LogQuery,
+    cursor: &mut TailCursor,
+    sink: &mpsc::Sender<TailEvent>,
+) -> bool {
+    let mut request = query.clone();
+    request.start_time = cursor.start_time();
+    let (entries_tx, mut entries_rx) = mpsc::channel(256);
+
+    let forward = async {
+        while let Some(entry) = entries_rx.recv().await {
+            for entry in cursor.accept(vec![entry]) {
+                if sink.send(TailEvent::Entry(entry)).await.is_err() {
+                    return;
+                }
+            }
+        }
+        // The backend stopped sending; wait for it to say why
+        std::future::pending::<()>().await
+    };
+
+    let ended = tokio::select! {
+        ended = client.tail(&request, entries_tx) => ended,
+        _ = forward => return false,
+    };
+    let warning = match ended {
+        Ok(()) => "Live tail ended; polling instead".to_string(),
+        Err(e) => format!("Live tail failed, polling instead: {:#}", e),
+    };
+    sink.send(TailEvent::Warning(warning)).await.is_ok()
+}
+
+/// Fetch everything since the cursor, paging back through full batches.
+/// Also returns whether the whole window was covered.
+async fn poll(
+    client: &Arc<dyn LogBackendClient>,
+    query: &LogQuery,
+    options: &TailOptions,
+    cursor: &mut TailCursor,
+) -> anyhow::Result<(Vec<LogEntry>, bool)> {
+    let mut request = query.clone();
+    request.start_time = cursor.start_time();
+    request.max_results = options.batch_size;
+
+    let mut entries = Vec::new();
+    let mut complete = true;
+    for page in 0..MAX_CATCH_UP_PAGES {
+        let batch = client.query_logs(&request).await?;
+        // Backends return the newest entries first, so a full batch may have
+        // left older ones out
+        let full = batch.len() >= options.batch_size;
+        let oldest = batch
+            .iter()
+            .filter_map(|entry| parse_timestamp(&entry.timestamp))
+            .min();
+        entries.extend(batch);
+
+        let previous_end = request.end_time.as_deref().and_then(parse_timestamp);
+        match oldest {
+            Some(oldest) if full && previous_end.is_none_or(|end| oldest < end) => {
+                // One microsecond on so that an exclusive end still covers `oldest`
+                request.end_time =
+                    Some(format_timestamp(oldest + chrono::Duration::microseconds(1)));
+                complete = page + 1 < MAX_CATCH_UP_PAGES;
+            }
+            _ => break,
+        }
+    }
+
+    Ok((cursor.accept(entries), complete))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: Option<&str>, timestamp: &str, message: &str) -> LogEntry {
+        LogEntry {
+            id: id.map(str::to_string),
+            timestamp: timestamp.to_string(),
+            level: "INFO".to_string(),
+            message: message.to_string(),
+            service: None,
+            trace_id: None,
+            labels: HashMap::new(),
+        }
+    }
+
+    fn ids(entries: &[LogEntry]) -> Vec<&str> {
+        entries
+            .iter()
+            .map(|e| e.id.as_deref().unwrap_or(&e.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_cursor_orders_and_deduplicates() {
+        let mut cursor = TailCursor::new(Duration::from_secs(5));
+        let first = cursor.accept(vec![
+            entry(Some("b"), "2024-05-01T10:00:02Z", "two"),
+            entry(Some("c"), "2024-05-01T10:00:01Z", "one"),
+            entry(Some("a"), "2024-05-01T10:00:02Z", "two again"),
+        ]);
+        assert_eq!(ids(&first), vec!["c", "a", "b"]);
+        assert_eq!(
+            cursor.start_time().as_deref(),
+            Some("2024-05-01T09:59:57.000000Z")
+        );
+
+        // The next poll overlaps the last one and brings a late arrival
+        let second = cursor.accept(vec![
+            entry(Some("d"), "2024-05-01T10:00:03Z", "three"),
+            entry(Some("b"), "2024-05-01T10:00:02Z", "two"),
+            entry(Some("late"), "2024-05-01T10:00:00Z", "late"),
+            entry(Some("old"), "2024-05-01T09:00:00Z", "too old"),
+        ]);
+        assert_eq!(ids(&second), vec!["late", "d"]);
+        assert!(cursor
+            .accept(vec![entry(Some("d"), "2024-05-01T10:00:03Z", "three")])
+            .is_empty());
+    }
+
+    #[test]
+    fn test_cursor_deduplicates_entries_without_ids() {
+        let mut cursor = TailCursor::new(Duration::from_secs(5));
+        let logs = vec![
+            entry(None, "2024-05-01T10:00:00Z", "same"),
+            entry(None, "2024-05-01T10:00:00Z", "same"),
+            entry(None, "2024-05-01T10:00:00Z", "different"),
+        ];
+        assert_eq!(cursor.accept(logs.clone()).len(), 2);
+        assert!(cursor.accept(logs).is_empty());
+    }
+
+    /// Backend over a list of entries, answering like the real ones: newest
+    /// first, within the time range, at most `max_results`
+    struct Memory(std::sync::Mutex<Vec<LogEntry>>);
+
+    #[async_trait::async_trait]
+    impl LogBackendClient for Memory {
+        async fn query_logs(&self, query: &LogQuery) -> anyhow::Result<Vec<LogEntry>> {
+            let start = query.start_time.as_deref().and_then(parse_timestamp);
+            let end = query.end_time.as_deref().and_then(parse_timestamp);
+            let mut entries: Vec<LogEntry> = self
+                .0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| {
+                    let time = parse_timestamp(&e.timestamp).unwrap();
+                    start.is_none_or(|s| time >= s) && end.is_none_or(|e| time <= e)
+                })
+                .cloned()
+                .collect();
+            entries.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+            entries.truncate(query.max_results);
+            Ok(entries)
+        }
+
+        async fn health_check(&self) -> anyhow::Result<bool> {
+            Ok(true)
+        }
+
+        fn backend_name(&self) -> &str {
+            "memory"
+        }
+    }
+
+    async fn next_id(events: &mut mpsc::Receiver<TailEvent>) -> String {
+        match tokio::time::timeout(Duration::from_secs(5), events.recv()).await {
+            Ok(Some(TailEvent::Entry(entry))) => entry.id.unwrap(),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tail_delivers_backlog_then_new_entries_once() {
+        let at = |second: u32| format!("2024-05-01T10:00:{:02}Z", second);
+        let backend = Arc::new(Memory(std::sync::Mutex::new(
+            (0..3)
+                .map(|i| entry(Some(&format!("old{}", i)), &at(i), "old"))
+                .collect(),
+        )));
+        let query = LogQuery {
+            query: "*".to_string(),
+            max_results: 0,
+            start_time: None,
+            end_time: None,
+            level: None,
+            service: None,
+            index_pattern: None,
+        };
+        let options = TailOptions {
+            backlog: 2,
+            interval: Duration::from_millis(10),
+            batch_size: 3,
+            overlap: Duration::from_secs(1),
+        };
+        let (events_tx, mut events_rx) = mpsc::channel(16);
+        let task = tokio::spawn(tail(backend.clone(), query, options, events_tx));
+
+        assert_eq!(next_id(&mut events_rx).await, "old1");
+        assert_eq!(next_id(&mut events_rx).await, "old2");
+
+        // More than a batch arrives between polls
+        backend
+            .0
+            .lock()
+            .unwrap()
+            .extend((3..8).map(|i| entry(Some(&format!("new{}", i)), &at(i), "new")));
+        let mut received = Vec::new();
+        for _ in 3..8 {
+            received.push(next_id(&mut events_rx).await);
+        }
+        assert_eq!(received, vec!["new3", "new4", "new5", "new6", "new7"]);
+
+        // Nothing is delivered twice
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(events_rx.try_recv().is_err());
+        task.abort();
+    }
+
+    #[test]
+    fn test_parse_timestamp_formats() {
+        let expected = DateTime::parse_from_rfc3339("2024-05-01T10:00:00.5Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        for timestamp in [
+            "2024-05-01T10:00:00.5Z",
+            "2024-05-01T12:00:00.5+02:00",
+            "2024-05-01T10:00:00.500",
+            "1714557600500",
+            "1714557600500000",
+        ] {
+            assert_eq!(parse_timestamp(timestamp), Some(expected), "{}", timestamp);
+        }
+        assert!(parse_timestamp("yesterday").is_none());
+    }
+}
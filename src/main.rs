@@ -1,26 +1,43 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
-use std::io;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::time::Duration;
 
+mod agent;
 mod backends;
 mod cache;
 mod config;
 mod logs;
 mod mcp;
 mod providers;
+mod report;
 mod retry;
 mod session;
+mod sse;
 mod tools;
 mod tui;
+mod usage;
+
+use config::SessionStorageKind;
+use logs::export::{self, ExportFormat};
+
+/// Shortest wait between `zeteo tail` polls; less would hammer the backend
+const MIN_TAIL_INTERVAL_SECS: f64 = 0.5;
 
 #[derive(Parser)]
 #[command(name = "zeteo")]
 #[command(author, version, about = "AI assistant with log analysis", long_about = None)]
 struct Cli {
+    /// AI provider type or config.json provider profile
     #[arg(short, long, global = true)]
     provider: Option<String>,
 
+    /// Model to use, overriding the provider profile
+    #[arg(short, long, global = true)]
+    model: Option<String>,
+
     /// Backend to use for log queries (kibana, openobserve)
     #[arg(short, long, global = true)]
     backend: Option<String>,
@@ -31,6 +48,87 @@ struct Cli {
 
 #[derive(Subcommand)]
 enum Commands {
+    /// Answer one question without the TUI and print the result
+    Ask {
+        /// The question; read from stdin when omitted
+        question: Vec<String>,
+
+        /// Print a JSON incident report (summary, suspected root cause,
+        /// affected services, evidence log IDs, confidence)
+        #[arg(long)]
+        json: bool,
+    },
+    /// Follow new logs as they arrive
+    Tail {
+        /// Search query; all logs when omitted
+        query: Vec<String>,
+
+        /// Only logs of this level
+        #[arg(short, long)]
+        level: Option<String>,
+
+        /// Only logs from this service
+        #[arg(long)]
+        service: Option<String>,
+
+        /// Only logs whose message contains this text. Checked here rather
+        /// than by the backend, so fewer than --lines recent logs may show.
+        #[arg(long)]
+        contains: Option<String>,
+
+        /// Number of recent logs to show first
+        #[arg(short = 'n', long, default_value_t = 10)]
+        lines: usize,
+
+        /// Seconds between polls, for backends without a native tail API
+        /// (at least 0.5)
+        #[arg(long, default_value_t = 2.0)]
+        interval: f64,
+    },
+    /// Write the logs matching a query to a file
+    Export {
+        /// Search query; all logs when omitted
+        query: Vec<String>,
+
+        /// File to write; standard output when omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Output format; taken from the file extension by default, else JSONL
+        #[arg(short, long, value_enum)]
+        format: Option<ExportFormat>,
+
+        /// Only logs of this level
+        #[arg(short, long)]
+        level: Option<String>,
+
+        /// Only logs from this service
+        #[arg(long)]
+        service: Option<String>,
+
+        /// Only logs at or after this time (RFC 3339)
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only logs at or before this time (RFC 3339)
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Stop after this many logs
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Logs fetched per request
+        #[arg(long, default_value_t = export::DEFAULT_PAGE_SIZE)]
+        page_size: usize,
+    },
+    /// Manage saved conversations
+    Sessions {
+        #[command(subcommand)]
+        command: SessionsCommand,
+    },
+    /// Serve zeteo's log tools to other agents over MCP on stdio
+    McpServe,
     Completions {
         #[arg(value_enum)]
         shell: Shell,
@@ -38,6 +136,26 @@ enum Commands {
     Version,
 }
 
+#[derive(Subcommand)]
+enum SessionsCommand {
+    /// Copy every saved conversation from one storage to another
+    Migrate {
+        /// Storage to read from
+        #[arg(long, value_enum)]
+        from: SessionStorageKind,
+
+        /// Storage to write to
+        #[arg(long, value_enum)]
+        to: SessionStorageKind,
+    },
+    /// Rewrite conversations saved by older versions in the current format
+    Upgrade {
+        /// Storage to upgrade; defaults to the configured one
+        #[arg(long, value_enum)]
+        storage: Option<SessionStorageKind>,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let _ = dotenv::dotenv();
@@ -49,11 +167,162 @@ async fn main() -> Result<()> {
             let mut cmd = Cli::command();
             generate(shell, &mut cmd, "zeteo", &mut io::stdout());
         }
+        Some(Commands::Ask { question, json }) => {
+            let question = if question.is_empty() {
+                let mut input = String::new();
+                io::stdin().read_to_string(&mut input)?;
+                input
+            } else {
+                question.join(" ")
+            };
+            if question.trim().is_empty() {
+                anyhow::bail!("No question given");
+            }
+
+            let agent = tui::create_agent(cli.provider, cli.model, cli.backend).await?;
+            if json {
+                let report = agent.ask_json(&question).await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", agent.ask(&question).await?);
+            }
+        }
+        Some(Commands::Tail {
+            query,
+            level,
+            service,
+            contains,
+            lines,
+            interval,
+        }) => {
+            if interval.is_nan() || interval < MIN_TAIL_INTERVAL_SECS {
+                anyhow::bail!(
+                    "--interval must be at least {} seconds",
+                    MIN_TAIL_INTERVAL_SECS
+                );
+            }
+            let client = logs::create_log_backend(cli.backend)?;
+            let filter = logs::LogFilter {
+                level,
+                service,
+                contains,
+                ..Default::default()
+            };
+            let options = logs::tail::TailOptions {
+                backlog: lines,
+                interval: Duration::try_from_secs_f64(interval)
+                    .map_err(|_| anyhow::anyhow!("Invalid --interval {}", interval))?,
+                ..Default::default()
+            };
+            let query = if query.is_empty() {
+                "*".to_string()
+            } else {
+                query.join(" ")
+            };
+            logs::LogExplorer::with_client(client)
+                .stream_logs(&query, &filter, options, |_| true)
+                .await?;
+        }
+        Some(Commands::Export {
+            query,
+            output,
+            format,
+            level,
+            service,
+            since,
+            until,
+            limit,
+            page_size,
+        }) => {
+            if page_size == 0 {
+                anyhow::bail!("--page-size must be at least 1");
+            }
+            let client = logs::create_log_backend(cli.backend)?;
+            let format = format
+                .or_else(|| output.as_deref().and_then(ExportFormat::from_path))
+                .unwrap_or(ExportFormat::Jsonl);
+            let query = backends::LogQuery {
+                query: if query.is_empty() {
+                    "*".to_string()
+                } else {
+                    query.join(" ")
+                },
+                max_results: page_size,
+                start_time: since,
+                end_time: until,
+                level,
+                service,
+                index_pattern: None,
+            };
+
+            let mut writer = match &output {
+                Some(path) => {
+                    let file = std::fs::File::create(path)
+                        .with_context(|| format!("Failed to create {}", path.display()))?;
+                    export::export_writer(format, io::BufWriter::new(file))?
+                }
+                None => export::export_writer(format, io::BufWriter::new(io::stdout()))?,
+            };
+            let written =
+                export::export_logs(client.as_ref(), &query, limit, page_size, writer.as_mut())
+                    .await?;
+            writer.finish()?;
+            if let Some(path) = &output {
+                eprintln!("Exported {} logs to {}", written, path.display());
+            }
+        }
+        Some(Commands::Sessions {
+            command: SessionsCommand::Migrate { from, to },
+        }) => {
+            if from == to {
+                anyhow::bail!("--from and --to must be different storages");
+            }
+            let config = config::Config::load().ok();
+            let settings = config.as_ref().and_then(|c| c.sessions.as_ref());
+            let (Some(source), Some(target)) = (
+                session::open_storage(from, settings).await?,
+                session::open_storage(to, settings).await?,
+            ) else {
+                anyhow::bail!("Can't migrate to or from \"none\"");
+            };
+            let copied = session::migrate(source.as_ref(), target.as_ref()).await?;
+            eprintln!(
+                "Copied {} sessions from {} to {} storage",
+                copied,
+                source.name(),
+                target.name()
+            );
+        }
+        Some(Commands::Sessions {
+            command: SessionsCommand::Upgrade { storage },
+        }) => {
+            let config = config::Config::load().ok();
+            let settings = config.as_ref().and_then(|c| c.sessions.as_ref());
+            let storage = match storage {
+                Some(kind) => session::open_storage(kind, settings).await?,
+                None => session::open_configured_storage(settings).await?,
+            };
+            let Some(storage) = storage else {
+                anyhow::bail!("Session saving is off; pass --storage file or --storage redis");
+            };
+            let upgraded = session::upgrade(storage.as_ref()).await?;
+            eprintln!(
+                "Upgraded {} sessions in {} storage to format {}",
+                upgraded,
+                storage.name(),
+                session::SESSION_FORMAT_VERSION
+            );
+        }
+        Some(Commands::McpServe) => {
+            // stdout carries the protocol, so nothing else may be printed to it
+            let executor = tui::create_log_tool_executor(cli.backend)?;
+            mcp::server::serve(executor, tokio::io::stdin(), tokio::io::stdout()).await?;
+        }
         Some(Commands::Version) => {
             println!("zeteo {}", env!("CARGO_PKG_VERSION"));
         }
         None => {
-            let mut app = tui::create_tui_session(cli.provider, cli.backend).await?;
+            let mut app = tui::create_tui_session(cli.provider, cli.model, cli.backend).await?;
             app.run().await?;
         }
     }
@@ -1,9 +1,25 @@
-use anyhow::{anyhow, Context, Result};
+pub mod registry;
+pub mod server;
+pub mod supervisor;
+pub mod transport;
+
+use anyhow::{anyhow, bail, Context, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::config::McpServer;
+use transport::{HttpTransport, StdioTransport, Transport};
+
+/// Time a request may take before it is cancelled, unless overridden
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// JSON-RPC "method not found", for server requests we don't support
+const METHOD_NOT_FOUND: i32 = -32601;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct McpRequest {
@@ -35,117 +51,332 @@ pub struct McpError {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ToolInfo {
     pub name: String,
+    #[serde(default)]
     pub description: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "inputSchema", skip_serializing_if = "Option::is_none")]
     pub input_schema: Option<serde_json::Value>,
 }
 
+/// A resource from `resources/list`, such as a runbook or an ownership map
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceInfo {
+    pub uri: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// One item of a `resources/read` result: `text` for text resources, `blob`
+/// (base64) for binary ones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+/// A prompt template from `prompts/list`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptInfo {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A message of an expanded prompt. `content` is a text, image or embedded
+/// resource content block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptMessage {
+    pub role: String,
+    pub content: Value,
+}
+
+impl PromptMessage {
+    /// The message as plain text; embedded text resources are inlined and
+    /// anything else is described by its type
+    pub fn text(&self) -> String {
+        let content = &self.content;
+        match content.get("type").and_then(Value::as_str) {
+            Some("text") => content
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            Some("resource") => {
+                let resource = &content["resource"];
+                match resource.get("text").and_then(Value::as_str) {
+                    Some(text) => text.to_string(),
+                    None => format!("[resource {}]", resource["uri"].as_str().unwrap_or("?")),
+                }
+            }
+            Some(other) => format!("[{} content]", other),
+            None => content.to_string(),
+        }
+    }
+}
+
+/// Server-sent notification other than progress, e.g. `notifications/message`
+/// or `notifications/tools/list_changed`
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct McpNotification {
+    pub method: String,
+    pub params: Option<Value>,
+}
+
+/// `notifications/progress` update for one in-flight request
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpProgress {
+    pub progress: f64,
+    pub total: Option<f64>,
+    pub message: Option<String>,
+}
+
+/// State shared between the client and its reader task
+struct Shared {
+    pending: Mutex<HashMap<u64, oneshot::Sender<McpResponse>>>,
+    progress: Mutex<HashMap<u64, mpsc::UnboundedSender<McpProgress>>>,
+    notifications: broadcast::Sender<McpNotification>,
+    /// Messages for the transport to deliver
+    outgoing: mpsc::UnboundedSender<String>,
+    /// Set once the server's stdout closes
+    closed: AtomicBool,
+}
+
+impl Shared {
+    fn send(&self, message: &impl Serialize) -> Result<()> {
+        let line = serde_json::to_string(message)?;
+        self.outgoing
+            .send(line)
+            .map_err(|_| anyhow!("MCP server connection is closed"))
+    }
+
+    /// Route one line from the server to whoever is waiting for it
+    fn dispatch(&self, line: &str) {
+        // Servers sometimes print banners on stdout; anything that isn't JSON-RPC is skipped
+        let Ok(message) = serde_json::from_str::<Value>(line) else {
+            return;
+        };
+        let method = message.get("method").and_then(Value::as_str);
+        let id = message.get("id").filter(|id| !id.is_null());
+
+        match (method, id) {
+            (Some(method), Some(id)) => self.answer_server_request(method, id.clone()),
+            (Some("notifications/progress"), None) => self.report_progress(message.get("params")),
+            (Some(method), None) => {
+                let _ = self.notifications.send(McpNotification {
+                    method: method.to_string(),
+                    params: message.get("params").cloned(),
+                });
+            }
+            (None, Some(_)) => {
+                let Ok(response) = serde_json::from_value::<McpResponse>(message) else {
+                    return;
+                };
+                if let Some(waiter) = self.pending.lock().unwrap().remove(&response.id) {
+                    let _ = waiter.send(response);
+                }
+            }
+            (None, None) => {}
+        }
+    }
+
+    fn report_progress(&self, params: Option<&Value>) {
+        let Some(params) = params else { return };
+        let Some(token) = params.get("progressToken").and_then(Value::as_u64) else {
+            return;
+        };
+        if let Some(sender) = self.progress.lock().unwrap().get(&token) {
+            let _ = sender.send(McpProgress {
+                progress: params
+                    .get("progress")
+                    .and_then(Value::as_f64)
+                    .unwrap_or(0.0),
+                total: params.get("total").and_then(Value::as_f64),
+                message: params
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            });
+        }
+    }
+
+    /// Servers may ping the client; everything else they might ask for
+    /// (sampling, roots) isn't offered in our capabilities
+    fn answer_server_request(&self, method: &str, id: Value) {
+        let reply = if method == "ping" {
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "result": {}})
+        } else {
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": METHOD_NOT_FOUND, "message": format!("Method not found: {}", method)}
+            })
+        };
+        let _ = self.send(&reply);
+    }
+
+    /// The server is gone: fail everything still waiting on it
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        self.pending.lock().unwrap().clear();
+        self.progress.lock().unwrap().clear();
+    }
+}
+
+/// Unregisters a request when its future completes or is dropped, telling the
+/// server to stop work on it in the second case
+struct InFlight<'a> {
+    shared: &'a Shared,
+    id: u64,
+    completed: bool,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.shared.pending.lock().unwrap().remove(&self.id);
+        self.shared.progress.lock().unwrap().remove(&self.id);
+        if !self.completed {
+            let _ = self.shared.send(&serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/cancelled",
+                "params": {"requestId": self.id, "reason": "Request cancelled by client"}
+            }));
+        }
+    }
+}
+
+/// Client for an MCP server. Requests can be issued concurrently from a
+/// shared reference; responses are matched to requests by ID.
 pub struct McpClient {
-    stdin: Arc<Mutex<BufWriter<ChildStdin>>>,
-    stdout: Arc<Mutex<BufReader<ChildStdout>>>,
-    process: Option<Child>,
+    shared: Arc<Shared>,
+    transport: Mutex<Box<dyn Transport>>,
     #[allow(dead_code)]
     server_name: String,
-    request_id: Arc<Mutex<u64>>,
-    initialized: bool,
+    next_id: AtomicU64,
+    initialized: AtomicBool,
+    /// `capabilities` from the server's initialize result
+    capabilities: Mutex<Value>,
+    request_timeout: Duration,
 }
 
 #[allow(dead_code)]
 impl McpClient {
+    /// Start a server process on stdio. Must be called from within a tokio runtime.
     pub fn new(
         command: &str,
         args: &[String],
         env: &HashMap<String, String>,
         server_name: String,
     ) -> Result<Self> {
-        let mut cmd = Command::new(command);
-        cmd.args(args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        for (key, value) in env {
-            cmd.env(key, value);
-        }
+        Self::with_transport(
+            Box::new(StdioTransport::spawn(command, args, env)?),
+            server_name,
+        )
+    }
 
-        let mut process = cmd.spawn().context("Failed to start MCP server process")?;
+    /// Connect to a server from config.json: remote when it has a `url`,
+    /// otherwise spawned from its `command`
+    pub fn connect(server_name: &str, server: &McpServer) -> Result<Self> {
+        let transport: Box<dyn Transport> = match &server.url {
+            Some(url) => Box::new(HttpTransport::new(url, &server.http_headers()?)?),
+            None if !server.command.is_empty() => Box::new(StdioTransport::spawn(
+                &server.command,
+                &server.args,
+                &server.env,
+            )?),
+            None => bail!("MCP server '{}' needs a command or a url", server_name),
+        };
+        Self::with_transport(transport, server_name.to_string())
+    }
 
-        let stdin = process.stdin.take().context("Failed to get stdin handle")?;
-        let stdout = process
-            .stdout
-            .take()
-            .context("Failed to get stdout handle")?;
+    pub fn with_transport(mut transport: Box<dyn Transport>, server_name: String) -> Result<Self> {
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+        let (incoming_tx, mut incoming) = mpsc::unbounded_channel::<String>();
+        transport.start(outgoing_rx, incoming_tx)?;
+
+        let shared = Arc::new(Shared {
+            pending: Mutex::new(HashMap::new()),
+            progress: Mutex::new(HashMap::new()),
+            notifications: broadcast::channel(64).0,
+            outgoing,
+            closed: AtomicBool::new(false),
+        });
+
+        let reader = shared.clone();
+        tokio::spawn(async move {
+            while let Some(line) = incoming.recv().await {
+                reader.dispatch(&line);
+            }
+            reader.close();
+        });
 
         Ok(McpClient {
-            stdin: Arc::new(Mutex::new(BufWriter::new(stdin))),
-            stdout: Arc::new(Mutex::new(BufReader::new(stdout))),
-            process: Some(process),
+            shared,
+            transport: Mutex::new(transport),
             server_name,
-            request_id: Arc::new(Mutex::new(0)),
-            initialized: false,
+            next_id: AtomicU64::new(0),
+            initialized: AtomicBool::new(false),
+            capabilities: Mutex::new(Value::Null),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
         })
     }
 
-    fn next_request_id(&self) -> u64 {
-        let mut id = self.request_id.lock().unwrap();
-        *id += 1;
-        *id
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
     }
 
-    pub fn initialize(&mut self) -> Result<serde_json::Value> {
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: self.next_request_id(),
-            method: "initialize".to_string(),
-            params: Some(serde_json::json!({
-                "protocolVersion": "2024-11-05",
-                "capabilities": {
-                    "tools": {}
-                },
-                "clientInfo": {
-                    "name": "zeteo-cli",
-                    "version": env!("CARGO_PKG_VERSION")
-                }
-            })),
-        };
-
-        let response = self.send_request(&request)?;
-
-        if let Some(error) = response.error {
-            return Err(anyhow!("Initialize failed: {}", error.message));
-        }
-
-        self.initialized = true;
-
+    pub async fn initialize(&self) -> Result<Value> {
+        let result = self
+            .request(
+                "initialize",
+                Some(serde_json::json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {
+                        "tools": {}
+                    },
+                    "clientInfo": {
+                        "name": "zeteo-cli",
+                        "version": env!("CARGO_PKG_VERSION")
+                    }
+                })),
+            )
+            .await
+            .context("Initialize failed")?;
+
+        *self.capabilities.lock().unwrap() =
+            result.get("capabilities").cloned().unwrap_or(Value::Null);
+        self.initialized.store(true, Ordering::SeqCst);
         self.send_notification("notifications/initialized", None)?;
 
-        response
-            .result
-            .ok_or_else(|| anyhow!("No result in initialize response"))
+        Ok(result)
     }
 
-    #[allow(dead_code)]
-    pub fn list_tools(&self) -> Result<Vec<ToolInfo>> {
-        if !self.initialized {
-            return Err(anyhow!("Client not initialized. Call initialize() first"));
-        }
-
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: self.next_request_id(),
-            method: "tools/list".to_string(),
-            params: None,
-        };
-
-        let response = self.send_request(&request)?;
+    pub async fn list_tools(&self) -> Result<Vec<ToolInfo>> {
+        self.ensure_initialized()?;
 
-        if let Some(error) = response.error {
-            return Err(anyhow!("List tools failed: {}", error.message));
-        }
-
-        let result = response
-            .result
-            .ok_or_else(|| anyhow!("No result in list tools response"))?;
+        let result = self
+            .request("tools/list", None)
+            .await
+            .context("List tools failed")?;
 
         let tools = result
             .get("tools")
@@ -154,143 +385,253 @@ impl McpClient {
         serde_json::from_value(tools.clone()).context("Failed to parse tools list")
     }
 
-    pub fn call_tool(
+    pub async fn call_tool(&self, tool_name: &str, arguments: Value) -> Result<Value> {
+        self.ensure_initialized()?;
+        self.request(
+            "tools/call",
+            Some(serde_json::json!({"name": tool_name, "arguments": arguments})),
+        )
+        .await
+        .context("Tool call failed")
+    }
+
+    /// Like [`call_tool`](Self::call_tool), forwarding the server's progress
+    /// notifications for this call to `progress`
+    pub async fn call_tool_with_progress(
         &self,
         tool_name: &str,
-        arguments: serde_json::Value,
-    ) -> Result<serde_json::Value> {
-        if !self.initialized {
-            return Err(anyhow!("Client not initialized. Call initialize() first"));
-        }
+        arguments: Value,
+        progress: mpsc::UnboundedSender<McpProgress>,
+    ) -> Result<Value> {
+        self.ensure_initialized()?;
+        self.request_with_progress(
+            "tools/call",
+            Some(serde_json::json!({"name": tool_name, "arguments": arguments})),
+            Some(progress),
+        )
+        .await
+        .context("Tool call failed")
+    }
 
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: self.next_request_id(),
-            method: "tools/call".to_string(),
-            params: Some(serde_json::json!({
-                "name": tool_name,
-                "arguments": arguments
-            })),
-        };
+    /// Whether the server declared `capability` (e.g. "prompts") when initializing
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.lock().unwrap().get(capability).is_some()
+    }
 
-        let response = self.send_request(&request)?;
+    /// Whether the server accepts `resources/subscribe`
+    pub fn supports_resource_subscriptions(&self) -> bool {
+        self.capabilities.lock().unwrap()["resources"]["subscribe"] == true
+    }
 
-        if let Some(error) = response.error {
-            return Err(anyhow!("Tool call failed: {}", error.message));
-        }
+    pub async fn list_resources(&self) -> Result<Vec<ResourceInfo>> {
+        self.list_all("resources/list", "resources")
+            .await
+            .context("List resources failed")
+    }
 
-        response
-            .result
-            .ok_or_else(|| anyhow!("No result in tool call response"))
+    pub async fn read_resource(&self, uri: &str) -> Result<Vec<ResourceContents>> {
+        self.ensure_initialized()?;
+        let result = self
+            .request("resources/read", Some(serde_json::json!({"uri": uri})))
+            .await
+            .with_context(|| format!("Read resource {} failed", uri))?;
+
+        let contents = result
+            .get("contents")
+            .ok_or_else(|| anyhow!("No 'contents' field in response"))?;
+        serde_json::from_value(contents.clone()).context("Failed to parse resource contents")
+    }
+
+    /// Ask for `notifications/resources/updated` when `uri` changes; they
+    /// arrive through [`notifications`](Self::notifications)
+    pub async fn subscribe_resource(&self, uri: &str) -> Result<()> {
+        self.ensure_initialized()?;
+        self.request("resources/subscribe", Some(serde_json::json!({"uri": uri})))
+            .await
+            .with_context(|| format!("Subscribe to {} failed", uri))?;
+        Ok(())
+    }
+
+    pub async fn unsubscribe_resource(&self, uri: &str) -> Result<()> {
+        self.ensure_initialized()?;
+        self.request(
+            "resources/unsubscribe",
+            Some(serde_json::json!({"uri": uri})),
+        )
+        .await
+        .with_context(|| format!("Unsubscribe from {} failed", uri))?;
+        Ok(())
     }
 
-    pub fn query_logs(&self, query: &str, max_results: usize) -> Result<serde_json::Value> {
+    pub async fn list_prompts(&self) -> Result<Vec<PromptInfo>> {
+        self.list_all("prompts/list", "prompts")
+            .await
+            .context("List prompts failed")
+    }
+
+    /// Expand a prompt template with its arguments
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &HashMap<String, String>,
+    ) -> Result<Vec<PromptMessage>> {
+        self.ensure_initialized()?;
+        let result = self
+            .request(
+                "prompts/get",
+                Some(serde_json::json!({"name": name, "arguments": arguments})),
+            )
+            .await
+            .with_context(|| format!("Get prompt {} failed", name))?;
+
+        let messages = result
+            .get("messages")
+            .ok_or_else(|| anyhow!("No 'messages' field in response"))?;
+        serde_json::from_value(messages.clone()).context("Failed to parse prompt messages")
+    }
+
+    /// Every page of a paginated list method, following `nextCursor`
+    async fn list_all<T: serde::de::DeserializeOwned>(
+        &self,
+        method: &str,
+        field: &str,
+    ) -> Result<Vec<T>> {
+        self.ensure_initialized()?;
+
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = cursor.map(|cursor| serde_json::json!({"cursor": cursor}));
+            let result = self.request(method, params).await?;
+            let page = result
+                .get(field)
+                .ok_or_else(|| anyhow!("No '{}' field in response", field))?;
+            let page: Vec<T> = serde_json::from_value(page.clone())
+                .with_context(|| format!("Failed to parse {} list", field))?;
+            items.extend(page);
+
+            cursor = result
+                .get("nextCursor")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if cursor.is_none() {
+                return Ok(items);
+            }
+        }
+    }
+
+    pub async fn query_logs(&self, query: &str, max_results: usize) -> Result<Value> {
         let arguments = serde_json::json!({
             "query": query,
             "maxResults": max_results
         });
 
-        self.call_tool("query_logs", arguments)
+        self.call_tool("query_logs", arguments).await
     }
 
-    fn send_request(&self, request: &McpRequest) -> Result<McpResponse> {
-        let json_request = serde_json::to_string(request)?;
+    /// Send a request and wait for its result. Dropping the future or hitting
+    /// the timeout sends `notifications/cancelled` for it.
+    pub async fn request(&self, method: &str, params: Option<Value>) -> Result<Value> {
+        self.request_with_progress(method, params, None).await
+    }
 
-        {
-            let mut stdin = self.stdin.lock().unwrap();
-            writeln!(stdin, "{}", json_request)?;
-            stdin.flush()?;
+    async fn request_with_progress(
+        &self,
+        method: &str,
+        mut params: Option<Value>,
+        progress: Option<mpsc::UnboundedSender<McpProgress>>,
+    ) -> Result<Value> {
+        if self.shared.closed.load(Ordering::SeqCst) {
+            bail!("MCP server has exited");
         }
 
-        let response_line = {
-            let mut stdout = self.stdout.lock().unwrap();
-            let mut line = String::new();
-            let mut attempts = 0;
-            const MAX_ATTEMPTS: usize = 10;
-
-            loop {
-                line.clear();
-                stdout
-                    .read_line(&mut line)
-                    .context("Failed to read response from MCP server")?;
-
-                if line.trim().is_empty() {
-                    attempts += 1;
-                    if attempts >= MAX_ATTEMPTS {
-                        return Err(anyhow!(
-                            "No response from MCP server after {} attempts",
-                            MAX_ATTEMPTS
-                        ));
-                    }
-                    continue;
-                }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let (tx, rx) = oneshot::channel();
+        self.shared.pending.lock().unwrap().insert(id, tx);
+        let mut in_flight = InFlight {
+            shared: &self.shared,
+            id,
+            completed: false,
+        };
 
-                if line.trim().starts_with('{') {
-                    break;
-                }
+        if let Some(progress) = progress {
+            let params = params.get_or_insert_with(|| serde_json::json!({}));
+            params["_meta"] = serde_json::json!({"progressToken": id});
+            self.shared.progress.lock().unwrap().insert(id, progress);
+        }
 
-                eprintln!("MCP server output: {}", line.trim());
-                attempts += 1;
-                if attempts >= MAX_ATTEMPTS {
-                    return Err(anyhow!(
-                        "No valid JSON response from MCP server after {} lines",
-                        MAX_ATTEMPTS
-                    ));
-                }
+        self.shared.send(&McpRequest {
+            jsonrpc: "2.0".to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        })?;
+
+        let response = match tokio::time::timeout(self.request_timeout, rx).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(_)) => {
+                in_flight.completed = true;
+                bail!("MCP server exited before answering {}", method)
             }
-
-            line
+            Err(_) => bail!(
+                "{} timed out after {}s",
+                method,
+                self.request_timeout.as_secs_f64()
+            ),
         };
+        in_flight.completed = true;
 
-        let response: McpResponse =
-            serde_json::from_str(&response_line).context("Failed to parse MCP response")?;
-
-        if response.id != request.id {
-            return Err(anyhow!(
-                "Response ID mismatch: expected {}, got {}",
-                request.id,
-                response.id
-            ));
+        if let Some(error) = response.error {
+            bail!("{} (code {})", error.message, error.code);
         }
+        response
+            .result
+            .ok_or_else(|| anyhow!("No result in {} response", method))
+    }
 
-        Ok(response)
+    fn ensure_initialized(&self) -> Result<()> {
+        if !self.initialized.load(Ordering::SeqCst) {
+            bail!("Client not initialized. Call initialize() first");
+        }
+        Ok(())
     }
 
-    fn send_notification(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
-        let notification = serde_json::json!({
+    fn send_notification(&self, method: &str, params: Option<Value>) -> Result<()> {
+        self.shared.send(&serde_json::json!({
             "jsonrpc": "2.0",
             "method": method,
             "params": params
-        });
+        }))
+    }
 
-        let json_notification = serde_json::to_string(&notification)?;
+    /// Notifications from the server other than progress, from now on
+    pub fn notifications(&self) -> broadcast::Receiver<McpNotification> {
+        self.shared.notifications.subscribe()
+    }
 
-        let mut stdin = self.stdin.lock().unwrap();
-        writeln!(stdin, "{}", json_notification)?;
-        stdin.flush()?;
+    /// The most recent lines a local server wrote to stderr
+    pub fn stderr_tail(&self) -> Vec<String> {
+        self.transport.lock().unwrap().stderr_tail()
+    }
 
-        Ok(())
+    /// Whether the server process is running, or the remote connection open
+    pub fn is_alive(&self) -> bool {
+        !self.shared.closed.load(Ordering::SeqCst) && self.transport.lock().unwrap().is_alive()
     }
 
-    #[allow(dead_code)]
-    pub fn is_alive(&mut self) -> bool {
-        if let Some(process) = &mut self.process {
-            match process.try_wait() {
-                Ok(Some(_)) => false,
-                Ok(None) => true,
-                Err(_) => false,
-            }
-        } else {
-            false
-        }
+    /// Check that the server still answers requests
+    pub async fn ping(&self) -> Result<()> {
+        self.request("ping", None).await.context("Ping failed")?;
+        Ok(())
     }
 }
 
 impl Drop for McpClient {
     fn drop(&mut self) {
-        if let Some(mut process) = self.process.take() {
-            let _ = process.kill();
-        }
+        self.transport
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .close();
     }
 }
 
@@ -0,0 +1,879 @@
+//! MCP servers from config.json, started at launch so their tools can be
+//! offered to the model next to the built-in log tools, their prompts used as
+//! slash commands and their resources attached to the conversation.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Notify};
+
+use super::supervisor::{self, Supervision};
+use super::{McpClient, PromptInfo, PromptMessage, ResourceInfo, ToolInfo};
+use crate::config::McpServer;
+use crate::providers::{Tool, ToolFunction};
+
+/// Time a server gets to start, initialize and list its tools
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long a failed server's stderr gets to be read before it is kept
+const STDERR_SETTLE: Duration = Duration::from_millis(100);
+
+/// Separates the server name from the tool name in the names the model sees
+const SEPARATOR: &str = "__";
+
+/// OpenAI and Anthropic both reject longer function names
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+/// Lines of stderr kept for each server, across restarts
+const STDERR_LINES: usize = 200;
+
+struct McpTool {
+    /// Name on the server, as passed to `tools/call`
+    name: String,
+    definition: Tool,
+}
+
+/// A server's prompt, offered as the slash command `/{server}:{prompt}`
+#[derive(Clone)]
+pub struct McpPrompt {
+    pub server: String,
+    pub command: String,
+    pub info: PromptInfo,
+}
+
+/// A resource listed by one of the servers
+pub struct McpResource {
+    pub server: String,
+    pub info: ResourceInfo,
+}
+
+/// `notifications/resources/updated` from a server
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceUpdate {
+    pub server: String,
+    pub uri: String,
+}
+
+/// Where a server is in its lifecycle
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerState {
+    Running,
+    /// Down, and the supervisor will start it again at `retry_at`
+    Restarting {
+        attempt: u32,
+        retry_at: Instant,
+    },
+    /// Down, with no supervisor to bring it back
+    Stopped,
+}
+
+/// A server as shown by `/mcp`
+#[derive(Debug, Clone)]
+pub struct ServerStatus {
+    pub name: String,
+    /// Command line or URL
+    pub target: String,
+    pub state: ServerState,
+    pub tools: usize,
+    pub prompts: usize,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    /// Most recent stderr lines, oldest first, including earlier processes'
+    pub stderr: Vec<String>,
+}
+
+/// What a newly started server offers
+pub(super) struct Started {
+    client: McpClient,
+    tools: Vec<ToolInfo>,
+    prompts: Vec<PromptInfo>,
+}
+
+/// A server that failed to start, with what its process said on stderr
+pub(super) struct StartFailure {
+    pub error: anyhow::Error,
+    pub stderr: Vec<String>,
+}
+
+/// A configured server and what its current process offers
+struct ServerEntry {
+    config: McpServer,
+    client: Option<Arc<McpClient>>,
+    tools: Vec<McpTool>,
+    prompts: Vec<McpPrompt>,
+    state: ServerState,
+    started_at: Option<Instant>,
+    starts: u32,
+    last_error: Option<String>,
+    /// stderr of the server's earlier processes
+    stderr: VecDeque<String>,
+    /// Resource URIs subscribed to, renewed when the server restarts
+    subscriptions: Vec<String>,
+    /// Wakes the supervisor to restart the server now
+    restart: Arc<Notify>,
+}
+
+impl ServerEntry {
+    fn new(config: McpServer) -> Self {
+        Self {
+            config,
+            client: None,
+            tools: Vec::new(),
+            prompts: Vec::new(),
+            state: ServerState::Stopped,
+            started_at: None,
+            starts: 0,
+            last_error: None,
+            stderr: VecDeque::new(),
+            subscriptions: Vec::new(),
+            restart: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Add a process's stderr, keeping the last [`STDERR_LINES`]
+    fn append_stderr(&mut self, lines: Vec<String>) {
+        self.stderr.extend(lines);
+        let excess = self.stderr.len().saturating_sub(STDERR_LINES);
+        self.stderr.drain(..excess);
+    }
+}
+
+/// The configured MCP servers and the tools they expose, keyed by prefixed name
+pub struct McpRegistry {
+    servers: RwLock<BTreeMap<String, ServerEntry>>,
+    updates: broadcast::Sender<ResourceUpdate>,
+}
+
+impl Default for McpRegistry {
+    fn default() -> Self {
+        Self {
+            servers: RwLock::new(BTreeMap::new()),
+            updates: broadcast::channel(64).0,
+        }
+    }
+}
+
+impl McpRegistry {
+    /// Start every configured server and list its tools. A server that fails to
+    /// start is reported in the returned errors rather than stopping the
+    /// others, and stays down unless [`supervise`](Self::supervise) is called.
+    pub async fn start(servers: &HashMap<String, McpServer>) -> (Self, Vec<String>) {
+        let mut names: Vec<&String> = servers.keys().collect();
+        names.sort();
+
+        let started = futures::future::join_all(names.into_iter().map(|name| {
+            let name = name.clone();
+            let server = servers[&name].clone();
+            async move {
+                let result = start_server(&name, &server).await;
+                (name, server, result)
+            }
+        }))
+        .await;
+
+        let registry = Self::default();
+        let mut errors = Vec::new();
+        for (name, server, result) in started {
+            registry
+                .servers
+                .write()
+                .unwrap()
+                .insert(name.clone(), ServerEntry::new(server));
+            match result {
+                Ok(started) => registry.install(&name, started),
+                Err(failure) => {
+                    let mut error = format!("MCP server '{}': {:#}", name, failure.error);
+                    if let Some(line) = failure.stderr.last() {
+                        error.push_str(&format!(" (stderr: {})", line));
+                    }
+                    errors.push(error);
+                    registry.record_error(&name, failure);
+                }
+            }
+        }
+        (registry, errors)
+    }
+
+    /// Watch every server from now on, restarting those that exit or stop
+    /// answering pings (and those that failed to start) with backoff
+    pub fn supervise(self: &Arc<Self>, supervision: Supervision) {
+        let names: Vec<String> = self.servers.read().unwrap().keys().cloned().collect();
+        for name in names {
+            tokio::spawn(supervisor::supervise(
+                Arc::downgrade(self),
+                name,
+                supervision.clone(),
+            ));
+        }
+    }
+
+    /// Put a freshly started server in service
+    pub(super) fn install(&self, name: &str, started: Started) {
+        let client = Arc::new(started.client);
+        self.forward_resource_updates(name, &client);
+
+        let mut servers = self.servers.write().unwrap();
+        let Some(entry) = servers.get_mut(name) else {
+            return;
+        };
+        entry.tools = started
+            .tools
+            .into_iter()
+            .map(|info| McpTool {
+                definition: tool_definition(name, &info),
+                name: info.name,
+            })
+            .collect();
+        entry.prompts = started
+            .prompts
+            .into_iter()
+            .map(|info| McpPrompt {
+                server: name.to_string(),
+                command: prompt_command(name, &info.name),
+                info,
+            })
+            .collect();
+        entry.client = Some(client);
+        entry.state = ServerState::Running;
+        entry.started_at = Some(Instant::now());
+        entry.starts += 1;
+    }
+
+    /// Take a server that went down out of service, keeping its stderr.
+    /// Returns how long it had been running.
+    pub(super) fn take_down(&self, name: &str, reason: Option<String>) -> Duration {
+        let mut servers = self.servers.write().unwrap();
+        let Some(entry) = servers.get_mut(name) else {
+            return Duration::ZERO;
+        };
+        if let Some(client) = entry.client.take() {
+            entry.append_stderr(client.stderr_tail());
+        }
+        entry.tools.clear();
+        entry.prompts.clear();
+        entry.state = ServerState::Stopped;
+        if reason.is_some() {
+            entry.last_error = reason;
+        }
+        entry
+            .started_at
+            .take()
+            .map(|started| started.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// Keep why a server failed to start, and the stderr of the process
+    pub(super) fn record_error(&self, name: &str, failure: StartFailure) {
+        if let Some(entry) = self.servers.write().unwrap().get_mut(name) {
+            entry.last_error = Some(format!("{:#}", failure.error));
+            entry.append_stderr(failure.stderr);
+        }
+    }
+
+    pub(super) fn set_state(&self, name: &str, state: ServerState) {
+        if let Some(entry) = self.servers.write().unwrap().get_mut(name) {
+            entry.state = state;
+        }
+    }
+
+    pub(super) fn config_of(&self, name: &str) -> Option<McpServer> {
+        self.servers
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|e| e.config.clone())
+    }
+
+    pub(super) fn restart_signal(&self, name: &str) -> Option<Arc<Notify>> {
+        self.servers
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|e| e.restart.clone())
+    }
+
+    /// The server's client, if it is running
+    pub(super) fn running_client(&self, name: &str) -> Option<Arc<McpClient>> {
+        self.servers
+            .read()
+            .unwrap()
+            .get(name)
+            .and_then(|e| e.client.clone())
+    }
+
+    /// Subscribe again to the resources followed before a restart, and
+    /// report them as updated since their contents may have changed meanwhile
+    pub(super) async fn renew_subscriptions(&self, name: &str) {
+        let Some(client) = self.running_client(name) else {
+            return;
+        };
+        let uris = self
+            .servers
+            .read()
+            .unwrap()
+            .get(name)
+            .map(|e| e.subscriptions.clone())
+            .unwrap_or_default();
+        for uri in uris {
+            if client.subscribe_resource(&uri).await.is_ok() {
+                let _ = self.updates.send(ResourceUpdate {
+                    server: name.to_string(),
+                    uri,
+                });
+            }
+        }
+    }
+
+    /// Ask the supervisor to restart a server now, skipping any backoff
+    pub fn restart(&self, name: &str) -> Result<()> {
+        self.restart_signal(name)
+            .ok_or_else(|| anyhow!("Unknown MCP server: {}", name))?
+            .notify_one();
+        Ok(())
+    }
+
+    /// Every configured server, by name
+    pub fn status(&self) -> Vec<ServerStatus> {
+        self.servers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, entry)| {
+                let mut stderr: Vec<String> = entry.stderr.iter().cloned().collect();
+                if let Some(client) = &entry.client {
+                    stderr.extend(client.stderr_tail());
+                }
+                let excess = stderr.len().saturating_sub(STDERR_LINES);
+                stderr.drain(..excess);
+
+                ServerStatus {
+                    name: name.clone(),
+                    target: match &entry.config.url {
+                        Some(url) => url.clone(),
+                        None => std::iter::once(&entry.config.command)
+                            .chain(&entry.config.args)
+                            .cloned()
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    },
+                    state: entry.state.clone(),
+                    tools: entry.tools.len(),
+                    prompts: entry.prompts.len(),
+                    restarts: entry.starts.saturating_sub(1),
+                    last_error: entry.last_error.clone(),
+                    stderr,
+                }
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.read().unwrap().is_empty()
+    }
+
+    pub fn tool_count(&self) -> usize {
+        self.definitions().len()
+    }
+
+    /// Tool definitions to send to the model. Names are unique: if two servers
+    /// map to the same name, the first server in name order keeps it.
+    pub fn definitions(&self) -> Vec<Tool> {
+        let mut seen = HashSet::new();
+        self.servers
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|entry| &entry.tools)
+            .filter(|tool| seen.insert(tool.definition.function.name.clone()))
+            .map(|tool| tool.definition.clone())
+            .collect()
+    }
+
+    /// Whether `name` is one of the running servers' prefixed tool names
+    pub fn handles(&self, name: &str) -> bool {
+        self.find_tool(name).is_some()
+    }
+
+    /// The server offering a prefixed tool
+    pub fn tool_server(&self, name: &str) -> Option<String> {
+        self.find_tool(name).map(|(server, _, _)| server)
+    }
+
+    /// The server, its client and the server-side name of a prefixed tool
+    fn find_tool(&self, name: &str) -> Option<(String, Arc<McpClient>, String)> {
+        let servers = self.servers.read().unwrap();
+        servers.iter().find_map(|(server, entry)| {
+            let tool = entry
+                .tools
+                .iter()
+                .find(|t| t.definition.function.name == name)?;
+            Some((server.clone(), entry.client.clone()?, tool.name.clone()))
+        })
+    }
+
+    /// Call a tool by its prefixed name and return the text of its result
+    pub async fn call(&self, name: &str, arguments: &str) -> Result<String> {
+        let (server, client, tool_name) = self
+            .find_tool(name)
+            .ok_or_else(|| anyhow!("Unknown tool: {}", name))?;
+
+        let arguments: serde_json::Value = if arguments.trim().is_empty() {
+            serde_json::json!({})
+        } else {
+            serde_json::from_str(arguments)
+                .with_context(|| format!("Failed to parse {} arguments", name))?
+        };
+
+        let result = client
+            .call_tool(&tool_name, arguments)
+            .await
+            .with_context(|| format!("MCP server '{}'", server))?;
+
+        Ok(result_text(&result))
+    }
+
+    /// Prompts of the running servers, in server order
+    pub fn prompts(&self) -> Vec<McpPrompt> {
+        let mut seen = HashSet::new();
+        self.servers
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|entry| &entry.prompts)
+            .filter(|prompt| seen.insert(prompt.command.clone()))
+            .cloned()
+            .collect()
+    }
+
+    /// The prompt behind a slash command, without its leading `/`
+    pub fn prompt(&self, command: &str) -> Option<McpPrompt> {
+        self.prompts().into_iter().find(|p| p.command == command)
+    }
+
+    /// Expand a prompt from the text typed after its slash command, either
+    /// `key=value` pairs or values in the order the prompt declares them
+    pub async fn expand_prompt(&self, command: &str, text: &str) -> Result<Vec<PromptMessage>> {
+        let prompt = self
+            .prompt(command)
+            .ok_or_else(|| anyhow!("Unknown prompt: {}", command))?;
+        let arguments = prompt_arguments(&prompt, text)?;
+
+        self.client(&prompt.server)?
+            .get_prompt(&prompt.info.name, &arguments)
+            .await
+            .with_context(|| format!("MCP server '{}'", prompt.server))
+    }
+
+    /// Resources of every running server that offers them. A server failing to
+    /// list them is reported without hiding the others'.
+    pub async fn list_resources(&self) -> (Vec<McpResource>, Vec<String>) {
+        let servers: Vec<(String, Arc<McpClient>)> = self
+            .servers
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|(name, entry)| Some((name.clone(), entry.client.clone()?)))
+            .filter(|(_, client)| client.supports("resources"))
+            .collect();
+
+        let listed = futures::future::join_all(
+            servers
+                .into_iter()
+                .map(|(server, client)| async move { (server, client.list_resources().await) }),
+        )
+        .await;
+
+        let mut resources = Vec::new();
+        let mut errors = Vec::new();
+        for (server, result) in listed {
+            match result {
+                Ok(listed) => resources.extend(listed.into_iter().map(|info| McpResource {
+                    server: server.clone(),
+                    info,
+                })),
+                Err(e) => errors.push(format!("MCP server '{}': {:#}", server, e)),
+            }
+        }
+        (resources, errors)
+    }
+
+    /// A resource's contents as text; binary contents are described rather
+    /// than included
+    pub async fn read_resource(&self, server: &str, uri: &str) -> Result<String> {
+        let contents = self
+            .client(server)?
+            .read_resource(uri)
+            .await
+            .with_context(|| format!("MCP server '{}'", server))?;
+
+        Ok(contents
+            .iter()
+            .map(|item| match &item.text {
+                Some(text) => text.clone(),
+                None => format!(
+                    "[binary content: {}]",
+                    item.mime_type.as_deref().unwrap_or("unknown type")
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Subscribe to changes of a resource, if its server supports that.
+    /// Returns whether a subscription was made.
+    pub async fn subscribe(&self, server: &str, uri: &str) -> Result<bool> {
+        let client = self.client(server)?;
+        if !client.supports_resource_subscriptions() {
+            return Ok(false);
+        }
+        client.subscribe_resource(uri).await?;
+        if let Some(entry) = self.servers.write().unwrap().get_mut(server) {
+            entry.subscriptions.push(uri.to_string());
+        }
+        Ok(true)
+    }
+
+    pub async fn unsubscribe(&self, server: &str, uri: &str) -> Result<()> {
+        if let Some(entry) = self.servers.write().unwrap().get_mut(server) {
+            entry.subscriptions.retain(|u| u != uri);
+        }
+        let client = self.client(server)?;
+        if client.supports_resource_subscriptions() {
+            client.unsubscribe_resource(uri).await?;
+        }
+        Ok(())
+    }
+
+    /// `notifications/resources/updated` from all servers, from now on,
+    /// including after they restart
+    pub fn resource_updates(&self) -> broadcast::Receiver<ResourceUpdate> {
+        self.updates.subscribe()
+    }
+
+    fn forward_resource_updates(&self, server: &str, client: &McpClient) {
+        let mut notifications = client.notifications();
+        let updates = self.updates.clone();
+        let server = server.to_string();
+        tokio::spawn(async move {
+            loop {
+                let notification = match notifications.recv().await {
+                    Ok(notification) => notification,
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if notification.method != "notifications/resources/updated" {
+                    continue;
+                }
+                let Some(uri) = notification
+                    .params
+                    .as_ref()
+                    .and_then(|p| p.get("uri"))
+                    .and_then(|u| u.as_str())
+                else {
+                    continue;
+                };
+                let _ = updates.send(ResourceUpdate {
+                    server: server.clone(),
+                    uri: uri.to_string(),
+                });
+            }
+        });
+    }
+
+    fn client(&self, server: &str) -> Result<Arc<McpClient>> {
+        let servers = self.servers.read().unwrap();
+        let entry = servers
+            .get(server)
+            .ok_or_else(|| anyhow!("Unknown MCP server: {}", server))?;
+        entry
+            .client
+            .clone()
+            .ok_or_else(|| anyhow!("MCP server '{}' is not running", server))
+    }
+}
+
+/// Start a server, initialize it and list what it offers, within
+/// [`STARTUP_TIMEOUT`]
+pub(super) async fn start_server(
+    name: &str,
+    server: &McpServer,
+) -> std::result::Result<Started, StartFailure> {
+    let client = McpClient::connect(name, server).map_err(|error| StartFailure {
+        error,
+        stderr: Vec::new(),
+    })?;
+    let discovered = async {
+        client.initialize().await?;
+        let tools = if client.supports("tools") {
+            client.list_tools().await?
+        } else {
+            Vec::new()
+        };
+        let prompts = if client.supports("prompts") {
+            client.list_prompts().await?
+        } else {
+            Vec::new()
+        };
+        Ok((tools, prompts))
+    };
+    let discovered = tokio::time::timeout(STARTUP_TIMEOUT, discovered)
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("timed out after {}s", STARTUP_TIMEOUT.as_secs())));
+    match discovered {
+        Ok((tools, prompts)) => Ok(Started {
+            client,
+            tools,
+            prompts,
+        }),
+        // A server that exits during startup usually says why on stderr,
+        // which is read separately from the reply that failed
+        Err(error) => {
+            tokio::time::sleep(STDERR_SETTLE).await;
+            Err(StartFailure {
+                error,
+                stderr: client.stderr_tail(),
+            })
+        }
+    }
+}
+
+/// `{server}:{prompt}` without whitespace, so it reads as one slash command
+fn prompt_command(server: &str, prompt: &str) -> String {
+    format!("{}:{}", server, prompt)
+        .chars()
+        .map(|c| if c.is_whitespace() { '_' } else { c })
+        .collect()
+}
+
+/// Map the text after a prompt's slash command to its arguments. With
+/// `key=value` pairs a value runs until the next pair, so it may contain
+/// spaces; otherwise words are taken in declaration order and the last
+/// argument gets the rest of the line.
+fn prompt_arguments(prompt: &McpPrompt, text: &str) -> Result<HashMap<String, String>> {
+    let declared = &prompt.info.arguments;
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut arguments: HashMap<String, String> = HashMap::new();
+
+    let is_pair = |word: &str| {
+        word.split_once('=')
+            .is_some_and(|(key, _)| declared.iter().any(|a| a.name == key))
+    };
+
+    if words.first().is_some_and(|w| is_pair(w)) {
+        let mut current: Option<String> = None;
+        for word in words {
+            match word.split_once('=').filter(|_| is_pair(word)) {
+                Some((key, value)) => {
+                    arguments.insert(key.to_string(), value.to_string());
+                    current = Some(key.to_string());
+                }
+                None => {
+                    if let Some(value) = current.as_ref().and_then(|k| arguments.get_mut(k)) {
+                        value.push(' ');
+                        value.push_str(word);
+                    }
+                }
+            }
+        }
+    } else if !words.is_empty() {
+        if declared.is_empty() {
+            bail!("/{} takes no arguments", prompt.command);
+        }
+        let mut words = words.into_iter();
+        for (i, argument) in declared.iter().enumerate() {
+            let value = if i + 1 == declared.len() {
+                words.by_ref().collect::<Vec<_>>().join(" ")
+            } else {
+                words.next().unwrap_or_default().to_string()
+            };
+            if !value.is_empty() {
+                arguments.insert(argument.name.clone(), value);
+            }
+        }
+    }
+
+    let missing: Vec<&str> = declared
+        .iter()
+        .filter(|a| a.required && !arguments.contains_key(&a.name))
+        .map(|a| a.name.as_str())
+        .collect();
+    if !missing.is_empty() {
+        bail!(
+            "/{} needs {}. Usage: {}",
+            prompt.command,
+            missing.join(", "),
+            prompt_usage(prompt)
+        );
+    }
+    Ok(arguments)
+}
+
+/// `/{command} <required> [optional]`
+pub fn prompt_usage(prompt: &McpPrompt) -> String {
+    let mut usage = format!("/{}", prompt.command);
+    for argument in &prompt.info.arguments {
+        if argument.required {
+            usage.push_str(&format!(" <{}>", argument.name));
+        } else {
+            usage.push_str(&format!(" [{}]", argument.name));
+        }
+    }
+    usage
+}
+
+/// The server's tool as the model sees it: `{server}__{tool}`, restricted to
+/// the characters and length function names allow. Names that are too long
+/// end in a hash of the full name, so tools sharing a long prefix stay apart.
+fn tool_definition(server: &str, info: &ToolInfo) -> Tool {
+    use std::hash::{Hash, Hasher};
+
+    let full = format!("{}{}{}", server, SEPARATOR, info.name);
+    let mut name: String = full
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.len() > MAX_TOOL_NAME_LEN {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        full.hash(&mut hasher);
+        let suffix = format!("_{:08x}", hasher.finish() as u32);
+        name.truncate(MAX_TOOL_NAME_LEN - suffix.len());
+        name.push_str(&suffix);
+    }
+
+    let description = if info.description.is_empty() {
+        format!("{} (from MCP server '{}')", info.name, server)
+    } else {
+        format!("{} (from MCP server '{}')", info.description, server)
+    };
+
+    Tool {
+        tool_type: "function".to_string(),
+        function: ToolFunction {
+            name,
+            description,
+            parameters: info
+                .input_schema
+                .clone()
+                .unwrap_or_else(|| serde_json::json!({"type": "object", "properties": {}})),
+        },
+    }
+}
+
+/// Flatten a `tools/call` result into text for the model: text content is
+/// joined, anything else is passed through as JSON
+fn result_text(result: &serde_json::Value) -> String {
+    let text = match result.get("content").and_then(|c| c.as_array()) {
+        Some(content) => content
+            .iter()
+            .map(|item| match item.get("text").and_then(|t| t.as_str()) {
+                Some(text) => text.to_string(),
+                None => item.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => result.to_string(),
+    };
+
+    if result.get("isError").and_then(|e| e.as_bool()) == Some(true) {
+        format!("Error: {}", text)
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_definition_prefixes_and_sanitizes() {
+        let info = ToolInfo {
+            name: "flags.get".to_string(),
+            description: "Read a feature flag".to_string(),
+            input_schema: Some(serde_json::json!({
+                "type": "object",
+                "properties": {"key": {"type": "string"}},
+                "required": ["key"]
+            })),
+        };
+
+        let tool = tool_definition("platform tools", &info);
+        assert_eq!(tool.function.name, "platform_tools__flags_get");
+        assert!(tool.function.description.contains("platform tools"));
+        assert_eq!(tool.function.parameters["required"][0], "key");
+
+        let long = ToolInfo {
+            name: "x".repeat(80),
+            description: String::new(),
+            input_schema: None,
+        };
+        let tool = tool_definition("deploys", &long);
+        assert_eq!(tool.function.name.len(), MAX_TOOL_NAME_LEN);
+        assert_eq!(tool.function.parameters["type"], "object");
+
+        // Tools differing only past the limit still get different names
+        let other = ToolInfo {
+            name: format!("{}y", "x".repeat(79)),
+            description: String::new(),
+            input_schema: None,
+        };
+        let other_tool = tool_definition("deploys", &other);
+        assert_eq!(other_tool.function.name.len(), MAX_TOOL_NAME_LEN);
+        assert_ne!(other_tool.function.name, tool.function.name);
+        assert_eq!(
+            tool_definition("deploys", &long).function.name,
+            tool.function.name
+        );
+    }
+
+    #[test]
+    fn test_prompt_arguments() {
+        let info: PromptInfo = serde_json::from_value(serde_json::json!({
+            "name": "incident review",
+            "arguments": [
+                {"name": "service", "required": true},
+                {"name": "window"}
+            ]
+        }))
+        .unwrap();
+        let prompt = McpPrompt {
+            server: "runbooks".to_string(),
+            command: prompt_command("runbooks", &info.name),
+            info,
+        };
+        assert_eq!(prompt.command, "runbooks:incident_review");
+
+        let args = prompt_arguments(&prompt, "window=last two hours service=checkout").unwrap();
+        assert_eq!(args["service"], "checkout");
+        assert_eq!(args["window"], "last two hours");
+
+        let args = prompt_arguments(&prompt, "checkout since 09:00").unwrap();
+        assert_eq!(args["service"], "checkout");
+        assert_eq!(args["window"], "since 09:00");
+
+        let err = prompt_arguments(&prompt, "").unwrap_err().to_string();
+        assert!(err.contains("needs service"), "{}", err);
+        assert!(err.contains("/runbooks:incident_review <service> [window]"));
+    }
+
+    #[test]
+    fn test_result_text() {
+        let ok = serde_json::json!({
+            "content": [{"type": "text", "text": "v1.4.2"}, {"type": "text", "text": "v1.4.1"}]
+        });
+        assert_eq!(result_text(&ok), "v1.4.2\nv1.4.1");
+
+        let failed = serde_json::json!({
+            "content": [{"type": "text", "text": "unknown flag"}],
+            "isError": true
+        });
+        assert_eq!(result_text(&failed), "Error: unknown flag");
+    }
+}
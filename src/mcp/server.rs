@@ -0,0 +1,195 @@
+//! `zeteo mcp-serve`: the log tools offered to other agents and IDEs as an
+//! MCP server on stdio, backed by the configured log backend.
+
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+
+use super::{McpError, METHOD_NOT_FOUND};
+use crate::tools::ToolExecutor;
+
+/// Protocol revisions we can speak, newest first
+const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i32 = -32700;
+const INVALID_PARAMS: i32 = -32602;
+
+/// Serve MCP on `input`/`output` until the client closes its end and the calls
+/// in progress have answered. Tool calls run concurrently and can be cancelled
+/// with `notifications/cancelled`.
+pub async fn serve<R, W>(executor: ToolExecutor, input: R, output: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let (replies, mut replies_rx) = mpsc::unbounded_channel::<Value>();
+    let writer = tokio::spawn(async move {
+        let mut output = output;
+        while let Some(message) = replies_rx.recv().await {
+            let line = format!("{}\n", message);
+            if output.write_all(line.as_bytes()).await.is_err() || output.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Running tool calls by request ID, so they can be cancelled
+    let in_flight: Arc<Mutex<HashMap<String, AbortHandle>>> = Arc::default();
+    let mut lines = BufReader::new(input).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                let _ = replies.send(error_response(
+                    Value::Null,
+                    PARSE_ERROR,
+                    format!("Parse error: {}", e),
+                ));
+                continue;
+            }
+        };
+
+        let method = message
+            .get("method")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        let Some(id) = message.get("id").cloned() else {
+            if method == "notifications/cancelled" {
+                if let Some(request) = params.get("requestId") {
+                    if let Some(call) = in_flight.lock().unwrap().remove(&request.to_string()) {
+                        call.abort();
+                    }
+                }
+            }
+            continue;
+        };
+
+        match method {
+            "initialize" => {
+                let _ = replies.send(result_response(id, initialize_result(&params)));
+            }
+            "ping" => {
+                let _ = replies.send(result_response(id, json!({})));
+            }
+            "tools/list" => {
+                let _ = replies.send(result_response(id, json!({"tools": tool_list(&executor)})));
+            }
+            "tools/call" => {
+                let key = id.to_string();
+                let executor = executor.clone();
+                let replies = replies.clone();
+                let running = in_flight.clone();
+
+                let mut in_flight = in_flight.lock().unwrap();
+                let call = tokio::spawn(async move {
+                    let response = match call_tool(&executor, &params).await {
+                        Ok(result) => result_response(id.clone(), result),
+                        Err(error) => error_response(id.clone(), error.code, error.message),
+                    };
+                    running.lock().unwrap().remove(&id.to_string());
+                    let _ = replies.send(response);
+                });
+                in_flight.insert(key, call.abort_handle());
+            }
+            _ => {
+                let _ = replies.send(error_response(
+                    id,
+                    METHOD_NOT_FOUND,
+                    format!("Method not found: {}", method),
+                ));
+            }
+        }
+    }
+
+    // Calls still running hold their own sender, so the writer lets them finish
+    drop(replies);
+    let _ = writer.await;
+    Ok(())
+}
+
+fn initialize_result(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| SUPPORTED_PROTOCOL_VERSIONS.contains(v))
+        .unwrap_or(SUPPORTED_PROTOCOL_VERSIONS[0]);
+
+    json!({
+        "protocolVersion": version,
+        "capabilities": {"tools": {"listChanged": false}},
+        "serverInfo": {"name": "zeteo", "version": env!("CARGO_PKG_VERSION")}
+    })
+}
+
+fn tool_list(executor: &ToolExecutor) -> Vec<Value> {
+    executor
+        .definitions()
+        .into_iter()
+        .map(|tool| {
+            json!({
+                "name": tool.function.name,
+                "description": tool.function.description,
+                "inputSchema": tool.function.parameters
+            })
+        })
+        .collect()
+}
+
+/// Run a tool. A failing tool is reported in the result with `isError` so the
+/// calling model can read it; only malformed calls are protocol errors.
+async fn call_tool(executor: &ToolExecutor, params: &Value) -> Result<Value, McpError> {
+    let invalid = |message: String| McpError {
+        code: INVALID_PARAMS,
+        message,
+        data: None,
+    };
+
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("Missing tool name".to_string()))?;
+    if !executor
+        .definitions()
+        .iter()
+        .any(|tool| tool.function.name == name)
+    {
+        return Err(invalid(format!("Unknown tool: {}", name)));
+    }
+    let arguments = params
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+
+    Ok(match executor.execute(name, &arguments.to_string()).await {
+        Ok(text) => json!({"content": [{"type": "text", "text": text}], "isError": false}),
+        Err(e) => json!({
+            "content": [{"type": "text", "text": format!("{:#}", e)}],
+            "isError": true
+        }),
+    })
+}
+
+fn result_response(id: Value, result: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "result": result})
+}
+
+fn error_response(id: Value, code: i32, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": McpError {
+            code,
+            message,
+            data: None
+        }
+    })
+}
@@ -0,0 +1,181 @@
+//! Keeps the configured MCP servers running. Each server gets a task that
+//! checks its process or connection, pings it now and then, and restarts it
+//! with exponential backoff when it goes down, re-running `initialize` and
+//! tool discovery.
+
+use std::sync::Weak;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+use super::registry::{start_server, McpRegistry, ServerState};
+use super::McpClient;
+
+/// How closely servers are watched and how quickly they are brought back
+#[derive(Debug, Clone)]
+pub struct Supervision {
+    /// How often to check that a server's process or connection is still up
+    pub check_interval: Duration,
+    /// How often to ping a server that looks alive
+    pub ping_interval: Duration,
+    pub ping_timeout: Duration,
+    /// Wait before the first restart; doubles with each consecutive failure
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for Supervision {
+    fn default() -> Self {
+        Self {
+            check_interval: Duration::from_secs(1),
+            ping_interval: Duration::from_secs(30),
+            ping_timeout: Duration::from_secs(10),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Supervision {
+    /// Wait before restart attempt `attempt` (1-based)
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Why a running server was taken down
+enum Down {
+    Exited,
+    Unresponsive(String),
+    /// The user asked for a restart
+    Requested,
+}
+
+/// Supervise one server until the registry is dropped
+pub(super) async fn supervise(registry: Weak<McpRegistry>, name: String, supervision: Supervision) {
+    let Some(restart) = registry.upgrade().and_then(|r| r.restart_signal(&name)) else {
+        return;
+    };
+    // Consecutive failed starts or short-lived runs
+    let mut failures: u32 = match registry.upgrade() {
+        Some(registry) if registry.running_client(&name).is_some() => 0,
+        Some(_) => 1,
+        None => return,
+    };
+
+    loop {
+        let client = registry.upgrade().and_then(|r| r.running_client(&name));
+        if let Some(client) = client {
+            let Some(down) = watch(&registry, &client, &supervision, &restart).await else {
+                return;
+            };
+            drop(client);
+            let Some(registry) = registry.upgrade() else {
+                return;
+            };
+            let reason = match &down {
+                Down::Exited => Some("server exited".to_string()),
+                Down::Unresponsive(error) => Some(format!("not responding: {}", error)),
+                Down::Requested => None,
+            };
+            let uptime = registry.take_down(&name, reason);
+            failures = match down {
+                Down::Requested => 0,
+                // A server that ran for a good while starts over with the shortest wait
+                _ if uptime >= supervision.max_backoff => 1,
+                _ => failures + 1,
+            };
+        }
+
+        if failures > 0 {
+            let delay = supervision.backoff(failures);
+            match registry.upgrade() {
+                Some(registry) => registry.set_state(
+                    &name,
+                    ServerState::Restarting {
+                        attempt: failures,
+                        retry_at: Instant::now() + delay,
+                    },
+                ),
+                None => return,
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = restart.notified() => failures = 0,
+            }
+        }
+
+        let Some(config) = registry.upgrade().and_then(|r| r.config_of(&name)) else {
+            return;
+        };
+        let started = start_server(&name, &config).await;
+        let Some(registry) = registry.upgrade() else {
+            return;
+        };
+        match started {
+            Ok(started) => {
+                registry.install(&name, started);
+                registry.renew_subscriptions(&name).await;
+            }
+            Err(failure) => {
+                registry.record_error(&name, failure);
+                failures += 1;
+            }
+        }
+    }
+}
+
+/// Wait for a running server to go down. Returns `None` once the registry is
+/// gone, so the client can be dropped and the server stopped.
+async fn watch(
+    registry: &Weak<McpRegistry>,
+    client: &McpClient,
+    supervision: &Supervision,
+    restart: &Notify,
+) -> Option<Down> {
+    let mut last_ping = Instant::now();
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(supervision.check_interval) => {}
+            _ = restart.notified() => return Some(Down::Requested),
+        }
+        if registry.strong_count() == 0 {
+            return None;
+        }
+        if !client.is_alive() {
+            return Some(Down::Exited);
+        }
+
+        if last_ping.elapsed() >= supervision.ping_interval {
+            last_ping = Instant::now();
+            let ping = tokio::time::timeout(supervision.ping_timeout, client.ping()).await;
+            match ping {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => return Some(Down::Unresponsive(format!("{:#}", e))),
+                Err(_) => {
+                    return Some(Down::Unresponsive(format!(
+                        "ping timed out after {}s",
+                        supervision.ping_timeout.as_secs_f64()
+                    )))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        let supervision = Supervision::default();
+        let waits: Vec<u64> = (1..=8)
+            .map(|attempt| supervision.backoff(attempt).as_secs())
+            .collect();
+        assert_eq!(waits, vec![1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(supervision.backoff(100), supervision.max_backoff);
+    }
+}
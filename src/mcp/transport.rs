@@ -0,0 +1,528 @@
+//! Ways of reaching an MCP server: a local process on stdio, or a remote
+//! service over Streamable HTTP with a fallback to the older HTTP+SSE transport.
+
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::sse::{SseDecoder, SseEvent};
+
+/// Lines of server stderr kept for diagnostics
+const STDERR_LINES: usize = 200;
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// JSON-RPC error code for transport failures reported back to a waiting request
+const TRANSPORT_ERROR: i32 = -32000;
+
+/// Moves JSON-RPC messages between the client and one server
+pub trait Transport: Send + Sync {
+    /// Start the connection. Each line from `outgoing` is sent to the server and
+    /// each message from the server is passed to `incoming`, which is dropped
+    /// once the connection has ended.
+    fn start(
+        &mut self,
+        outgoing: mpsc::UnboundedReceiver<String>,
+        incoming: mpsc::UnboundedSender<String>,
+    ) -> Result<()>;
+
+    fn is_alive(&mut self) -> bool;
+
+    /// End the connection; called when the client is dropped
+    fn close(&mut self);
+
+    /// The most recent lines a local server wrote to stderr
+    fn stderr_tail(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// A server process spawned locally, speaking newline-delimited JSON on stdio
+pub struct StdioTransport {
+    process: Child,
+    stderr: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl StdioTransport {
+    /// Spawn the server. Must be called from within a tokio runtime.
+    pub fn spawn(command: &str, args: &[String], env: &HashMap<String, String>) -> Result<Self> {
+        let mut cmd = Command::new(command);
+        cmd.args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        for (key, value) in env {
+            cmd.env(key, value);
+        }
+
+        let process = cmd.spawn().context("Failed to start MCP server process")?;
+
+        Ok(Self {
+            process,
+            stderr: Arc::new(Mutex::new(VecDeque::new())),
+        })
+    }
+}
+
+impl Transport for StdioTransport {
+    fn start(
+        &mut self,
+        mut outgoing: mpsc::UnboundedReceiver<String>,
+        incoming: mpsc::UnboundedSender<String>,
+    ) -> Result<()> {
+        let mut stdin = self
+            .process
+            .stdin
+            .take()
+            .context("Failed to get stdin handle")?;
+        let stdout = self
+            .process
+            .stdout
+            .take()
+            .context("Failed to get stdout handle")?;
+        let stderr = self
+            .process
+            .stderr
+            .take()
+            .context("Failed to get stderr handle")?;
+
+        tokio::spawn(async move {
+            while let Some(line) = outgoing.recv().await {
+                let written = async {
+                    stdin.write_all(line.as_bytes()).await?;
+                    stdin.write_all(b"\n").await?;
+                    stdin.flush().await
+                };
+                if written.await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if incoming.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let log = self.stderr.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let mut log = log.lock().unwrap();
+                if log.len() == STDERR_LINES {
+                    log.pop_front();
+                }
+                log.push_back(line);
+            }
+        });
+
+        Ok(())
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.process.try_wait(), Ok(None))
+    }
+
+    fn close(&mut self) {
+        let _ = self.process.start_kill();
+    }
+
+    fn stderr_tail(&self) -> Vec<String> {
+        self.stderr.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// A remote server. Messages are POSTed to the endpoint per the Streamable
+/// HTTP transport; if the endpoint rejects that, the legacy HTTP+SSE
+/// transport is used instead.
+pub struct HttpTransport {
+    url: reqwest::Url,
+    client: reqwest::Client,
+    alive: Arc<AtomicBool>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str, headers: &HashMap<String, String>) -> Result<Self> {
+        let url = reqwest::Url::parse(url).with_context(|| format!("Invalid MCP url '{}'", url))?;
+
+        let mut default_headers = HeaderMap::new();
+        for (name, value) in headers {
+            default_headers.insert(
+                HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("Invalid header name '{}'", name))?,
+                HeaderValue::from_str(value)
+                    .with_context(|| format!("Invalid value for header '{}'", name))?,
+            );
+        }
+
+        let client = reqwest::Client::builder()
+            .default_headers(default_headers)
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        Ok(Self {
+            url,
+            client,
+            alive: Arc::new(AtomicBool::new(false)),
+            shutdown: None,
+        })
+    }
+}
+
+impl Transport for HttpTransport {
+    fn start(
+        &mut self,
+        outgoing: mpsc::UnboundedReceiver<String>,
+        incoming: mpsc::UnboundedSender<String>,
+    ) -> Result<()> {
+        let (shutdown, shutdown_rx) = oneshot::channel();
+        self.shutdown = Some(shutdown);
+        self.alive.store(true, Ordering::SeqCst);
+
+        let connection = HttpConnection {
+            url: self.url.clone(),
+            client: self.client.clone(),
+            incoming,
+        };
+        let alive = self.alive.clone();
+        tokio::spawn(async move {
+            connection.run(outgoing, shutdown_rx).await;
+            alive.store(false, Ordering::SeqCst);
+        });
+
+        Ok(())
+    }
+
+    fn is_alive(&mut self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    fn close(&mut self) {
+        self.shutdown.take();
+    }
+}
+
+/// How messages reach the server once the first one has gone through
+#[derive(Clone)]
+enum HttpMode {
+    Streamable {
+        session: Option<String>,
+    },
+    /// Legacy transport: POST to the endpoint the SSE stream announced,
+    /// replies arrive on the stream
+    Sse {
+        endpoint: reqwest::Url,
+    },
+}
+
+/// Outcome of POSTing one message
+enum PostError {
+    /// The endpoint doesn't speak Streamable HTTP
+    Unsupported(reqwest::StatusCode),
+    Failed(anyhow::Error),
+}
+
+#[derive(Clone)]
+struct HttpConnection {
+    url: reqwest::Url,
+    client: reqwest::Client,
+    incoming: mpsc::UnboundedSender<String>,
+}
+
+impl HttpConnection {
+    async fn run(
+        self,
+        mut outgoing: mpsc::UnboundedReceiver<String>,
+        mut shutdown: oneshot::Receiver<()>,
+    ) {
+        let mut mode: Option<HttpMode> = None;
+        let (stream_closed, mut stream_closed_rx) = oneshot::channel::<()>();
+        let mut stream_closed = Some(stream_closed);
+        let mut sse_stream = None;
+
+        loop {
+            let message = tokio::select! {
+                message = outgoing.recv() => match message {
+                    Some(message) => message,
+                    None => break,
+                },
+                _ = &mut shutdown => break,
+                _ = &mut stream_closed_rx, if sse_stream.is_some() => break,
+            };
+
+            let Some(current) = mode.clone() else {
+                // The first message (initialize) decides the transport, so it
+                // is sent before anything else
+                match self.post_streamable(&message, None).await {
+                    Ok(session) => mode = Some(HttpMode::Streamable { session }),
+                    Err(PostError::Unsupported(_)) => {
+                        match self.open_sse(stream_closed.take()).await {
+                            Ok((endpoint, task)) => {
+                                sse_stream = Some(task);
+                                if let Err(e) = self.post_sse(&endpoint, &message).await {
+                                    self.fail(&message, e);
+                                }
+                                mode = Some(HttpMode::Sse { endpoint });
+                            }
+                            Err(e) => {
+                                self.fail(&message, e);
+                                break;
+                            }
+                        }
+                    }
+                    // Without a transport nothing else can be sent, and the
+                    // next message must not be taken for the probe
+                    Err(PostError::Failed(e)) => {
+                        self.fail(&message, e);
+                        break;
+                    }
+                }
+                continue;
+            };
+
+            let request = is_request(&message);
+            let connection = self.clone();
+            let send = async move {
+                let result = match &current {
+                    HttpMode::Streamable { session } => connection
+                        .post_streamable(&message, session.as_deref())
+                        .await
+                        .map(|_| ())
+                        .map_err(|e| match e {
+                            PostError::Unsupported(status) => anyhow!("HTTP {}", status),
+                            PostError::Failed(e) => e,
+                        }),
+                    HttpMode::Sse { endpoint } => connection.post_sse(endpoint, &message).await,
+                };
+                if let Err(e) = result {
+                    connection.fail(&message, e);
+                }
+            };
+            // Requests run side by side, as a reply may stream for a while.
+            // Notifications are sent in place, so that the server sees
+            // `notifications/initialized` before any request that follows it.
+            if request {
+                tokio::spawn(send);
+            } else {
+                send.await;
+            }
+        }
+
+        if let Some(task) = sse_stream {
+            task.abort();
+        }
+        // Let the server free the session rather than wait for it to expire
+        if let Some(HttpMode::Streamable {
+            session: Some(session),
+        }) = mode
+        {
+            let _ = self
+                .client
+                .delete(self.url.clone())
+                .header(SESSION_HEADER, session)
+                .send()
+                .await;
+        }
+    }
+
+    /// POST one message and forward whatever the server answers with, either a
+    /// JSON body or an SSE stream. Returns the session ID the server assigned.
+    async fn post_streamable(
+        &self,
+        message: &str,
+        session: Option<&str>,
+    ) -> std::result::Result<Option<String>, PostError> {
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header(ACCEPT, "application/json, text/event-stream")
+            .header(CONTENT_TYPE, "application/json")
+            .body(message.to_string());
+        if let Some(session) = session {
+            request = request.header(SESSION_HEADER, session);
+        }
+
+        let mut response = request
+            .send()
+            .await
+            .map_err(|e| PostError::Failed(anyhow!("Failed to reach MCP server: {}", e)))?;
+
+        let status = response.status();
+        // Legacy servers answer a POST to the SSE URL with 404, 405 or 400
+        // (and behind some proxies other 4xx); SSE is tried on any of them
+        if session.is_none() && status.is_client_error() {
+            return Err(PostError::Unsupported(status));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(PostError::Failed(anyhow!(
+                "MCP server returned HTTP {}: {}",
+                status,
+                body
+            )));
+        }
+
+        let session = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let is_stream = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+
+        if is_stream {
+            let mut decoder = SseDecoder::default();
+            while let Some(chunk) = response
+                .chunk()
+                .await
+                .map_err(|e| PostError::Failed(anyhow!("MCP stream interrupted: {}", e)))?
+            {
+                for event in decoder.feed(&chunk) {
+                    self.forward(&event.data);
+                }
+            }
+        } else {
+            let body = response
+                .text()
+                .await
+                .map_err(|e| PostError::Failed(anyhow!("Failed to read MCP response: {}", e)))?;
+            self.forward(&body);
+        }
+
+        Ok(session)
+    }
+
+    /// Open the legacy SSE stream and wait for the endpoint event. The stream
+    /// keeps being read in the returned task; `closed` fires when it ends.
+    async fn open_sse(
+        &self,
+        closed: Option<oneshot::Sender<()>>,
+    ) -> Result<(reqwest::Url, tokio::task::JoinHandle<()>)> {
+        let mut response = self
+            .client
+            .get(self.url.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await
+            .context("Failed to open MCP SSE stream")?;
+        if !response.status().is_success() {
+            bail!(
+                "MCP server rejected both Streamable HTTP and SSE (HTTP {})",
+                response.status()
+            );
+        }
+
+        let mut decoder = SseDecoder::default();
+        let (endpoint, early) = loop {
+            let Some(chunk) = response.chunk().await? else {
+                bail!("MCP SSE stream ended before announcing an endpoint");
+            };
+            let mut events = decoder.feed(&chunk).into_iter();
+            if let Some(event) = events.find(|e| e.event.as_deref() == Some("endpoint")) {
+                break (event.data, events.collect::<Vec<_>>());
+            }
+        };
+        let endpoint = self
+            .url
+            .join(endpoint.trim())
+            .context("Invalid endpoint from MCP SSE stream")?;
+
+        let connection = self.clone();
+        let task = tokio::spawn(async move {
+            for event in early {
+                connection.forward_sse(&event);
+            }
+            while let Ok(Some(chunk)) = response.chunk().await {
+                for event in decoder.feed(&chunk) {
+                    connection.forward_sse(&event);
+                }
+            }
+            if let Some(closed) = closed {
+                let _ = closed.send(());
+            }
+        });
+
+        Ok((endpoint, task))
+    }
+
+    async fn post_sse(&self, endpoint: &reqwest::Url, message: &str) -> Result<()> {
+        let response = self
+            .client
+            .post(endpoint.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(message.to_string())
+            .send()
+            .await
+            .context("Failed to reach MCP server")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            bail!("MCP server returned HTTP {}: {}", status, body);
+        }
+        Ok(())
+    }
+
+    fn forward_sse(&self, event: &SseEvent) {
+        if matches!(event.event.as_deref(), None | Some("message")) {
+            self.forward(&event.data);
+        }
+    }
+
+    /// Pass a server payload on, splitting JSON-RPC batches
+    fn forward(&self, payload: &str) {
+        match serde_json::from_str::<Value>(payload) {
+            Ok(Value::Array(messages)) => {
+                for message in messages {
+                    let _ = self.incoming.send(message.to_string());
+                }
+            }
+            Ok(_) => {
+                let _ = self.incoming.send(payload.to_string());
+            }
+            // Empty bodies (202 Accepted) and keep-alives
+            Err(_) => {}
+        }
+    }
+
+    /// Answer a request that couldn't be delivered with an error, so the
+    /// caller fails now instead of at its timeout
+    fn fail(&self, message: &str, error: anyhow::Error) {
+        let Ok(message) = serde_json::from_str::<Value>(message) else {
+            return;
+        };
+        if let (Some(id), Some(_)) = (message.get("id"), message.get("method")) {
+            let _ = self.incoming.send(
+                serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {"code": TRANSPORT_ERROR, "message": format!("{:#}", error)}
+                })
+                .to_string(),
+            );
+        }
+    }
+}
+
+/// Whether a JSON-RPC message expects a reply (notifications and our own
+/// replies to the server don't)
+fn is_request(message: &str) -> bool {
+    serde_json::from_str::<Value>(message)
+        .is_ok_and(|message| message.get("id").is_some() && message.get("method").is_some())
+}
@@ -0,0 +1,633 @@
+use super::{
+    AiProvider, ChatRequest, ChatResponse, FunctionCall, Message, ProviderError, Tool, ToolCall,
+    Usage,
+};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+#[derive(Clone)]
+pub struct AnthropicProvider {
+    api_key: String,
+    model: String,
+    base_url: String,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct AnthropicRequest {
+    model: String,
+    messages: Vec<AnthropicMessage>,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AnthropicTool>>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct AnthropicMessage {
+    role: String,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: serde_json::Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+    },
+    /// Block types this client doesn't use, such as thinking
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Serialize)]
+struct AnthropicTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnthropicResponse {
+    content: Vec<ContentBlock>,
+    model: String,
+    usage: Option<AnthropicUsage>,
+}
+
+/// `input_tokens` excludes tokens read from or written to the prompt cache
+#[derive(Deserialize, Debug, Default)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+}
+
+impl From<AnthropicUsage> for Usage {
+    fn from(u: AnthropicUsage) -> Self {
+        Usage {
+            prompt_tokens: u.input_tokens
+                + u.cache_creation_input_tokens
+                + u.cache_read_input_tokens,
+            completion_tokens: u.output_tokens,
+            cached_tokens: u.cache_read_input_tokens,
+        }
+    }
+}
+
+/// Server-sent event payloads of the streaming Messages API
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart {
+        message: StreamMessageStart,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: StreamDelta,
+    },
+    ContentBlockStop {
+        #[allow(dead_code)]
+        index: usize,
+    },
+    MessageDelta {
+        usage: Option<AnthropicUsage>,
+    },
+    MessageStop,
+    Ping,
+    Error {
+        error: StreamError,
+    },
+    /// Events added to the API after this client was written
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamMessageStart {
+    model: String,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Deserialize, Debug)]
+struct StreamError {
+    message: String,
+}
+
+/// Content block being assembled from stream deltas
+enum PartialBlock {
+    Text(String),
+    ToolUse {
+        id: String,
+        name: String,
+        input_json: String,
+    },
+}
+
+impl AnthropicProvider {
+    pub fn new(api_key: String, model: Option<String>) -> Self {
+        AnthropicProvider {
+            api_key,
+            model: model.unwrap_or_else(|| "claude-sonnet-4-5".to_string()),
+            base_url: "https://api.anthropic.com".to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Override the API base URL (e.g. for a proxy or gateway)
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    fn build_request(&self, request: ChatRequest, stream: bool) -> AnthropicRequest {
+        let (system, messages) = convert_messages(&request.messages);
+
+        AnthropicRequest {
+            model: self.model.clone(),
+            messages,
+            max_tokens: request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            system,
+            temperature: request.temperature,
+            tools: request.tools.as_ref().map(|t| convert_tools(t)),
+            stream,
+        }
+    }
+
+    async fn send(&self, body: &AnthropicRequest) -> Result<reqwest::Response> {
+        let url = format!("{}/v1/messages", self.base_url.trim_end_matches('/'));
+
+        let response = self
+            .client
+            .post(&url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(body)
+            .send()
+            .await
+            .context("Failed to send request to Anthropic")?;
+
+        if !response.status().is_success() {
+            return Err(ProviderError::from_response("Anthropic", response)
+                .await
+                .into());
+        }
+
+        Ok(response)
+    }
+}
+
+/// Split out system messages into the top-level `system` field and convert the
+/// remaining history into alternating user/assistant turns with content blocks.
+fn convert_messages(messages: &[Message]) -> (Option<String>, Vec<AnthropicMessage>) {
+    let mut system_parts: Vec<&str> = Vec::new();
+    let mut converted: Vec<AnthropicMessage> = Vec::new();
+
+    for m in messages {
+        let (role, blocks) = match m.role.as_str() {
+            "system" => {
+                system_parts.push(&m.content);
+                continue;
+            }
+            "tool" => (
+                "user",
+                vec![ContentBlock::ToolResult {
+                    tool_use_id: m.tool_call_id.clone().unwrap_or_default(),
+                    content: m.content.clone(),
+                }],
+            ),
+            "assistant" => {
+                let mut blocks = Vec::new();
+                if !m.content.is_empty() {
+                    blocks.push(ContentBlock::Text {
+                        text: m.content.clone(),
+                    });
+                }
+                if let Some(tool_calls) = &m.tool_calls {
+                    blocks.extend(tool_calls.iter().map(|tc| {
+                        ContentBlock::ToolUse {
+                            id: tc.id.clone(),
+                            name: tc.function.name.clone(),
+                            input: serde_json::from_str(&tc.function.arguments)
+                                .unwrap_or_else(|_| serde_json::json!({})),
+                        }
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            _ => (
+                "user",
+                vec![ContentBlock::Text {
+                    text: m.content.clone(),
+                }],
+            ),
+        };
+
+        if blocks.is_empty() {
+            continue;
+        }
+
+        // The Messages API requires strictly alternating roles, so consecutive
+        // tool results (and any user text following them) share one user turn.
+        match converted.last_mut() {
+            Some(last) if last.role == role => last.content.extend(blocks),
+            _ => converted.push(AnthropicMessage {
+                role: role.to_string(),
+                content: blocks,
+            }),
+        }
+    }
+
+    let system = if system_parts.is_empty() {
+        None
+    } else {
+        Some(system_parts.join("\n\n"))
+    };
+
+    (system, converted)
+}
+
+fn convert_tools(tools: &[Tool]) -> Vec<AnthropicTool> {
+    tools
+        .iter()
+        .map(|t| AnthropicTool {
+            name: t.function.name.clone(),
+            description: t.function.description.clone(),
+            input_schema: t.function.parameters.clone(),
+        })
+        .collect()
+}
+
+fn convert_response(
+    blocks: Vec<ContentBlock>,
+    model: String,
+    usage: Option<Usage>,
+) -> ChatResponse {
+    let mut content = String::new();
+    let mut tool_calls = Vec::new();
+
+    for block in blocks {
+        match block {
+            ContentBlock::Text { text } => content.push_str(&text),
+            ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                id,
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name,
+                    arguments: serde_json::to_string(&input).unwrap_or_default(),
+                },
+            }),
+            ContentBlock::ToolResult { .. } | ContentBlock::Unknown => {}
+        }
+    }
+
+    ChatResponse {
+        content,
+        model,
+        tool_calls: if tool_calls.is_empty() {
+            None
+        } else {
+            Some(tool_calls)
+        },
+        provider: None,
+        usage,
+    }
+}
+
+/// Accumulates streaming events into a complete response
+struct StreamAccumulator {
+    model: String,
+    blocks: Vec<Option<PartialBlock>>,
+    usage: Option<Usage>,
+}
+
+impl StreamAccumulator {
+    fn new(model: String) -> Self {
+        Self {
+            model,
+            blocks: Vec::new(),
+            usage: None,
+        }
+    }
+
+    /// Apply one SSE `data:` payload, returning any new text to surface to the caller
+    fn apply(&mut self, data: &str) -> Result<Option<String>> {
+        let event: StreamEvent =
+            serde_json::from_str(data).context("Failed to parse Anthropic stream event")?;
+
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.model = message.model;
+                self.usage = message.usage.map(Usage::from);
+            }
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => {
+                if self.blocks.len() <= index {
+                    self.blocks.resize_with(index + 1, || None);
+                }
+                self.blocks[index] = match content_block {
+                    ContentBlock::Text { text } => Some(PartialBlock::Text(text)),
+                    ContentBlock::ToolUse { id, name, .. } => Some(PartialBlock::ToolUse {
+                        id,
+                        name,
+                        input_json: String::new(),
+                    }),
+                    ContentBlock::ToolResult { .. } | ContentBlock::Unknown => None,
+                };
+            }
+            StreamEvent::ContentBlockDelta { index, delta } => {
+                match (self.blocks.get_mut(index).and_then(Option::as_mut), delta) {
+                    (Some(PartialBlock::Text(text)), StreamDelta::TextDelta { text: delta }) => {
+                        text.push_str(&delta);
+                        return Ok(Some(delta));
+                    }
+                    (
+                        Some(PartialBlock::ToolUse { input_json, .. }),
+                        StreamDelta::InputJsonDelta { partial_json },
+                    ) => {
+                        input_json.push_str(&partial_json);
+                    }
+                    _ => {}
+                }
+            }
+            StreamEvent::Error { error } => {
+                anyhow::bail!("Anthropic stream error: {}", error.message);
+            }
+            StreamEvent::MessageDelta { usage } => {
+                // Carries the final output token count for the whole message
+                if let Some(delta) = usage {
+                    self.usage
+                        .get_or_insert_with(Usage::default)
+                        .completion_tokens = delta.output_tokens;
+                }
+            }
+            StreamEvent::ContentBlockStop { .. }
+            | StreamEvent::MessageStop
+            | StreamEvent::Ping
+            | StreamEvent::Unknown => {}
+        }
+
+        Ok(None)
+    }
+
+    fn finish(self) -> ChatResponse {
+        let blocks = self
+            .blocks
+            .into_iter()
+            .flatten()
+            .map(|block| match block {
+                PartialBlock::Text(text) => ContentBlock::Text { text },
+                PartialBlock::ToolUse {
+                    id,
+                    name,
+                    input_json,
+                } => ContentBlock::ToolUse {
+                    id,
+                    name,
+                    input: if input_json.trim().is_empty() {
+                        serde_json::json!({})
+                    } else {
+                        serde_json::from_str(&input_json).unwrap_or_else(|_| serde_json::json!({}))
+                    },
+                },
+            })
+            .collect();
+
+        convert_response(blocks, self.model, self.usage)
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProvider for AnthropicProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+        let body = self.build_request(request, false);
+        let response = self.send(&body).await?;
+
+        let anthropic_response: AnthropicResponse = response
+            .json()
+            .await
+            .context("Failed to parse Anthropic response")?;
+
+        Ok(convert_response(
+            anthropic_response.content,
+            anthropic_response.model,
+            anthropic_response.usage.map(Usage::from),
+        ))
+    }
+
+    async fn chat_stream(
+        &self,
+        request: ChatRequest,
+        on_delta: &(dyn for<'s> Fn(&'s str) + Send + Sync),
+    ) -> Result<ChatResponse> {
+        let body = self.build_request(request, true);
+        let mut response = self.send(&body).await?;
+
+        let mut decoder = crate::sse::SseDecoder::default();
+        let mut accumulator = StreamAccumulator::new(self.model.clone());
+
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .context("Failed to read Anthropic stream")?
+        {
+            for event in decoder.feed(&chunk) {
+                if let Some(text) = accumulator.apply(&event.data)? {
+                    on_delta(&text);
+                }
+            }
+        }
+
+        Ok(accumulator.finish())
+    }
+
+    fn provider_name(&self) -> &str {
+        "Anthropic"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    #[test]
+    fn test_system_prompt_is_top_level() {
+        let (system, messages) = convert_messages(&[
+            message("system", "You are Zeteo"),
+            message("user", "Any errors?"),
+        ]);
+
+        assert_eq!(system.as_deref(), Some("You are Zeteo"));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, "user");
+    }
+
+    #[test]
+    fn test_tool_exchange_conversion() {
+        let mut assistant = message("assistant", "");
+        assistant.tool_calls = Some(vec![
+            ToolCall {
+                id: "toolu_1".to_string(),
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name: "query_logs".to_string(),
+                    arguments: r#"{"query":"error"}"#.to_string(),
+                },
+            },
+            ToolCall {
+                id: "toolu_2".to_string(),
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name: "list_services".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            },
+        ]);
+        let mut result_1 = message("tool", "[]");
+        result_1.tool_call_id = Some("toolu_1".to_string());
+        let mut result_2 = message("tool", "[\"api\"]");
+        result_2.tool_call_id = Some("toolu_2".to_string());
+
+        let (_, messages) = convert_messages(&[
+            message("user", "Any errors?"),
+            assistant,
+            result_1,
+            result_2,
+        ]);
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].role, "assistant");
+        assert_eq!(messages[1].content.len(), 2);
+        assert!(matches!(
+            &messages[1].content[0],
+            ContentBlock::ToolUse { input, .. } if input["query"] == "error"
+        ));
+        assert_eq!(messages[2].role, "user");
+        assert_eq!(messages[2].content.len(), 2);
+        assert!(matches!(
+            &messages[2].content[1],
+            ContentBlock::ToolResult { tool_use_id, .. } if tool_use_id == "toolu_2"
+        ));
+    }
+
+    #[test]
+    fn test_response_conversion() {
+        let json = r#"{
+            "model": "claude-sonnet-4-5",
+            "content": [
+                {"type": "thinking", "thinking": "Timeouts first.", "signature": "c2ln"},
+                {"type": "text", "text": "Let me check."},
+                {"type": "tool_use", "id": "toolu_1", "name": "query_logs", "input": {"query": "timeout"}}
+            ],
+            "usage": {"input_tokens": 20, "cache_read_input_tokens": 100, "output_tokens": 15}
+        }"#;
+        let response: AnthropicResponse = serde_json::from_str(json).unwrap();
+        let chat = convert_response(
+            response.content,
+            response.model,
+            response.usage.map(Usage::from),
+        );
+
+        let usage = chat.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 120);
+        assert_eq!(usage.cached_tokens, 100);
+        assert_eq!(usage.completion_tokens, 15);
+
+        assert_eq!(chat.content, "Let me check.");
+        let tool_calls = chat.tool_calls.unwrap();
+        assert_eq!(tool_calls[0].id, "toolu_1");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"query":"timeout"}"#);
+    }
+
+    #[test]
+    fn test_stream_accumulation() {
+        let events = [
+            r#"{"type":"message_start","message":{"model":"claude-sonnet-4-5","usage":{"input_tokens":42,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Check"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"ing"}}"#,
+            r#"{"type":"some_future_event","detail":{"x":1}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"tool_use","id":"toolu_1","name":"query_logs","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"{\"query\":"}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"input_json_delta","partial_json":"\"error\"}"}}"#,
+            r#"{"type":"content_block_stop","index":1}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":17}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+
+        let mut accumulator = StreamAccumulator::new(String::new());
+        let mut streamed = String::new();
+        for event in events {
+            if let Some(text) = accumulator.apply(event).unwrap() {
+                streamed.push_str(&text);
+            }
+        }
+
+        let response = accumulator.finish();
+        assert_eq!(streamed, "Checking");
+        assert_eq!(response.content, "Checking");
+        assert_eq!(response.model, "claude-sonnet-4-5");
+        let tool_calls = response.tool_calls.unwrap();
+        assert_eq!(tool_calls[0].function.name, "query_logs");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"query":"error"}"#);
+
+        let usage = response.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 42);
+        assert_eq!(usage.completion_tokens, 17);
+    }
+}
@@ -0,0 +1,502 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Tokens are refreshed this long before they expire, so a request never
+/// goes out with one that lapses in flight
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const GOOGLE_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+const METADATA_HOST: &str = "metadata.google.internal";
+
+const AZURE_AUTHORITY_HOST: &str = "https://login.microsoftonline.com";
+const COGNITIVE_SERVICES_SCOPE: &str = "https://cognitiveservices.azure.com/.default";
+
+/// A bearer token and the moment it stops being valid
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub token: String,
+    pub expires_at: Instant,
+}
+
+impl AccessToken {
+    pub fn new(token: String, expires_in: u64) -> Self {
+        Self {
+            token,
+            expires_at: Instant::now() + Duration::from_secs(expires_in),
+        }
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.expires_at > Instant::now() + EXPIRY_MARGIN
+    }
+}
+
+/// Holds one access token, fetching a new one only when it is about to expire.
+/// Concurrent callers wait on the same refresh instead of each starting one.
+#[derive(Default)]
+pub struct TokenCache {
+    token: Mutex<Option<AccessToken>>,
+}
+
+impl TokenCache {
+    pub async fn get_or_refresh<F, Fut>(&self, fetch: F) -> Result<String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<AccessToken>>,
+    {
+        let mut token = self.token.lock().await;
+
+        if let Some(cached) = token.as_ref().filter(|t| t.is_fresh()) {
+            return Ok(cached.token.clone());
+        }
+
+        let fresh = fetch().await?;
+        let value = fresh.token.clone();
+        *token = Some(fresh);
+        Ok(value)
+    }
+}
+
+/// Standard OAuth 2.0 token endpoint response
+#[derive(Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    #[serde(default = "default_expires_in")]
+    pub expires_in: u64,
+}
+
+fn default_expires_in() -> u64 {
+    3600
+}
+
+impl From<TokenResponse> for AccessToken {
+    fn from(response: TokenResponse) -> Self {
+        AccessToken::new(response.access_token, response.expires_in)
+    }
+}
+
+/// Contents of a Google credentials JSON file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GoogleCredentials {
+    ServiceAccount {
+        client_email: String,
+        private_key: String,
+        private_key_id: Option<String>,
+        #[serde(default = "default_token_uri")]
+        token_uri: String,
+    },
+    /// Written by `gcloud auth application-default login`
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+        #[serde(default = "default_token_uri")]
+        token_uri: String,
+    },
+}
+
+fn default_token_uri() -> String {
+    GOOGLE_TOKEN_URI.to_string()
+}
+
+#[derive(Serialize)]
+struct JwtClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64,
+}
+
+impl GoogleCredentials {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read credentials file {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Unsupported credentials file {}", path.display()))
+    }
+
+    /// Exchange these credentials for a cloud-platform access token
+    pub async fn fetch_token(&self, client: &reqwest::Client) -> Result<AccessToken> {
+        let request = match self {
+            GoogleCredentials::ServiceAccount {
+                client_email,
+                private_key,
+                private_key_id,
+                token_uri,
+            } => {
+                let assertion =
+                    sign_service_account_jwt(client_email, private_key, private_key_id, token_uri)?;
+                client.post(token_uri).form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", assertion.as_str()),
+                ])
+            }
+            GoogleCredentials::AuthorizedUser {
+                client_id,
+                client_secret,
+                refresh_token,
+                token_uri,
+            } => client.post(token_uri).form(&[
+                ("grant_type", "refresh_token"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("refresh_token", refresh_token.as_str()),
+            ]),
+        };
+
+        request_token(request, "Google OAuth").await
+    }
+}
+
+fn sign_service_account_jwt(
+    client_email: &str,
+    private_key: &str,
+    private_key_id: &Option<String>,
+    token_uri: &str,
+) -> Result<String> {
+    let iat = chrono::Utc::now().timestamp() as u64;
+    let claims = JwtClaims {
+        iss: client_email,
+        scope: CLOUD_PLATFORM_SCOPE,
+        aud: token_uri,
+        iat,
+        exp: iat + 3600,
+    };
+
+    let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+    header.kid = private_key_id.clone();
+
+    let key = jsonwebtoken::EncodingKey::from_rsa_pem(private_key.as_bytes())
+        .context("Invalid service account private key")?;
+    jsonwebtoken::encode(&header, &claims, &key).context("Failed to sign service account JWT")
+}
+
+/// Send a token request and parse the standard response
+pub async fn request_token(request: reqwest::RequestBuilder, issuer: &str) -> Result<AccessToken> {
+    let response = request
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach {} token endpoint", issuer))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!("{} token request failed ({}): {}", issuer, status, body);
+    }
+
+    let token: TokenResponse = response
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse {} token response", issuer))?;
+    Ok(token.into())
+}
+
+/// Where Google access tokens come from
+#[derive(Debug, Clone)]
+enum GoogleTokenSource {
+    Credentials(GoogleCredentials),
+    /// GCE / GKE / Cloud Run metadata server
+    Metadata(String),
+}
+
+/// Google Application Default Credentials with a token cache.
+///
+/// Lookup order follows the client libraries: `GOOGLE_APPLICATION_CREDENTIALS`,
+/// the gcloud well-known file, then the metadata server. The source is
+/// resolved on the first token request, so constructing this never fails.
+pub struct GoogleAuth {
+    explicit_file: Option<PathBuf>,
+    source: Mutex<Option<GoogleTokenSource>>,
+    cache: TokenCache,
+    client: reqwest::Client,
+}
+
+impl GoogleAuth {
+    pub fn application_default() -> Self {
+        Self::with_file(None)
+    }
+
+    /// Use a specific credentials file instead of searching for one
+    pub fn from_file(path: impl Into<PathBuf>) -> Self {
+        Self::with_file(Some(path.into()))
+    }
+
+    fn with_file(explicit_file: Option<PathBuf>) -> Self {
+        Self {
+            explicit_file,
+            source: Mutex::new(None),
+            cache: TokenCache::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn access_token(&self) -> Result<String> {
+        self.cache
+            .get_or_refresh(|| async {
+                let source = self.source().await?;
+                match source {
+                    GoogleTokenSource::Credentials(credentials) => {
+                        credentials.fetch_token(&self.client).await
+                    }
+                    GoogleTokenSource::Metadata(host) => {
+                        let url = format!(
+                            "http://{}/computeMetadata/v1/instance/service-accounts/default/token",
+                            host
+                        );
+                        request_token(
+                            self.client.get(url).header("Metadata-Flavor", "Google"),
+                            "GCE metadata",
+                        )
+                        .await
+                    }
+                }
+            })
+            .await
+    }
+
+    async fn source(&self) -> Result<GoogleTokenSource> {
+        let mut source = self.source.lock().await;
+        if let Some(source) = source.as_ref() {
+            return Ok(source.clone());
+        }
+
+        let resolved = self.discover().await?;
+        *source = Some(resolved.clone());
+        Ok(resolved)
+    }
+
+    async fn discover(&self) -> Result<GoogleTokenSource> {
+        let env_file = std::env::var_os("GOOGLE_APPLICATION_CREDENTIALS").map(PathBuf::from);
+        if let Some(path) = self.explicit_file.clone().or(env_file) {
+            return Ok(GoogleTokenSource::Credentials(
+                GoogleCredentials::from_file(&path)?,
+            ));
+        }
+
+        if let Some(path) = well_known_credentials_file().filter(|p| p.exists()) {
+            return Ok(GoogleTokenSource::Credentials(
+                GoogleCredentials::from_file(&path)?,
+            ));
+        }
+
+        let host = std::env::var("GCE_METADATA_HOST").unwrap_or_else(|_| METADATA_HOST.to_string());
+        if self.metadata_server_available(&host).await {
+            return Ok(GoogleTokenSource::Metadata(host));
+        }
+
+        anyhow::bail!(
+            "No Google credentials found. Set GOOGLE_APPLICATION_CREDENTIALS to a service account \
+             key file, or run 'gcloud auth application-default login'"
+        )
+    }
+
+    async fn metadata_server_available(&self, host: &str) -> bool {
+        let probe = self
+            .client
+            .get(format!("http://{}/computeMetadata/v1/", host))
+            .header("Metadata-Flavor", "Google")
+            .timeout(Duration::from_secs(1))
+            .send()
+            .await;
+
+        matches!(probe, Ok(r) if r.headers().get("metadata-flavor").is_some())
+    }
+}
+
+/// `application_default_credentials.json` as written by gcloud
+fn well_known_credentials_file() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)?
+    } else {
+        dirs::home_dir()?.join(".config")
+    };
+    Some(
+        base.join("gcloud")
+            .join("application_default_credentials.json"),
+    )
+}
+
+/// A Microsoft Entra ID application identity
+#[derive(Debug, Clone)]
+pub enum AzureCredential {
+    ClientSecret {
+        tenant_id: String,
+        client_id: String,
+        client_secret: String,
+    },
+    /// Federated token projected into the pod by AKS workload identity. The
+    /// file is re-read on every refresh since the kubelet rotates it.
+    WorkloadIdentity {
+        tenant_id: String,
+        client_id: String,
+        token_file: PathBuf,
+    },
+}
+
+impl AzureCredential {
+    /// Detect a credential from the standard `AZURE_*` environment variables
+    pub fn from_env() -> Option<Self> {
+        let tenant_id = std::env::var("AZURE_TENANT_ID").ok()?;
+        let client_id = std::env::var("AZURE_CLIENT_ID").ok()?;
+
+        if let Ok(token_file) = std::env::var("AZURE_FEDERATED_TOKEN_FILE") {
+            return Some(AzureCredential::WorkloadIdentity {
+                tenant_id,
+                client_id,
+                token_file: token_file.into(),
+            });
+        }
+
+        std::env::var("AZURE_CLIENT_SECRET")
+            .ok()
+            .map(|client_secret| AzureCredential::ClientSecret {
+                tenant_id,
+                client_id,
+                client_secret,
+            })
+    }
+
+    fn tenant_id(&self) -> &str {
+        match self {
+            AzureCredential::ClientSecret { tenant_id, .. }
+            | AzureCredential::WorkloadIdentity { tenant_id, .. } => tenant_id,
+        }
+    }
+}
+
+/// Entra ID (Azure AD) client-credentials flow for Azure OpenAI, with a token cache
+pub struct AzureAuth {
+    credential: AzureCredential,
+    authority_host: String,
+    cache: TokenCache,
+    client: reqwest::Client,
+}
+
+impl AzureAuth {
+    /// Authenticate against the public cloud authority
+    pub fn new(credential: AzureCredential) -> Self {
+        Self {
+            credential,
+            authority_host: AZURE_AUTHORITY_HOST.to_string(),
+            cache: TokenCache::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Use a sovereign cloud or test authority
+    pub fn with_authority_host(mut self, authority_host: String) -> Self {
+        self.authority_host = authority_host;
+        self
+    }
+
+    pub async fn access_token(&self) -> Result<String> {
+        self.cache.get_or_refresh(|| self.fetch_token()).await
+    }
+
+    async fn fetch_token(&self) -> Result<AccessToken> {
+        let url = format!(
+            "{}/{}/oauth2/v2.0/token",
+            self.authority_host.trim_end_matches('/'),
+            self.credential.tenant_id()
+        );
+
+        let request = match &self.credential {
+            AzureCredential::ClientSecret {
+                client_id,
+                client_secret,
+                ..
+            } => self.client.post(&url).form(&[
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id.as_str()),
+                ("client_secret", client_secret.as_str()),
+                ("scope", COGNITIVE_SERVICES_SCOPE),
+            ]),
+            AzureCredential::WorkloadIdentity {
+                client_id,
+                token_file,
+                ..
+            } => {
+                let assertion = tokio::fs::read_to_string(token_file)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "Failed to read federated token file {}",
+                            token_file.display()
+                        )
+                    })?;
+                self.client.post(&url).form(&[
+                    ("grant_type", "client_credentials"),
+                    ("client_id", client_id.as_str()),
+                    (
+                        "client_assertion_type",
+                        "urn:ietf:params:oauth:client-assertion-type:jwt-bearer",
+                    ),
+                    ("client_assertion", assertion.trim()),
+                    ("scope", COGNITIVE_SERVICES_SCOPE),
+                ])
+            }
+        };
+
+        request_token(request, "Entra ID").await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_token_cache_reuses_until_near_expiry() {
+        let cache = TokenCache::default();
+        let fetches = AtomicU32::new(0);
+
+        for _ in 0..3 {
+            let token = cache
+                .get_or_refresh(|| async {
+                    let n = fetches.fetch_add(1, Ordering::SeqCst);
+                    Ok(AccessToken::new(format!("token-{}", n), 3600))
+                })
+                .await
+                .unwrap();
+            assert_eq!(token, "token-0");
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Inside the expiry margin counts as expired
+        let cache = TokenCache::default();
+        for expected in ["token-1", "token-2"] {
+            let token = cache
+                .get_or_refresh(|| async {
+                    let n = fetches.fetch_add(1, Ordering::SeqCst);
+                    Ok(AccessToken::new(format!("token-{}", n), 30))
+                })
+                .await
+                .unwrap();
+            assert_eq!(token, expected);
+        }
+    }
+
+    #[test]
+    fn test_parses_credential_files() {
+        let user: GoogleCredentials = serde_json::from_str(
+            r#"{"type": "authorized_user", "client_id": "id", "client_secret": "secret", "refresh_token": "rt"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            user,
+            GoogleCredentials::AuthorizedUser { ref token_uri, .. } if token_uri == GOOGLE_TOKEN_URI
+        ));
+
+        let unsupported = serde_json::from_str::<GoogleCredentials>(
+            r#"{"type": "external_account", "audience": "x"}"#,
+        );
+        assert!(unsupported.is_err());
+    }
+}
@@ -1,15 +1,31 @@
-use super::{AiProvider, ChatRequest, ChatResponse, FunctionCall, Tool, ToolCall};
+use super::auth::AzureAuth;
+use super::{
+    AiProvider, ChatRequest, ChatResponse, FunctionCall, ProviderError, Tool, ToolCall, Usage,
+};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const DEFAULT_API_VERSION: &str = "2024-02-15-preview";
+
+/// First API version accepting `json_schema` response formats; older ones only have JSON mode
+const STRUCTURED_OUTPUT_API_VERSION: &str = "2024-08-01-preview";
 
 #[derive(Clone)]
 pub struct AzureProvider {
-    api_key: String,
+    credential: AzureCredentialKind,
     endpoint: String,
     deployment: String,
+    api_version: String,
     client: reqwest::Client,
 }
 
+#[derive(Clone)]
+enum AzureCredentialKind {
+    ApiKey(String),
+    EntraId(Arc<AzureAuth>),
+}
+
 #[derive(Serialize)]
 struct AzureRequest {
     messages: Vec<AzureMessage>,
@@ -19,6 +35,8 @@ struct AzureRequest {
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -49,6 +67,20 @@ struct AzureFunctionCall {
 #[derive(Deserialize, Debug)]
 struct AzureResponse {
     choices: Vec<AzureChoice>,
+    usage: Option<AzureUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AzureUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    prompt_tokens_details: Option<AzurePromptTokensDetails>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AzurePromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -58,13 +90,43 @@ struct AzureChoice {
 
 impl AzureProvider {
     pub fn new(api_key: String, endpoint: String, deployment: String) -> Self {
+        Self::with_credential(AzureCredentialKind::ApiKey(api_key), endpoint, deployment)
+    }
+
+    /// Authenticate with Entra ID bearer tokens instead of an API key
+    pub fn with_entra_id(auth: AzureAuth, endpoint: String, deployment: String) -> Self {
+        Self::with_credential(
+            AzureCredentialKind::EntraId(Arc::new(auth)),
+            endpoint,
+            deployment,
+        )
+    }
+
+    fn with_credential(
+        credential: AzureCredentialKind,
+        endpoint: String,
+        deployment: String,
+    ) -> Self {
         AzureProvider {
-            api_key,
+            credential,
             endpoint,
             deployment,
+            api_version: DEFAULT_API_VERSION.to_string(),
             client: reqwest::Client::new(),
         }
     }
+
+    pub fn with_api_version(mut self, api_version: String) -> Self {
+        self.api_version = api_version;
+        self
+    }
+
+    async fn authorize(&self, request: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder> {
+        Ok(match &self.credential {
+            AzureCredentialKind::ApiKey(key) => request.header("api-key", key),
+            AzureCredentialKind::EntraId(auth) => request.bearer_auth(auth.access_token().await?),
+        })
+    }
 }
 
 #[async_trait::async_trait]
@@ -110,18 +172,29 @@ impl AiProvider for AzureProvider {
             temperature: request.temperature,
             max_tokens: request.max_tokens,
             tools: request.tools,
+            response_format: request.response_format.map(|format| {
+                // API versions are dates, so they order as strings
+                if self.api_version.as_str() >= STRUCTURED_OUTPUT_API_VERSION {
+                    serde_json::json!({
+                        "type": "json_schema",
+                        "json_schema": {"name": format.name, "schema": format.schema, "strict": true},
+                    })
+                } else {
+                    serde_json::json!({"type": "json_object"})
+                }
+            }),
         };
 
         let url = format!(
-            "{}/openai/deployments/{}/chat/completions?api-version=2024-02-15-preview",
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
             self.endpoint.trim_end_matches('/'),
-            self.deployment
+            self.deployment,
+            self.api_version
         );
 
         let response = self
-            .client
-            .post(&url)
-            .header("api-key", &self.api_key)
+            .authorize(self.client.post(&url))
+            .await?
             .header("Content-Type", "application/json")
             .json(&azure_request)
             .send()
@@ -129,11 +202,9 @@ impl AiProvider for AzureProvider {
             .context("Failed to send request to Azure OpenAI")?;
 
         if !response.status().is_success() {
-            let error_text = response
-                .text()
+            return Err(ProviderError::from_response("Azure OpenAI", response)
                 .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            anyhow::bail!("Azure OpenAI API error: {}", error_text);
+                .into());
         }
 
         let azure_response: AzureResponse = response
@@ -165,10 +236,20 @@ impl AiProvider for AzureProvider {
             content,
             model: self.deployment.clone(),
             tool_calls,
+            provider: None,
+            usage: azure_response.usage.map(|u| Usage {
+                prompt_tokens: u.prompt_tokens,
+                completion_tokens: u.completion_tokens,
+                cached_tokens: u.prompt_tokens_details.map_or(0, |d| d.cached_tokens),
+            }),
         })
     }
 
     fn provider_name(&self) -> &str {
         "Azure OpenAI"
     }
+
+    fn model_name(&self) -> &str {
+        &self.deployment
+    }
 }
@@ -0,0 +1,189 @@
+use super::{AiProvider, ChatRequest, ChatResponse, ModelCapabilities, ProviderError};
+use crate::retry::{retry_with_policy, RetryAction, RetryConfig};
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Wraps a chain of providers: each one is retried on transient errors
+/// (honouring `Retry-After`), and when it keeps failing the next one in the
+/// chain is asked instead.
+///
+/// Cancellation is by dropping the future, which also abandons any pending
+/// backoff sleep.
+pub struct FallbackProvider {
+    chain: Vec<(String, Arc<dyn AiProvider>)>,
+    retry: RetryConfig,
+}
+
+impl FallbackProvider {
+    /// `chain` pairs a profile name with its provider, primary first
+    pub fn new(chain: Vec<(String, Arc<dyn AiProvider>)>) -> Self {
+        assert!(
+            !chain.is_empty(),
+            "fallback chain needs at least one provider"
+        );
+        Self {
+            chain,
+            retry: RetryConfig::default(),
+        }
+    }
+
+    pub fn with_retry_config(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn primary(&self) -> &Arc<dyn AiProvider> {
+        &self.chain[0].1
+    }
+
+    /// Answer from the first provider that succeeds, tagging the response with
+    /// its name when it wasn't the primary. Once `committed` returns true the
+    /// current error is final: no more retries and no fallback.
+    async fn run<'a, F, Fut, C>(&'a self, call: F, committed: C) -> Result<ChatResponse>
+    where
+        F: Fn(&'a Arc<dyn AiProvider>) -> Fut,
+        Fut: std::future::Future<Output = Result<ChatResponse>>,
+        C: Fn() -> bool,
+    {
+        let mut failures = Vec::new();
+
+        for (index, (name, provider)) in self.chain.iter().enumerate() {
+            let policy = |e: &anyhow::Error| {
+                if committed() {
+                    RetryAction::Stop
+                } else {
+                    retry_action(e)
+                }
+            };
+
+            match retry_with_policy(|| call(provider), &self.retry, policy).await {
+                Ok(mut response) => {
+                    if index > 0 {
+                        response.provider = Some(name.clone());
+                    }
+                    return Ok(response);
+                }
+                Err(e) if committed() => return Err(e),
+                Err(e) => failures.push((name, e)),
+            }
+        }
+
+        // A lone failure is returned as it is, so a `ProviderError` in it
+        // can still be downcast
+        if failures.len() == 1 {
+            let (name, e) = failures.remove(0);
+            return Err(e.context(name.clone()));
+        }
+        let failures: Vec<String> = failures
+            .iter()
+            .map(|(name, e)| format!("{}: {:#}", name, e))
+            .collect();
+        anyhow::bail!("All providers failed:\n{}", failures.join("\n"))
+    }
+}
+
+/// Retry rate limits, server errors and network failures; anything else
+/// (bad request, auth) goes straight to the next provider.
+fn retry_action(error: &anyhow::Error) -> RetryAction {
+    for cause in error.chain() {
+        if let Some(e) = cause.downcast_ref::<ProviderError>() {
+            return match (e.is_transient(), e.retry_after) {
+                (false, _) => RetryAction::Stop,
+                (true, Some(after)) => RetryAction::RetryAfter(after),
+                (true, None) => RetryAction::Retry,
+            };
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() || e.is_connect() {
+                return RetryAction::Retry;
+            }
+        }
+    }
+    RetryAction::Stop
+}
+
+#[async_trait::async_trait]
+impl AiProvider for FallbackProvider {
+    async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
+        self.run(|provider| provider.chat(request.clone()), || false)
+            .await
+    }
+
+    fn provider_name(&self) -> &str {
+        self.primary().provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.primary().model_name()
+    }
+
+    fn supports_tools(&self) -> bool {
+        self.primary().supports_tools()
+    }
+
+    async fn capabilities(&self) -> Result<ModelCapabilities> {
+        self.primary().capabilities().await
+    }
+
+    /// Once any text has been streamed the answer can't be retried without
+    /// repeating it, so later failures are returned as-is.
+    async fn chat_stream(
+        &self,
+        request: ChatRequest,
+        on_delta: &(dyn for<'s> Fn(&'s str) + Send + Sync),
+    ) -> Result<ChatResponse> {
+        let streamed = AtomicBool::new(false);
+        let forward = |delta: &str| {
+            streamed.store(true, Ordering::Relaxed);
+            on_delta(delta);
+        };
+
+        self.run(
+            |provider| provider.chat_stream(request.clone(), &forward),
+            || streamed.load(Ordering::Relaxed),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn provider_error(status: u16, retry_after: Option<Duration>) -> anyhow::Error {
+        ProviderError {
+            provider: "Test".to_string(),
+            status,
+            retry_after,
+            message: "boom".to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_retry_action_classifies_status() {
+        assert!(matches!(
+            retry_action(&provider_error(429, None)),
+            RetryAction::Retry
+        ));
+        assert!(matches!(
+            retry_action(&provider_error(503, Some(Duration::from_secs(2)))),
+            RetryAction::RetryAfter(d) if d == Duration::from_secs(2)
+        ));
+        assert!(matches!(
+            retry_action(&provider_error(401, None)),
+            RetryAction::Stop
+        ));
+        // A conflict is a rejected request, not a server hiccup
+        assert!(matches!(
+            retry_action(&provider_error(409, None)),
+            RetryAction::Stop
+        ));
+        assert!(matches!(
+            retry_action(&anyhow::anyhow!("parse failure")),
+            RetryAction::Stop
+        ));
+    }
+}
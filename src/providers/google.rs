@@ -1,4 +1,7 @@
-use super::{AiProvider, ChatRequest, ChatResponse, FunctionCall, Tool, ToolCall};
+use super::{
+    AiProvider, ChatRequest, ChatResponse, FunctionCall, ModelCapabilities, ProviderError, Tool,
+    ToolCall, Usage,
+};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 
@@ -68,11 +71,26 @@ struct GenerationConfig {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 struct GoogleResponse {
     candidates: Vec<GoogleCandidate>,
+    usage_metadata: Option<GoogleUsageMetadata>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleUsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+    #[serde(default)]
+    cached_content_token_count: u64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -92,6 +110,12 @@ enum GoogleResponsePart {
     FunctionCall { function_call: GoogleFunctionCall },
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleModelInfo {
+    input_token_limit: Option<u64>,
+}
+
 impl GoogleProvider {
     pub fn new(api_key: String, model: Option<String>) -> Self {
         GoogleProvider {
@@ -158,10 +182,18 @@ impl AiProvider for GoogleProvider {
             })
             .collect();
 
-        let generation_config = if request.temperature.is_some() || request.max_tokens.is_some() {
+        let generation_config = if request.temperature.is_some()
+            || request.max_tokens.is_some()
+            || request.response_format.is_some()
+        {
             Some(GenerationConfig {
                 temperature: request.temperature,
                 max_output_tokens: request.max_tokens,
+                // JSON mode; the schema itself is left to the prompt
+                response_mime_type: request
+                    .response_format
+                    .as_ref()
+                    .map(|_| "application/json".to_string()),
             })
         } else {
             None
@@ -190,11 +222,9 @@ impl AiProvider for GoogleProvider {
             .context("Failed to send request to Google AI")?;
 
         if !response.status().is_success() {
-            let error_text = response
-                .text()
+            return Err(ProviderError::from_response("Google AI", response)
                 .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            anyhow::bail!("Google AI API error: {}", error_text);
+                .into());
         }
 
         let google_response: GoogleResponse = response
@@ -237,10 +267,50 @@ impl AiProvider for GoogleProvider {
             } else {
                 Some(tool_calls)
             },
+            provider: None,
+            usage: google_response.usage_metadata.map(|u| Usage {
+                prompt_tokens: u.prompt_token_count,
+                completion_tokens: u.candidates_token_count,
+                cached_tokens: u.cached_content_token_count,
+            }),
         })
     }
 
     fn provider_name(&self) -> &str {
         "Google AI"
     }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn capabilities(&self) -> Result<ModelCapabilities> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1/models/{}?key={}",
+            self.model, self.api_key
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .context("Failed to query Google AI model info")?;
+
+        if !response.status().is_success() {
+            return Err(ProviderError::from_response("Google AI", response)
+                .await
+                .into());
+        }
+
+        let info: GoogleModelInfo = response
+            .json()
+            .await
+            .context("Failed to parse Google AI model info")?;
+
+        Ok(ModelCapabilities {
+            tools: true,
+            context_length: info.input_token_limit,
+        })
+    }
 }
@@ -1,5 +1,10 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{AzureAuthConfig, Config, ProviderConfig, ProviderType};
+use crate::retry::RetryConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -46,6 +51,18 @@ pub struct ChatRequest {
     pub max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
+    /// Ask for a JSON reply matching a schema. Providers with a native JSON or
+    /// structured-output mode use it; the others ignore this, so callers
+    /// should describe the schema in the prompt as well and validate the reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+}
+
+/// JSON Schema the reply must conform to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseFormat {
+    pub name: String,
+    pub schema: serde_json::Value,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +71,102 @@ pub struct ChatResponse {
     pub model: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// Name of the provider that produced the answer, set when a fallback chain is in use
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// Token counts reported by the API, when it reports them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+}
+
+/// Token usage of a single completion
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Usage {
+    /// Input tokens, including any served from the prompt cache
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Portion of `prompt_tokens` read from the prompt cache
+    #[serde(default)]
+    pub cached_tokens: u64,
+}
+
+impl Usage {
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cached_tokens += other.cached_tokens;
+    }
+}
+
+/// A non-success HTTP response from a provider API
+#[derive(Debug)]
+pub struct ProviderError {
+    pub provider: String,
+    pub status: u16,
+    /// Delay requested by the server through `Retry-After`
+    pub retry_after: Option<Duration>,
+    pub message: String,
+}
+
+impl ProviderError {
+    pub async fn from_response(provider: &str, response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        let message = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+
+        Self {
+            provider: provider.to_string(),
+            status,
+            retry_after,
+            message,
+        }
+    }
+
+    /// Rate limits, timeouts and server errors are worth retrying
+    pub fn is_transient(&self) -> bool {
+        matches!(self.status, 408 | 429 | 500..=599)
+    }
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} API error: {}", self.provider, self.message)
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+/// Parse a `Retry-After` value: either delay seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let delay = date.signed_duration_since(chrono::Utc::now());
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// What the configured model can do, as far as the provider can tell
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelCapabilities {
+    /// Native function/tool calling
+    pub tools: bool,
+    /// Maximum context window in tokens, when the API reports it
+    pub context_length: Option<u64>,
 }
 
 #[async_trait::async_trait]
@@ -61,24 +174,259 @@ pub trait AiProvider: Send + Sync {
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse>;
     #[allow(dead_code)]
     fn provider_name(&self) -> &str;
+    /// Model (or deployment) requests are sent to
+    fn model_name(&self) -> &str;
     #[allow(dead_code)]
     fn supports_tools(&self) -> bool {
         true
     }
+
+    /// Ask the API what the model supports. Providers that can't be queried
+    /// report `supports_tools` and no context length.
+    async fn capabilities(&self) -> Result<ModelCapabilities> {
+        Ok(ModelCapabilities {
+            tools: self.supports_tools(),
+            context_length: None,
+        })
+    }
+
+    /// Stream a completion, calling `on_delta` with each text fragment as it arrives.
+    /// Providers without native streaming deliver the whole answer as one fragment.
+    async fn chat_stream(
+        &self,
+        request: ChatRequest,
+        on_delta: &(dyn for<'s> Fn(&'s str) + Send + Sync),
+    ) -> Result<ChatResponse> {
+        let response = self.chat(request).await?;
+        if !response.content.is_empty() {
+            on_delta(&response.content);
+        }
+        Ok(response)
+    }
 }
 
+pub mod anthropic;
+pub mod auth;
 pub mod azure;
+pub mod fallback;
 pub mod google;
 pub mod ollama;
 pub mod openai;
+pub mod prompt_tools;
 pub mod vertex;
 
+pub use anthropic::AnthropicProvider;
 pub use azure::AzureProvider;
+pub use fallback::FallbackProvider;
 pub use google::GoogleProvider;
 pub use ollama::OllamaProvider;
 pub use openai::OpenAiProvider;
+pub use prompt_tools::PromptToolsProvider;
 pub use vertex::VertexProvider;
 
+/// Find the profile for `name`: a named profile from config.json, or a bare
+/// provider type ("openai", "azure", ...) configured through environment variables.
+pub fn resolve_profile(name: &str, config: Option<&Config>) -> Option<ProviderConfig> {
+    if let Some(profile) = config.and_then(|c| c.providers.get(name)) {
+        return Some(profile.clone());
+    }
+    ProviderType::from_name(name).map(ProviderConfig::for_type)
+}
+
+/// Build a provider from a profile. `model` overrides the profile's model
+/// (for Azure it names the deployment).
+pub fn from_config(
+    name: &str,
+    config: &ProviderConfig,
+    model: Option<&str>,
+) -> Result<Arc<dyn AiProvider>> {
+    let model = model.map(String::from).or_else(|| config.model.clone());
+    // An Azure profile on Entra ID never uses the key, so don't run its
+    // api_key_command
+    let entra_id = config.provider_type == ProviderType::Azure
+        && !matches!(config.auth, None | Some(AzureAuthConfig::ApiKey));
+    let api_key = if entra_id {
+        None
+    } else {
+        config.resolve_api_key()?
+    };
+    let require_key = || {
+        api_key.clone().with_context(|| {
+            format!(
+                "Provider '{}' has no API key (set api_key, api_key_env or api_key_command{})",
+                name,
+                config
+                    .provider_type
+                    .default_api_key_env()
+                    .map(|var| format!(", or {}", var))
+                    .unwrap_or_default()
+            )
+        })
+    };
+    let env_or =
+        |value: &Option<String>, var: &str| value.clone().or_else(|| std::env::var(var).ok());
+
+    let provider: Arc<dyn AiProvider> = match config.provider_type {
+        ProviderType::Openai => {
+            let mut provider =
+                OpenAiProvider::new(require_key()?, model).with_headers(config.headers.clone());
+            if let Some(endpoint) = &config.endpoint {
+                provider = provider.with_base_url(endpoint.clone());
+            }
+            Arc::new(provider)
+        }
+        ProviderType::Anthropic => {
+            let mut provider = AnthropicProvider::new(require_key()?, model);
+            if let Some(endpoint) = &config.endpoint {
+                provider = provider.with_base_url(endpoint.clone());
+            }
+            Arc::new(provider)
+        }
+        ProviderType::Google => Arc::new(GoogleProvider::new(require_key()?, model)),
+        ProviderType::Azure => {
+            let endpoint = env_or(&config.endpoint, "AZURE_OPENAI_ENDPOINT")
+                .with_context(|| format!("Provider '{}' requires an endpoint", name))?;
+            let deployment = model
+                .or_else(|| env_or(&config.deployment, "AZURE_OPENAI_DEPLOYMENT"))
+                .with_context(|| format!("Provider '{}' requires a deployment", name))?;
+            let provider = match azure_credential(name, config.auth.as_ref(), &api_key)? {
+                Some(credential) => {
+                    let mut auth = auth::AzureAuth::new(credential);
+                    // Sovereign clouds, as the Azure SDKs read it
+                    if let Ok(host) = std::env::var("AZURE_AUTHORITY_HOST") {
+                        auth = auth.with_authority_host(host);
+                    }
+                    AzureProvider::with_entra_id(auth, endpoint, deployment)
+                }
+                None => AzureProvider::new(require_key()?, endpoint, deployment),
+            };
+            Arc::new(
+                match env_or(&config.api_version, "AZURE_OPENAI_API_VERSION") {
+                    Some(version) => provider.with_api_version(version),
+                    None => provider,
+                },
+            )
+        }
+        ProviderType::Vertex => {
+            let project = env_or(&config.project, "GOOGLE_CLOUD_PROJECT")
+                .with_context(|| format!("Provider '{}' requires a project", name))?;
+            let location = env_or(&config.location, "GOOGLE_CLOUD_LOCATION")
+                .unwrap_or_else(|| "us-central1".to_string());
+            let mut provider = VertexProvider::new(project, location, model);
+            if let Some(path) = &config.credentials_file {
+                provider = provider.with_credentials_file(path.clone());
+            }
+            Arc::new(provider)
+        }
+        ProviderType::Ollama => Arc::new(OllamaProvider::new(
+            model.or_else(|| std::env::var("OLLAMA_MODEL").ok()),
+            env_or(&config.endpoint, "OLLAMA_BASE_URL"),
+        )),
+        ProviderType::OpenaiCompatible => {
+            let endpoint = config
+                .endpoint
+                .clone()
+                .with_context(|| format!("Provider '{}' requires an endpoint", name))?;
+            let model = model.with_context(|| format!("Provider '{}' requires a model", name))?;
+
+            Arc::new(
+                OpenAiProvider::compatible(endpoint, model, api_key.clone())
+                    .with_api_key_header(config.api_key_header.clone())
+                    .with_headers(config.headers.clone()),
+            )
+        }
+    };
+
+    Ok(Arc::new(PromptToolsProvider::new(provider)))
+}
+
+/// The Entra ID identity for an Azure profile, or `None` to use the API key.
+/// Without an explicit `auth` setting, a profile with no API key picks up
+/// Entra ID from the environment.
+fn azure_credential(
+    name: &str,
+    auth: Option<&AzureAuthConfig>,
+    api_key: &Option<String>,
+) -> Result<Option<auth::AzureCredential>> {
+    let env_or = |value: &Option<String>, var: &str| {
+        value
+            .clone()
+            .or_else(|| std::env::var(var).ok())
+            .with_context(|| format!("Provider '{}' requires {} for Entra ID auth", name, var))
+    };
+
+    Ok(match auth {
+        Some(AzureAuthConfig::ApiKey) => None,
+        Some(AzureAuthConfig::ClientSecret {
+            tenant_id,
+            client_id,
+            client_secret,
+            client_secret_env,
+        }) => {
+            let secret_env = client_secret_env
+                .as_deref()
+                .unwrap_or("AZURE_CLIENT_SECRET");
+            Some(auth::AzureCredential::ClientSecret {
+                tenant_id: env_or(tenant_id, "AZURE_TENANT_ID")?,
+                client_id: env_or(client_id, "AZURE_CLIENT_ID")?,
+                client_secret: env_or(client_secret, secret_env)?,
+            })
+        }
+        Some(AzureAuthConfig::WorkloadIdentity {
+            tenant_id,
+            client_id,
+            token_file,
+        }) => Some(auth::AzureCredential::WorkloadIdentity {
+            tenant_id: env_or(tenant_id, "AZURE_TENANT_ID")?,
+            client_id: env_or(client_id, "AZURE_CLIENT_ID")?,
+            token_file: env_or(token_file, "AZURE_FEDERATED_TOKEN_FILE")?.into(),
+        }),
+        None if api_key.is_none() => auth::AzureCredential::from_env(),
+        None => None,
+    })
+}
+
+/// Retries per provider when a profile doesn't set `max_retries`
+const DEFAULT_MAX_RETRIES: u32 = 2;
+
+/// Build the provider for profile `name` with retries on transient errors,
+/// followed by the profile's `fallback` chain. Fallbacks that can't be built
+/// (e.g. no API key in this environment) are skipped.
+pub fn with_fallbacks(
+    name: &str,
+    profile: &ProviderConfig,
+    config: Option<&Config>,
+    model: Option<&str>,
+) -> Result<Arc<dyn AiProvider>> {
+    let mut chain = vec![(name.to_string(), from_config(name, profile, model)?)];
+
+    for fallback in &profile.fallback {
+        if chain.iter().any(|(n, _)| n == fallback) {
+            continue;
+        }
+        let provider =
+            resolve_profile(fallback, config).and_then(|p| from_config(fallback, &p, None).ok());
+        if let Some(provider) = provider {
+            chain.push((fallback.clone(), provider));
+        }
+    }
+
+    let retry = RetryConfig {
+        // The setting counts retries; RetryConfig counts attempts
+        max_retries: profile
+            .max_retries
+            .unwrap_or(DEFAULT_MAX_RETRIES)
+            .saturating_add(1),
+        initial_delay: Duration::from_secs(1),
+        max_delay: Duration::from_secs(30),
+        multiplier: 2.0,
+    };
+
+    Ok(Arc::new(
+        FallbackProvider::new(chain).with_retry_config(retry),
+    ))
+}
+
 /// Create the log query tools definition for AI function calling
 pub fn create_log_tools() -> Vec<Tool> {
     vec![
@@ -156,3 +504,74 @@ pub fn create_log_tools() -> Vec<Tool> {
         },
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("7"), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_resolve_profile_prefers_config() {
+        let mut config: Config = serde_json::from_str(r#"{"servers": {}}"#).unwrap();
+        let mut profile = ProviderConfig::for_type(ProviderType::Ollama);
+        profile.model = Some("qwen2.5".to_string());
+        config.providers.insert("openai".to_string(), profile);
+
+        let resolved = resolve_profile("openai", Some(&config)).unwrap();
+        assert_eq!(resolved.provider_type, ProviderType::Ollama);
+
+        let bare = resolve_profile("anthropic", Some(&config)).unwrap();
+        assert_eq!(bare.provider_type, ProviderType::Anthropic);
+
+        assert!(resolve_profile("nope", Some(&config)).is_none());
+    }
+
+    #[test]
+    fn test_from_config_requires_key() {
+        let mut profile = ProviderConfig::for_type(ProviderType::Openai);
+        profile.api_key_env = Some("ZETEO_TEST_UNSET_KEY".to_string());
+
+        let err = from_config("work", &profile, None).err().unwrap();
+        assert!(err.to_string().contains("no API key"));
+
+        profile.api_key = Some("sk-test".to_string());
+        let provider = from_config("work", &profile, Some("gpt-4o-mini")).unwrap();
+        assert_eq!(provider.provider_name(), "OpenAI");
+    }
+
+    #[test]
+    fn test_openai_compatible_requires_model() {
+        let mut profile = ProviderConfig::for_type(ProviderType::OpenaiCompatible);
+        profile.endpoint = Some("http://localhost:8000/v1".to_string());
+
+        assert!(from_config("vllm", &profile, None).is_err());
+        assert!(from_config("vllm", &profile, Some("llama")).is_ok());
+    }
+
+    #[test]
+    fn test_azure_entra_id_skips_api_key_command() {
+        let mut profile = ProviderConfig::for_type(ProviderType::Azure);
+        profile.endpoint = Some("https://example.openai.azure.com".to_string());
+        profile.deployment = Some("gpt-4o".to_string());
+        profile.api_key_command = Some("exit 1".to_string());
+        profile.auth = Some(AzureAuthConfig::WorkloadIdentity {
+            tenant_id: Some("tenant".to_string()),
+            client_id: Some("client".to_string()),
+            token_file: Some("/tmp/zeteo-test-token".to_string()),
+        });
+
+        assert!(from_config("azure", &profile, None).is_ok());
+
+        profile.auth = Some(AzureAuthConfig::ApiKey);
+        assert!(from_config("azure", &profile, None).is_err());
+    }
+}
@@ -1,6 +1,10 @@
-use super::{AiProvider, ChatRequest, ChatResponse, FunctionCall, Tool, ToolCall};
+use super::{
+    AiProvider, ChatRequest, ChatResponse, FunctionCall, ModelCapabilities, ProviderError, Tool,
+    ToolCall, Usage,
+};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Clone)]
 pub struct OllamaProvider {
@@ -13,15 +17,24 @@ pub struct OllamaProvider {
 struct OllamaRequest {
     model: String,
     messages: Vec<OllamaMessage>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    temperature: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    max_tokens: Option<u32>,
+    options: OllamaOptions,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
+    /// JSON Schema constraining the reply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
     stream: bool,
 }
 
+/// Sampling parameters; Ollama ignores OpenAI-style top-level fields
+#[derive(Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 struct OllamaMessage {
     role: String,
@@ -32,14 +45,20 @@ struct OllamaMessage {
     tool_call_id: Option<String>,
 }
 
+/// Ollama omits `id` and `type` on the tool calls it returns
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct OllamaToolCall {
+    #[serde(default)]
     id: String,
-    #[serde(rename = "type")]
+    #[serde(rename = "type", default = "default_call_type")]
     call_type: String,
     function: OllamaFunctionCall,
 }
 
+fn default_call_type() -> String {
+    "function".to_string()
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct OllamaFunctionCall {
     name: String,
@@ -49,6 +68,8 @@ struct OllamaFunctionCall {
 #[derive(Deserialize, Debug)]
 struct OllamaResponse {
     message: OllamaResponseMessage,
+    prompt_eval_count: Option<u64>,
+    eval_count: Option<u64>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -60,6 +81,44 @@ struct OllamaResponseMessage {
     tool_calls: Option<Vec<OllamaToolCall>>,
 }
 
+#[derive(Serialize)]
+struct OllamaShowRequest<'a> {
+    model: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct OllamaShowResponse {
+    /// Reported by Ollama 0.6+, e.g. `["completion", "tools"]`
+    #[serde(default)]
+    capabilities: Vec<String>,
+    #[serde(default)]
+    template: String,
+    #[serde(default)]
+    model_info: HashMap<String, serde_json::Value>,
+}
+
+impl OllamaShowResponse {
+    fn to_capabilities(&self) -> ModelCapabilities {
+        // Older servers don't list capabilities; tool-capable templates reference .Tools
+        let tools = if self.capabilities.is_empty() {
+            self.template.contains(".Tools")
+        } else {
+            self.capabilities.iter().any(|c| c == "tools")
+        };
+
+        let context_length = self
+            .model_info
+            .iter()
+            .find(|(key, _)| key.ends_with(".context_length"))
+            .and_then(|(_, value)| value.as_u64());
+
+        ModelCapabilities {
+            tools,
+            context_length,
+        }
+    }
+}
+
 impl OllamaProvider {
     pub fn new(model: Option<String>, base_url: Option<String>) -> Self {
         OllamaProvider {
@@ -109,9 +168,12 @@ impl AiProvider for OllamaProvider {
         let ollama_request = OllamaRequest {
             model: self.model.clone(),
             messages,
-            temperature: request.temperature,
-            max_tokens: request.max_tokens,
+            options: OllamaOptions {
+                temperature: request.temperature,
+                num_predict: request.max_tokens,
+            },
             tools: request.tools,
+            format: request.response_format.map(|format| format.schema),
             stream: false,
         };
 
@@ -126,11 +188,9 @@ impl AiProvider for OllamaProvider {
             .context("Failed to send request to Ollama")?;
 
         if !response.status().is_success() {
-            let error_text = response
-                .text()
+            return Err(ProviderError::from_response("Ollama", response)
                 .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            anyhow::bail!("Ollama API error: {}", error_text);
+                .into());
         }
 
         let ollama_response: OllamaResponse = response
@@ -138,12 +198,28 @@ impl AiProvider for OllamaProvider {
             .await
             .context("Failed to parse Ollama response")?;
 
+        let usage = match (
+            ollama_response.prompt_eval_count,
+            ollama_response.eval_count,
+        ) {
+            (None, None) => None,
+            (prompt, completion) => Some(Usage {
+                prompt_tokens: prompt.unwrap_or(0),
+                completion_tokens: completion.unwrap_or(0),
+                cached_tokens: 0,
+            }),
+        };
         let content = ollama_response.message.content;
 
         let tool_calls = ollama_response.message.tool_calls.as_ref().map(|tcs| {
             tcs.iter()
                 .map(|tc| ToolCall {
-                    id: tc.id.clone(),
+                    // Unique across turns: results are matched to calls by ID
+                    id: if tc.id.is_empty() {
+                        format!("call_{}", uuid::Uuid::new_v4().simple())
+                    } else {
+                        tc.id.clone()
+                    },
                     call_type: tc.call_type.clone(),
                     function: FunctionCall {
                         name: tc.function.name.clone(),
@@ -158,6 +234,8 @@ impl AiProvider for OllamaProvider {
             content,
             model: self.model.clone(),
             tool_calls,
+            provider: None,
+            usage,
         })
     }
 
@@ -165,8 +243,36 @@ impl AiProvider for OllamaProvider {
         "Ollama"
     }
 
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
     fn supports_tools(&self) -> bool {
         // Ollama has varying tool support depending on the model
         false
     }
+
+    async fn capabilities(&self) -> Result<ModelCapabilities> {
+        let url = format!("{}/api/show", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .json(&OllamaShowRequest { model: &self.model })
+            .send()
+            .await
+            .context("Failed to query Ollama model info")?;
+
+        if !response.status().is_success() {
+            return Err(ProviderError::from_response("Ollama", response)
+                .await
+                .into());
+        }
+
+        let show: OllamaShowResponse = response
+            .json()
+            .await
+            .context("Failed to parse Ollama model info")?;
+
+        Ok(show.to_capabilities())
+    }
 }
@@ -1,11 +1,21 @@
-use super::{AiProvider, ChatRequest, ChatResponse, FunctionCall, Tool, ToolCall};
+use super::{
+    AiProvider, ChatRequest, ChatResponse, FunctionCall, ModelCapabilities, ProviderError, Tool,
+    ToolCall, Usage,
+};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
 
 #[derive(Clone)]
 pub struct OpenAiProvider {
-    api_key: String,
+    api_key: Option<String>,
     model: String,
+    base_url: String,
+    api_key_header: String,
+    extra_headers: HashMap<String, String>,
+    name: String,
     client: reqwest::Client,
 }
 
@@ -19,6 +29,8 @@ struct OpenAiRequest {
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -49,6 +61,20 @@ struct OpenAiFunctionCall {
 #[derive(Deserialize, Debug)]
 struct OpenAiResponse {
     choices: Vec<OpenAiChoice>,
+    usage: Option<OpenAiUsage>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAiUsage {
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    prompt_tokens_details: Option<OpenAiPromptTokensDetails>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OpenAiPromptTokensDetails {
+    #[serde(default)]
+    cached_tokens: u64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -56,14 +82,81 @@ struct OpenAiChoice {
     message: OpenAiMessage,
 }
 
+#[derive(Deserialize, Debug)]
+struct OpenAiModelList {
+    data: Vec<OpenAiModel>,
+}
+
+/// Entry of `GET /models`. OpenAI itself reports no limits, but vLLM sets
+/// `max_model_len` and OpenRouter / LM Studio set `context_length`.
+#[derive(Deserialize, Debug)]
+struct OpenAiModel {
+    id: String,
+    max_model_len: Option<u64>,
+    context_length: Option<u64>,
+}
+
 impl OpenAiProvider {
     pub fn new(api_key: String, model: Option<String>) -> Self {
         OpenAiProvider {
-            api_key,
+            api_key: Some(api_key),
             model: model.unwrap_or_else(|| "gpt-4o".to_string()),
+            base_url: OPENAI_BASE_URL.to_string(),
+            api_key_header: "Authorization".to_string(),
+            extra_headers: HashMap::new(),
+            name: "OpenAI".to_string(),
             client: reqwest::Client::new(),
         }
     }
+
+    /// Create a provider for any server speaking the chat-completions wire format
+    /// (vLLM, LM Studio, LiteLLM, internal gateways, ...)
+    pub fn compatible(base_url: String, model: String, api_key: Option<String>) -> Self {
+        OpenAiProvider {
+            api_key,
+            model,
+            base_url,
+            api_key_header: "Authorization".to_string(),
+            extra_headers: HashMap::new(),
+            name: "OpenAI-compatible".to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Override the API base URL (e.g. for a proxy or gateway)
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    /// Send the API key in a custom header instead of `Authorization: Bearer`
+    pub fn with_api_key_header(mut self, header: String) -> Self {
+        self.api_key_header = header;
+        self
+    }
+
+    /// Additional headers sent with every request
+    pub fn with_headers(mut self, headers: HashMap<String, String>) -> Self {
+        self.extra_headers = headers;
+        self
+    }
+
+    /// Attach the API key and any extra headers
+    fn authorize(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(api_key) = &self.api_key {
+            request = if self.api_key_header.eq_ignore_ascii_case("Authorization") {
+                request.header("Authorization", format!("Bearer {}", api_key))
+            } else {
+                request.header(self.api_key_header.as_str(), api_key)
+            };
+        }
+
+        for (name, value) in &self.extra_headers {
+            request = request.header(name.as_str(), value);
+        }
+
+        request
+    }
 }
 
 #[async_trait::async_trait]
@@ -110,35 +203,39 @@ impl AiProvider for OpenAiProvider {
             temperature: request.temperature,
             max_tokens: request.max_tokens,
             tools: request.tools,
+            response_format: request.response_format.map(|format| {
+                serde_json::json!({
+                    "type": "json_schema",
+                    "json_schema": {"name": format.name, "schema": format.schema, "strict": true},
+                })
+            }),
         };
 
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+
         let response = self
-            .client
-            .post("https://api.openai.com/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .authorize(self.client.post(&url))
             .header("Content-Type", "application/json")
             .json(&openai_request)
             .send()
             .await
-            .context("Failed to send request to OpenAI")?;
+            .with_context(|| format!("Failed to send request to {}", self.name))?;
 
         if !response.status().is_success() {
-            let error_text = response
-                .text()
+            return Err(ProviderError::from_response(&self.name, response)
                 .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            anyhow::bail!("OpenAI API error: {}", error_text);
+                .into());
         }
 
         let openai_response: OpenAiResponse = response
             .json()
             .await
-            .context("Failed to parse OpenAI response")?;
+            .with_context(|| format!("Failed to parse {} response", self.name))?;
 
         let choice = openai_response
             .choices
             .first()
-            .context("No choices in OpenAI response")?;
+            .with_context(|| format!("No choices in {} response", self.name))?;
 
         let content = choice.message.content.clone().unwrap_or_default();
 
@@ -159,10 +256,51 @@ impl AiProvider for OpenAiProvider {
             content,
             model: self.model.clone(),
             tool_calls,
+            provider: None,
+            usage: openai_response.usage.map(|u| Usage {
+                prompt_tokens: u.prompt_tokens,
+                completion_tokens: u.completion_tokens,
+                cached_tokens: u.prompt_tokens_details.map_or(0, |d| d.cached_tokens),
+            }),
         })
     }
 
     fn provider_name(&self) -> &str {
-        "OpenAI"
+        &self.name
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    async fn capabilities(&self) -> Result<ModelCapabilities> {
+        let url = format!("{}/models", self.base_url.trim_end_matches('/'));
+        let response = self
+            .authorize(self.client.get(&url))
+            .send()
+            .await
+            .with_context(|| format!("Failed to list {} models", self.name))?;
+
+        if !response.status().is_success() {
+            return Err(ProviderError::from_response(&self.name, response)
+                .await
+                .into());
+        }
+
+        let models: OpenAiModelList = response
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse {} model list", self.name))?;
+
+        let context_length = models
+            .data
+            .into_iter()
+            .find(|m| m.id == self.model)
+            .and_then(|m| m.max_model_len.or(m.context_length));
+
+        Ok(ModelCapabilities {
+            tools: true,
+            context_length,
+        })
     }
 }
@@ -0,0 +1,398 @@
+use super::{
+    AiProvider, ChatRequest, ChatResponse, FunctionCall, Message, ModelCapabilities, Tool, ToolCall,
+};
+use anyhow::Result;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Gives tool calling to models that lack it natively.
+///
+/// The model's capabilities are probed on the first request that
+/// carries tools. If the model can call tools the request passes through
+/// untouched; otherwise the tools are described in the system prompt and the
+/// model is asked to reply with a JSON object, which is parsed back into a
+/// [`ToolCall`] so callers see the same response either way.
+pub struct PromptToolsProvider {
+    inner: Arc<dyn AiProvider>,
+    capabilities: OnceCell<ModelCapabilities>,
+}
+
+/// Reply shape requested from models without native tool calling
+#[derive(Deserialize)]
+struct PromptToolCall {
+    tool: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+impl PromptToolsProvider {
+    pub fn new(inner: Arc<dyn AiProvider>) -> Self {
+        Self {
+            inner,
+            capabilities: OnceCell::new(),
+        }
+    }
+
+    async fn probe(&self) -> ModelCapabilities {
+        match self
+            .capabilities
+            .get_or_try_init(|| self.inner.capabilities())
+            .await
+        {
+            Ok(capabilities) => *capabilities,
+            // Not cached, so the next request probes again; until then the
+            // provider's own `supports_tools` decides
+            Err(_) => ModelCapabilities {
+                tools: self.inner.supports_tools(),
+                context_length: None,
+            },
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AiProvider for PromptToolsProvider {
+    async fn chat(&self, mut request: ChatRequest) -> Result<ChatResponse> {
+        let tools = match request.tools.take() {
+            Some(tools) if !tools.is_empty() && !self.probe().await.tools => tools,
+            tools => {
+                request.tools = tools;
+                return self.inner.chat(request).await;
+            }
+        };
+
+        request.messages = to_prompt_messages(request.messages, &tools);
+        let mut response = self.inner.chat(request).await?;
+
+        if let Some(call) = parse_tool_call(&response.content, &tools) {
+            response.content = String::new();
+            response.tool_calls = Some(vec![call]);
+        }
+
+        Ok(response)
+    }
+
+    async fn chat_stream(
+        &self,
+        request: ChatRequest,
+        on_delta: &(dyn for<'s> Fn(&'s str) + Send + Sync),
+    ) -> Result<ChatResponse> {
+        let no_tools = request.tools.as_ref().is_none_or(|tools| tools.is_empty());
+        if no_tools || self.probe().await.tools {
+            return self.inner.chat_stream(request, on_delta).await;
+        }
+
+        // Prompted tool calls come back as JSON text, which must not be shown
+        // as it arrives, so the answer is delivered whole
+        let response = self.chat(request).await?;
+        if !response.content.is_empty() {
+            on_delta(&response.content);
+        }
+        Ok(response)
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn supports_tools(&self) -> bool {
+        true
+    }
+
+    async fn capabilities(&self) -> Result<ModelCapabilities> {
+        Ok(self.probe().await)
+    }
+}
+
+fn tool_instructions(tools: &[Tool]) -> String {
+    let mut text =
+        String::from("## Tools\n\nYou can call the following tools to look up information:\n\n");
+
+    for tool in tools {
+        text.push_str(&format!(
+            "### {}\n{}\nArguments (JSON Schema): {}\n\n",
+            tool.function.name, tool.function.description, tool.function.parameters
+        ));
+    }
+
+    text.push_str(
+        "To call a tool, reply with ONLY a JSON object and nothing else, for example:\n\
+         {\"tool\": \"<tool name>\", \"arguments\": { ... }}\n\n\
+         The result will be sent back to you in the next message. \
+         When you don't need a tool, answer normally in plain text.",
+    );
+    text
+}
+
+/// Rewrite a tool-calling conversation into plain system/user/assistant turns
+fn to_prompt_messages(messages: Vec<Message>, tools: &[Tool]) -> Vec<Message> {
+    let instructions = tool_instructions(tools);
+    let mut converted = Vec::with_capacity(messages.len() + 1);
+    let mut has_system = false;
+
+    for m in messages {
+        let message = match m.role.as_str() {
+            "system" if !has_system => {
+                has_system = true;
+                Message {
+                    content: format!("{}\n\n{}", m.content, instructions),
+                    ..m
+                }
+            }
+            "assistant" => match &m.tool_calls {
+                Some(calls) if !calls.is_empty() => Message {
+                    role: "assistant".to_string(),
+                    content: calls
+                        .iter()
+                        .map(|c| {
+                            serde_json::json!({
+                                "tool": c.function.name,
+                                "arguments": serde_json::from_str::<serde_json::Value>(
+                                    &c.function.arguments
+                                )
+                                .unwrap_or_default(),
+                            })
+                            .to_string()
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    tool_calls: None,
+                    tool_call_id: None,
+                },
+                _ => m,
+            },
+            "tool" => Message {
+                role: "user".to_string(),
+                content: format!("Tool result:\n{}", m.content),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+            _ => m,
+        };
+        converted.push(message);
+    }
+
+    if !has_system {
+        converted.insert(
+            0,
+            Message {
+                role: "system".to_string(),
+                content: instructions,
+                tool_calls: None,
+                tool_call_id: None,
+            },
+        );
+    }
+
+    converted
+}
+
+/// Find a `{"tool": ..., "arguments": ...}` object naming a known tool in a
+/// reply, tolerating code fences and surrounding prose.
+fn parse_tool_call(content: &str, tools: &[Tool]) -> Option<ToolCall> {
+    let start = content.find('{')?;
+    let end = content.rfind('}')?;
+    if end < start {
+        return None;
+    }
+
+    let call: PromptToolCall = serde_json::from_str(&content[start..=end]).ok()?;
+    if !tools.iter().any(|t| t.function.name == call.tool) {
+        return None;
+    }
+
+    let arguments = if call.arguments.is_null() {
+        serde_json::json!({})
+    } else {
+        call.arguments
+    };
+
+    Some(ToolCall {
+        id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+        call_type: "function".to_string(),
+        function: FunctionCall {
+            name: call.tool,
+            arguments: arguments.to_string(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::create_log_tools;
+
+    #[test]
+    fn test_parse_fenced_tool_call() {
+        let tools = create_log_tools();
+        let reply = "Let me look.\n```json\n{\"tool\": \"query_logs\", \"arguments\": {\"query\": \"timeout\"}}\n```";
+
+        let call = parse_tool_call(reply, &tools).unwrap();
+        assert_eq!(call.function.name, "query_logs");
+        assert_eq!(call.function.arguments, r#"{"query":"timeout"}"#);
+    }
+
+    #[test]
+    fn test_plain_answers_and_unknown_tools_are_not_calls() {
+        let tools = create_log_tools();
+
+        assert!(parse_tool_call("No errors in the last hour.", &tools).is_none());
+        assert!(parse_tool_call(r#"{"tool": "rm_rf", "arguments": {}}"#, &tools).is_none());
+        assert!(parse_tool_call(r#"Config looks like {"level": "debug"}"#, &tools).is_none());
+    }
+
+    #[test]
+    fn test_tool_history_is_flattened() {
+        let tools = create_log_tools();
+        let messages = vec![
+            Message {
+                role: "user".to_string(),
+                content: "errors?".to_string(),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+            Message {
+                role: "assistant".to_string(),
+                content: String::new(),
+                tool_calls: Some(vec![ToolCall {
+                    id: "call_1".to_string(),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "query_logs".to_string(),
+                        arguments: r#"{"query":"error"}"#.to_string(),
+                    },
+                }]),
+                tool_call_id: None,
+            },
+            Message {
+                role: "tool".to_string(),
+                content: "3 errors".to_string(),
+                tool_calls: None,
+                tool_call_id: Some("call_1".to_string()),
+            },
+        ];
+
+        let converted = to_prompt_messages(messages, &tools);
+
+        assert_eq!(converted[0].role, "system");
+        assert!(converted[0].content.contains("### query_logs"));
+        assert_eq!(
+            converted[2].content,
+            r#"{"arguments":{"query":"error"},"tool":"query_logs"}"#
+        );
+        assert!(converted[2].tool_calls.is_none());
+        assert_eq!(converted[3].role, "user");
+        assert_eq!(converted[3].content, "Tool result:\n3 errors");
+    }
+
+    /// Fails its first capability probe, then reports native tools
+    struct FlakyProbe {
+        probes: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl AiProvider for FlakyProbe {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse> {
+            anyhow::bail!("not used")
+        }
+
+        fn provider_name(&self) -> &str {
+            "flaky"
+        }
+
+        fn model_name(&self) -> &str {
+            "flaky"
+        }
+
+        fn supports_tools(&self) -> bool {
+            false
+        }
+
+        async fn capabilities(&self) -> Result<ModelCapabilities> {
+            if self
+                .probes
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+                == 0
+            {
+                anyhow::bail!("connection reset");
+            }
+            Ok(ModelCapabilities {
+                tools: true,
+                context_length: Some(8192),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_is_retried() {
+        let provider = PromptToolsProvider::new(Arc::new(FlakyProbe {
+            probes: Default::default(),
+        }));
+
+        assert!(!provider.capabilities().await.unwrap().tools);
+        assert!(provider.capabilities().await.unwrap().tools);
+        assert!(provider.capabilities().await.unwrap().tools);
+    }
+
+    /// Streams "a", "b" natively; `chat` answers in one piece
+    struct Streaming;
+
+    #[async_trait::async_trait]
+    impl AiProvider for Streaming {
+        async fn chat(&self, _request: ChatRequest) -> Result<ChatResponse> {
+            Ok(ChatResponse {
+                content: "ab".to_string(),
+                model: "streaming".to_string(),
+                tool_calls: None,
+                provider: None,
+                usage: None,
+            })
+        }
+
+        async fn chat_stream(
+            &self,
+            request: ChatRequest,
+            on_delta: &(dyn for<'s> Fn(&'s str) + Send + Sync),
+        ) -> Result<ChatResponse> {
+            on_delta("a");
+            on_delta("b");
+            self.chat(request).await
+        }
+
+        fn provider_name(&self) -> &str {
+            "streaming"
+        }
+
+        fn model_name(&self) -> &str {
+            "streaming"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_streaming_is_forwarded() {
+        let provider = PromptToolsProvider::new(Arc::new(Streaming));
+        let request = ChatRequest {
+            messages: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            tools: Some(create_log_tools()),
+            response_format: None,
+        };
+
+        let deltas = std::sync::Mutex::new(Vec::new());
+        let response = provider
+            .chat_stream(request, &|delta| {
+                deltas.lock().unwrap().push(delta.to_string())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(*deltas.lock().unwrap(), vec!["a", "b"]);
+        assert_eq!(response.content, "ab");
+    }
+}
@@ -1,12 +1,17 @@
-use super::{AiProvider, ChatRequest, ChatResponse, FunctionCall, Tool, ToolCall};
+use super::auth::GoogleAuth;
+use super::{
+    AiProvider, ChatRequest, ChatResponse, FunctionCall, ProviderError, Tool, ToolCall, Usage,
+};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct VertexProvider {
     project_id: String,
     location: String,
     model: String,
+    auth: Arc<GoogleAuth>,
     client: reqwest::Client,
 }
 
@@ -69,11 +74,26 @@ struct GenerationConfig {
     temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 struct VertexResponse {
     candidates: Vec<VertexCandidate>,
+    usage_metadata: Option<VertexUsageMetadata>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct VertexUsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+    #[serde(default)]
+    cached_content_token_count: u64,
 }
 
 #[derive(Deserialize, Debug)]
@@ -99,10 +119,17 @@ impl VertexProvider {
             project_id,
             location,
             model: model.unwrap_or_else(|| "gemini-pro".to_string()),
+            auth: Arc::new(GoogleAuth::application_default()),
             client: reqwest::Client::new(),
         }
     }
 
+    /// Authenticate with a specific credentials file instead of Application Default Credentials
+    pub fn with_credentials_file(mut self, path: String) -> Self {
+        self.auth = Arc::new(GoogleAuth::from_file(path));
+        self
+    }
+
     fn convert_tools(tools: &[Tool]) -> Vec<VertexTool> {
         vec![VertexTool {
             function_declarations: tools
@@ -120,7 +147,7 @@ impl VertexProvider {
 #[async_trait::async_trait]
 impl AiProvider for VertexProvider {
     async fn chat(&self, request: ChatRequest) -> Result<ChatResponse> {
-        let token = Self::get_access_token().await?;
+        let token = self.auth.access_token().await?;
 
         let contents: Vec<VertexContent> = request
             .messages
@@ -162,10 +189,18 @@ impl AiProvider for VertexProvider {
             })
             .collect();
 
-        let generation_config = if request.temperature.is_some() || request.max_tokens.is_some() {
+        let generation_config = if request.temperature.is_some()
+            || request.max_tokens.is_some()
+            || request.response_format.is_some()
+        {
             Some(GenerationConfig {
                 temperature: request.temperature,
                 max_output_tokens: request.max_tokens,
+                // JSON mode; the schema itself is left to the prompt
+                response_mime_type: request
+                    .response_format
+                    .as_ref()
+                    .map(|_| "application/json".to_string()),
             })
         } else {
             None
@@ -195,11 +230,9 @@ impl AiProvider for VertexProvider {
             .context("Failed to send request to Vertex AI")?;
 
         if !response.status().is_success() {
-            let error_text = response
-                .text()
+            return Err(ProviderError::from_response("Vertex AI", response)
                 .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            anyhow::bail!("Vertex AI API error: {}", error_text);
+                .into());
         }
 
         let vertex_response: VertexResponse = response
@@ -242,34 +275,20 @@ impl AiProvider for VertexProvider {
             } else {
                 Some(tool_calls)
             },
+            provider: None,
+            usage: vertex_response.usage_metadata.map(|u| Usage {
+                prompt_tokens: u.prompt_token_count,
+                completion_tokens: u.candidates_token_count,
+                cached_tokens: u.cached_content_token_count,
+            }),
         })
     }
 
     fn provider_name(&self) -> &str {
         "Vertex AI"
     }
-}
 
-impl VertexProvider {
-    async fn get_access_token() -> Result<String> {
-        let output = tokio::process::Command::new("gcloud")
-            .args(["auth", "print-access-token"])
-            .output()
-            .await;
-
-        match output {
-            Ok(output) if output.status.success() => {
-                let token = String::from_utf8(output.stdout)
-                    .context("Invalid UTF-8 in gcloud output")?
-                    .trim()
-                    .to_string();
-                Ok(token)
-            }
-            _ => {
-                anyhow::bail!(
-                    "Failed to get access token. Please run 'gcloud auth application-default login'"
-                )
-            }
-        }
+    fn model_name(&self) -> &str {
+        &self.model
     }
 }
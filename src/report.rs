@@ -0,0 +1,472 @@
+//! Investigation reports: a conversation written out as a timeline of
+//! questions, tool calls and answers, for pasting into postmortems.
+
+use chrono::{DateTime, Local, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::providers::Message;
+use crate::tools::TimeRange;
+
+/// Longest result summary, for tools whose output isn't understood
+const MAX_SUMMARY_CHARS: usize = 300;
+
+/// A conversation message with what the report needs besides its content
+#[derive(Debug, Clone)]
+pub struct ReportMessage {
+    pub message: Message,
+    pub at: Option<DateTime<Utc>>,
+    /// Backend a tool result came from
+    pub backend: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InvestigationReport {
+    pub title: String,
+    pub generated_at: DateTime<Utc>,
+    pub provider: String,
+    pub model: String,
+    /// Backend and index selected when the report was written
+    pub backend: Option<String>,
+    pub steps: Vec<ReportStep>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ReportStep {
+    Question {
+        at: Option<DateTime<Utc>>,
+        text: String,
+    },
+    ToolCall {
+        at: Option<DateTime<Utc>>,
+        tool: String,
+        backend: Option<String>,
+        arguments: Value,
+        /// `start_time` and `end_time` as the model asked for them
+        requested_range: Option<RequestedRange>,
+        /// Timestamps of the oldest and newest log returned
+        result_range: Option<TimeRange>,
+        summary: String,
+        failed: bool,
+    },
+    Answer {
+        at: Option<DateTime<Utc>>,
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestedRange {
+    pub start: Option<String>,
+    pub end: Option<String>,
+}
+
+impl InvestigationReport {
+    pub fn new(
+        messages: &[ReportMessage],
+        provider: &str,
+        model: &str,
+        backend: Option<String>,
+    ) -> Self {
+        let mut steps = Vec::new();
+        for (index, m) in messages.iter().enumerate() {
+            let message = &m.message;
+            match message.role.as_str() {
+                "user" => steps.push(ReportStep::Question {
+                    at: m.at,
+                    text: message.content.clone(),
+                }),
+                "assistant" => {
+                    if !message.content.trim().is_empty() {
+                        steps.push(ReportStep::Answer {
+                            at: m.at,
+                            text: message.content.clone(),
+                        });
+                    }
+                    for call in message.tool_calls.iter().flatten() {
+                        // Some providers reuse IDs across turns, so take the
+                        // first result after this call
+                        let result = messages[index + 1..].iter().find(|r| {
+                            r.message.role == "tool"
+                                && r.message.tool_call_id.as_deref() == Some(call.id.as_str())
+                        });
+                        let arguments = serde_json::from_str(&call.function.arguments)
+                            .unwrap_or_else(|_| Value::String(call.function.arguments.clone()));
+                        let (summary, result_range, failed) = match result {
+                            Some(result) => summarize_result(&result.message.content),
+                            None => ("No result".to_string(), None, true),
+                        };
+                        steps.push(ReportStep::ToolCall {
+                            at: m.at,
+                            tool: call.function.name.clone(),
+                            backend: result.and_then(|r| r.backend.clone()),
+                            requested_range: requested_range(&arguments),
+                            arguments,
+                            result_range,
+                            summary,
+                            failed,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let title = steps
+            .iter()
+            .find_map(|step| match step {
+                ReportStep::Question { text, .. } => Some(first_line(text, 80)),
+                _ => None,
+            })
+            .unwrap_or_else(|| "Investigation".to_string());
+
+        Self {
+            title,
+            generated_at: Utc::now(),
+            provider: provider.to_string(),
+            model: model.to_string(),
+            backend,
+            steps,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title);
+        out.push_str(&format!(
+            "- **Generated:** {}\n- **Model:** {} ({})\n",
+            local_time(&self.generated_at),
+            self.model,
+            self.provider
+        ));
+        if let Some(backend) = &self.backend {
+            out.push_str(&format!("- **Backend:** {}\n", backend));
+        }
+        let times: Vec<&DateTime<Utc>> = self.steps.iter().filter_map(ReportStep::at).collect();
+        if let (Some(first), Some(last)) = (times.first(), times.last()) {
+            out.push_str(&format!(
+                "- **Investigation:** {} to {}\n",
+                local_time(first),
+                local_time(last)
+            ));
+        }
+        out.push_str("\n## Timeline\n");
+
+        for step in &self.steps {
+            let at = step
+                .at()
+                .map(|at| format!("{} · ", at.with_timezone(&Local).format("%H:%M:%S")))
+                .unwrap_or_default();
+            match step {
+                ReportStep::Question { text, .. } => {
+                    out.push_str(&format!("\n### {}Question\n\n", at));
+                    for line in text.lines() {
+                        out.push_str(&format!("> {}\n", line));
+                    }
+                }
+                ReportStep::ToolCall {
+                    tool,
+                    backend,
+                    arguments,
+                    requested_range,
+                    result_range,
+                    summary,
+                    failed,
+                    ..
+                } => {
+                    let on = backend
+                        .as_ref()
+                        .map(|b| format!(" on {}", b))
+                        .unwrap_or_default();
+                    out.push_str(&format!("\n### {}`{}`{}\n\n", at, tool, on));
+                    let arguments =
+                        serde_json::to_string(arguments).unwrap_or_else(|_| arguments.to_string());
+                    out.push_str(&format!("- **Arguments:** `{}`\n", arguments));
+                    if let Some(range) = requested_range {
+                        out.push_str(&format!(
+                            "- **Requested range:** {} to {}\n",
+                            range.start.as_deref().unwrap_or("default"),
+                            range.end.as_deref().unwrap_or("now")
+                        ));
+                    }
+                    if let Some(range) = result_range {
+                        out.push_str(&format!(
+                            "- **Logs from:** {} to {}\n",
+                            range.start, range.end
+                        ));
+                    }
+                    let label = if *failed { "Failed" } else { "Result" };
+                    out.push_str(&format!("- **{}:** {}\n", label, summary));
+                }
+                ReportStep::Answer { text, .. } => {
+                    out.push_str(&format!("\n### {}Answer\n\n{}\n", at, text.trim()));
+                }
+            }
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl ReportStep {
+    pub fn at(&self) -> Option<&DateTime<Utc>> {
+        match self {
+            ReportStep::Question { at, .. }
+            | ReportStep::ToolCall { at, .. }
+            | ReportStep::Answer { at, .. } => at.as_ref(),
+        }
+    }
+}
+
+fn local_time(at: &DateTime<Utc>) -> String {
+    at.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M:%S %Z")
+        .to_string()
+}
+
+fn first_line(text: &str, max_chars: usize) -> String {
+    let line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let line = line.trim();
+    if line.chars().count() > max_chars {
+        format!("{}...", line.chars().take(max_chars).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn requested_range(arguments: &Value) -> Option<RequestedRange> {
+    let field = |name: &str| {
+        arguments
+            .get(name)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let range = RequestedRange {
+        start: field("start_time"),
+        end: field("end_time"),
+    };
+    (range.start.is_some() || range.end.is_some()).then_some(range)
+}
+
+/// One line about a tool result, the time range of the logs in it and
+/// whether the call failed
+fn summarize_result(content: &str) -> (String, Option<TimeRange>, bool) {
+    if content.starts_with("Error executing tool:") {
+        return (first_line(content, MAX_SUMMARY_CHARS), None, true);
+    }
+    let Ok(value) = serde_json::from_str::<Value>(content) else {
+        return (first_line(content, MAX_SUMMARY_CHARS), None, false);
+    };
+
+    // query_logs
+    if let Some(total) = value.get("total_count").and_then(Value::as_u64) {
+        let mut summary = format!("{} logs", total);
+        let levels = counts(value.get("level_distribution"));
+        if !levels.is_empty() {
+            summary.push_str(&format!(" ({})", levels));
+        }
+        let services: Vec<&str> = value
+            .get("services")
+            .and_then(Value::as_array)
+            .map(|s| s.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        if !services.is_empty() {
+            summary.push_str(&format!(" from {}", services.join(", ")));
+        }
+        let range = value
+            .get("time_range")
+            .and_then(|r| serde_json::from_value(r.clone()).ok());
+        return (summary, range, false);
+    }
+
+    // get_log_stats
+    if let Some(total) = value.get("total_logs").and_then(Value::as_u64) {
+        let mut summary = format!("{} logs", total);
+        let levels = counts(value.get("level_distribution"));
+        if !levels.is_empty() {
+            summary.push_str(&format!(" ({})", levels));
+        }
+        let services = counts(value.get("service_distribution"));
+        if !services.is_empty() {
+            summary.push_str(&format!("; by service: {}", services));
+        }
+        return (summary, None, false);
+    }
+
+    // list_services
+    if let Some(items) = value.as_array() {
+        let names: Vec<&str> = items.iter().filter_map(Value::as_str).collect();
+        if names.len() == items.len() {
+            return (
+                format!("{} services: {}", names.len(), names.join(", ")),
+                None,
+                false,
+            );
+        }
+    }
+
+    let compact = serde_json::to_string(&value).unwrap_or_default();
+    (first_line(&compact, MAX_SUMMARY_CHARS), None, false)
+}
+
+/// "ERROR 10, WARN 2" from a name-to-count object, largest first
+fn counts(value: Option<&Value>) -> String {
+    let Some(object) = value.and_then(Value::as_object) else {
+        return String::new();
+    };
+    let mut counts: Vec<(&String, u64)> = object
+        .iter()
+        .filter_map(|(name, count)| Some((name, count.as_u64()?)))
+        .collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    counts
+        .iter()
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{FunctionCall, ToolCall};
+
+    fn message(role: &str, content: &str) -> ReportMessage {
+        ReportMessage {
+            message: Message {
+                role: role.to_string(),
+                content: content.to_string(),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+            at: Some(Utc::now()),
+            backend: None,
+        }
+    }
+
+    fn conversation() -> Vec<ReportMessage> {
+        let mut call = message("assistant", "");
+        call.message.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: "query_logs".to_string(),
+                arguments: r#"{"query":"checkout","level":"ERROR","start_time":"1h"}"#.to_string(),
+            },
+        }]);
+        let mut result = message(
+            "tool",
+            r#"{"total_count": 3, "logs": [], "level_distribution": {"ERROR": 3},
+                "services": ["checkout"],
+                "time_range": {"start": "2024-05-01T10:00:00Z", "end": "2024-05-01T10:05:00Z"}}"#,
+        );
+        result.message.tool_call_id = Some("call_1".to_string());
+        result.backend = Some("kibana (logs-*)".to_string());
+
+        vec![
+            message("user", "Why is checkout failing?\nIt started at 10:00"),
+            call,
+            result,
+            message("assistant", "The payment gateway times out."),
+            message("error", "not part of the report"),
+        ]
+    }
+
+    #[test]
+    fn test_report_timeline() {
+        let report =
+            InvestigationReport::new(&conversation(), "openai", "gpt-4o", Some("kibana".into()));
+        assert_eq!(report.title, "Why is checkout failing?");
+        assert_eq!(report.steps.len(), 3);
+
+        let ReportStep::ToolCall {
+            tool,
+            backend,
+            requested_range,
+            result_range,
+            summary,
+            failed,
+            ..
+        } = &report.steps[1]
+        else {
+            panic!("expected a tool call, got {:?}", report.steps[1]);
+        };
+        assert_eq!(tool, "query_logs");
+        assert_eq!(backend.as_deref(), Some("kibana (logs-*)"));
+        assert_eq!(
+            requested_range.as_ref().unwrap().start.as_deref(),
+            Some("1h")
+        );
+        assert_eq!(result_range.as_ref().unwrap().end, "2024-05-01T10:05:00Z");
+        assert_eq!(summary, "3 logs (ERROR 3) from checkout");
+        assert!(!failed);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.starts_with("# Why is checkout failing?\n"));
+        assert!(markdown.contains("> It started at 10:00\n"));
+        assert!(markdown.contains("`query_logs` on kibana (logs-*)"));
+        assert!(markdown.contains("- **Requested range:** 1h to now\n"));
+        assert!(markdown.contains("The payment gateway times out."));
+
+        let json: Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["steps"][1]["type"], "tool_call");
+        assert_eq!(json["steps"][1]["arguments"]["level"], "ERROR");
+    }
+
+    #[test]
+    fn test_reused_call_ids_match_their_own_turn() {
+        let mut messages = conversation();
+        let mut call = messages[1].clone();
+        let mut result = message("tool", "Error executing tool: timeout");
+        result.message.tool_call_id = Some("call_1".to_string());
+        call.message.tool_calls.as_mut().unwrap()[0]
+            .function
+            .arguments = r#"{"query":"payment"}"#.to_string();
+        messages.extend([message("user", "And payment?"), call, result]);
+
+        let report = InvestigationReport::new(&messages, "ollama", "qwen2.5", None);
+        let summaries: Vec<(&str, bool)> = report
+            .steps
+            .iter()
+            .filter_map(|step| match step {
+                ReportStep::ToolCall {
+                    summary, failed, ..
+                } => Some((summary.as_str(), *failed)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            summaries,
+            vec![
+                ("3 logs (ERROR 3) from checkout", false),
+                ("Error executing tool: timeout", true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summaries() {
+        assert_eq!(
+            summarize_result(r#"["checkout", "payment"]"#).0,
+            "2 services: checkout, payment"
+        );
+        let (summary, _, failed) = summarize_result("Error executing tool: timeout");
+        assert!(failed);
+        assert_eq!(summary, "Error executing tool: timeout");
+        assert_eq!(
+            summarize_result(
+                r#"{"total_logs": 5, "level_distribution": {"INFO": 1, "ERROR": 4},
+                    "service_distribution": {"api": 5}}"#
+            )
+            .0,
+            "5 logs (ERROR 4, INFO 1); by service: api 5"
+        );
+    }
+}
@@ -20,11 +20,39 @@ impl Default for RetryConfig {
     }
 }
 
+/// What to do after an attempt fails
+pub enum RetryAction {
+    /// Give up and return the error
+    Stop,
+    /// Retry after the next backoff delay
+    Retry,
+    /// Retry after the delay the server asked for (e.g. `Retry-After`)
+    RetryAfter(Duration),
+}
+
 #[allow(dead_code)]
 pub async fn retry_with_backoff<F, T, Fut>(operation: F, config: &RetryConfig) -> Result<T>
 where
     F: Fn() -> Fut,
     Fut: std::future::Future<Output = Result<T>>,
+{
+    retry_with_policy(operation, config, |_| RetryAction::Retry).await
+}
+
+/// Like [`retry_with_backoff`], but `policy` decides per error whether to retry.
+///
+/// A server-requested delay longer than `max_delay` stops retrying rather
+/// than stalling the caller. Dropping the returned future cancels any
+/// pending wait, so callers can abort with `tokio::select!` or a timeout.
+pub async fn retry_with_policy<F, T, Fut, P>(
+    operation: F,
+    config: &RetryConfig,
+    policy: P,
+) -> Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+    P: Fn(&anyhow::Error) -> RetryAction,
 {
     let mut attempt = 0;
     let mut delay = config.initial_delay;
@@ -35,20 +63,23 @@ where
             Err(e) => {
                 attempt += 1;
 
+                let wait = match policy(&e) {
+                    RetryAction::Stop => return Err(e),
+                    RetryAction::Retry => delay,
+                    RetryAction::RetryAfter(after) if after <= config.max_delay => after,
+                    RetryAction::RetryAfter(_) => return Err(e),
+                };
+
+                // Context rather than a new error, so callers can still
+                // downcast to what the operation returned
                 if attempt >= config.max_retries {
-                    return Err(anyhow::anyhow!(
-                        "Operation failed after {} attempts: {}",
-                        config.max_retries,
-                        e
-                    ));
+                    return Err(e.context(format!(
+                        "Operation failed after {} attempts",
+                        config.max_retries
+                    )));
                 }
 
-                eprintln!(
-                    "Attempt {}/{} failed: {}. Retrying in {:?}...",
-                    attempt, config.max_retries, e, delay
-                );
-
-                tokio::time::sleep(delay).await;
+                tokio::time::sleep(wait).await;
 
                 delay =
                     Duration::from_millis(((delay.as_millis() as f64) * config.multiplier) as u64)
@@ -139,7 +170,58 @@ mod tests {
         )
         .await;
 
-        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "Operation failed after 2 attempts: Persistent failure"
+        );
         assert_eq!(call_count.load(Ordering::SeqCst), 2);
     }
+
+    #[tokio::test]
+    async fn test_policy_stop_returns_first_error() {
+        let config = RetryConfig::default();
+        let call_count = Arc::new(AtomicU32::new(0));
+
+        let result = retry_with_policy(
+            || {
+                let count = call_count.clone();
+                async move {
+                    count.fetch_add(1, Ordering::SeqCst);
+                    Err::<i32, anyhow::Error>(anyhow::anyhow!("Bad request"))
+                }
+            },
+            &config,
+            |_| RetryAction::Stop,
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().to_string(), "Bad request");
+        assert_eq!(call_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_policy_gives_up_when_retry_after_exceeds_max_delay() {
+        let config = RetryConfig {
+            max_delay: Duration::from_millis(100),
+            ..RetryConfig::default()
+        };
+        let call_count = Arc::new(AtomicU32::new(0));
+
+        let result = retry_with_policy(
+            || {
+                let count = call_count.clone();
+                async move {
+                    count.fetch_add(1, Ordering::SeqCst);
+                    Err::<i32, anyhow::Error>(anyhow::anyhow!("Rate limited"))
+                }
+            },
+            &config,
+            |_| RetryAction::RetryAfter(Duration::from_secs(60)),
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(call_count.load(Ordering::SeqCst), 1);
+    }
 }
@@ -0,0 +1,153 @@
+use super::{
+    decode_session, encode_session, ConversationInfo, SessionData, SessionStorage, StoredMessage,
+};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+const MESSAGES_SUFFIX: &str = ".json";
+const INFO_SUFFIX: &str = ".info.json";
+
+/// Sessions kept as JSON files in one directory: `<id>.json` holds the
+/// messages and `<id>.info.json` the summary shown by `/resume`, so
+/// listing never has to read whole conversations. Files never expire.
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create session directory {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    /// `<data dir>/zeteo/sessions`
+    pub fn default_dir() -> Result<PathBuf> {
+        let data_dir = dirs::data_dir().context("Could not determine data directory")?;
+        Ok(data_dir.join("zeteo").join("sessions"))
+    }
+
+    #[allow(dead_code)]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, session_id: &str, suffix: &str) -> Result<PathBuf> {
+        // Ids come from the store (UUIDs) or from files we listed, but they
+        // end up in paths, so refuse anything that could leave the directory.
+        let valid = !session_id.is_empty()
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            bail!("Invalid session id '{}'", session_id);
+        }
+        Ok(self.dir.join(format!("{}{}", session_id, suffix)))
+    }
+}
+
+/// Write through a temporary file so a crash never leaves half a session.
+async fn write_atomic(path: &Path, data: Vec<u8>) -> Result<()> {
+    let tmp = path.with_extension(format!("tmp-{}", std::process::id()));
+    tokio::fs::write(&tmp, data)
+        .await
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+async fn read_file(path: &Path) -> Result<Option<Vec<u8>>> {
+    match tokio::fs::read(path).await {
+        Ok(data) => Ok(Some(data)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+async fn remove_if_exists(path: &Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != ErrorKind::NotFound => {
+            Err(e).with_context(|| format!("Failed to delete {}", path.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[async_trait]
+impl SessionStorage for FileStorage {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    async fn save(&self, info: &ConversationInfo, messages: &[StoredMessage]) -> Result<()> {
+        // Messages first: an info file without messages would show up in
+        // /resume as an empty conversation.
+        write_atomic(
+            &self.path(&info.id, MESSAGES_SUFFIX)?,
+            encode_session(messages)?,
+        )
+        .await?;
+        self.save_info(info).await
+    }
+
+    async fn load(&self, session_id: &str) -> Result<Option<SessionData>> {
+        let path = self.path(session_id, MESSAGES_SUFFIX)?;
+        read_file(&path)
+            .await?
+            .map(|data| {
+                decode_session(&data).with_context(|| format!("Failed to read {}", path.display()))
+            })
+            .transpose()
+    }
+
+    async fn info(&self, session_id: &str) -> Result<Option<ConversationInfo>> {
+        let path = self.path(session_id, INFO_SUFFIX)?;
+        read_file(&path)
+            .await?
+            .map(|data| {
+                serde_json::from_slice(&data)
+                    .with_context(|| format!("Failed to parse {}", path.display()))
+            })
+            .transpose()
+    }
+
+    async fn save_info(&self, info: &ConversationInfo) -> Result<()> {
+        write_atomic(
+            &self.path(&info.id, INFO_SUFFIX)?,
+            serde_json::to_vec(info)?,
+        )
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<ConversationInfo>> {
+        let mut entries = tokio::fs::read_dir(&self.dir)
+            .await
+            .with_context(|| format!("Failed to read {}", self.dir.display()))?;
+
+        let mut sessions = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            if !name.to_string_lossy().ends_with(INFO_SUFFIX) {
+                continue;
+            }
+            // A corrupt file shouldn't hide every other session.
+            if let Ok(Some(data)) = read_file(&entry.path()).await {
+                if let Ok(info) = serde_json::from_slice::<ConversationInfo>(&data) {
+                    sessions.push(info);
+                }
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    async fn delete(&self, session_id: &str) -> Result<()> {
+        remove_if_exists(&self.path(session_id, INFO_SUFFIX)?).await?;
+        remove_if_exists(&self.path(session_id, MESSAGES_SUFFIX)?).await
+    }
+}
@@ -0,0 +1,203 @@
+use super::StoredMessage;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Format written by this build. Version 1 was a bare JSON array of
+/// `{role, content}` messages without tool calls or results.
+pub const SESSION_FORMAT_VERSION: u32 = 2;
+
+/// A session's messages as read from storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionData {
+    /// Format the session was saved in
+    pub version: u32,
+    pub messages: Vec<StoredMessage>,
+}
+
+/// Serialize messages in the current format
+pub fn encode_session(messages: &[StoredMessage]) -> Result<Vec<u8>> {
+    #[derive(Serialize)]
+    struct Envelope<'a> {
+        version: u32,
+        messages: &'a [StoredMessage],
+    }
+
+    Ok(serde_json::to_vec(&Envelope {
+        version: SESSION_FORMAT_VERSION,
+        messages,
+    })?)
+}
+
+/// Read messages saved in any known format. Older formats are upgraded in
+/// memory; `version` says which one was found.
+pub fn decode_session(data: &[u8]) -> Result<SessionData> {
+    let value: Value = serde_json::from_slice(data).context("Session is not valid JSON")?;
+
+    // Version 1 messages are a subset of version 2 ones, so the same struct
+    // reads them with the new fields left empty.
+    if value.is_array() {
+        return Ok(SessionData {
+            version: 1,
+            messages: serde_json::from_value(value).context("Invalid version 1 session")?,
+        });
+    }
+
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .context("Session has no format version")? as u32;
+    if version > SESSION_FORMAT_VERSION {
+        bail!(
+            "Session was saved in format {} by a newer zeteo (this one reads up to {})",
+            version,
+            SESSION_FORMAT_VERSION
+        );
+    }
+
+    serde_json::from_value(value).with_context(|| format!("Invalid version {} session", version))
+}
+
+const MISSING_RESULT: &str = "No result was saved for this tool call.";
+
+/// Make a history safe to send back to a provider: every tool call of an
+/// assistant message is answered by a tool message right after it, and
+/// every tool message answers such a call. Providers reject anything else,
+/// which a session saved mid-turn (or cancelled while tools ran) can hold.
+pub fn repair_tool_exchanges(messages: Vec<StoredMessage>) -> Vec<StoredMessage> {
+    let mut repaired = Vec::with_capacity(messages.len());
+    // Calls of the latest assistant message still waiting for a result
+    let mut pending: Vec<String> = Vec::new();
+    let mut answered: HashSet<String> = HashSet::new();
+
+    for message in messages {
+        if message.role == "tool" {
+            match &message.tool_call_id {
+                Some(id) if pending.contains(id) && answered.insert(id.clone()) => {
+                    repaired.push(message)
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        close_pending(&mut repaired, &mut pending, &answered);
+        answered.clear();
+
+        if let Some(calls) = &message.tool_calls {
+            pending = calls.iter().map(|call| call.id.clone()).collect();
+        }
+        repaired.push(message);
+    }
+    close_pending(&mut repaired, &mut pending, &answered);
+
+    repaired
+}
+
+fn close_pending(
+    repaired: &mut Vec<StoredMessage>,
+    pending: &mut Vec<String>,
+    answered: &HashSet<String>,
+) {
+    for id in pending.drain(..) {
+        if !answered.contains(&id) {
+            repaired.push(StoredMessage {
+                role: "tool".to_string(),
+                content: MISSING_RESULT.to_string(),
+                tool_call_id: Some(id),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{FunctionCall, ToolCall};
+
+    fn message(role: &str, content: &str) -> StoredMessage {
+        StoredMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn calls(ids: &[&str]) -> StoredMessage {
+        StoredMessage {
+            tool_calls: Some(
+                ids.iter()
+                    .map(|id| ToolCall {
+                        id: id.to_string(),
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: "query_logs".to_string(),
+                            arguments: "{}".to_string(),
+                        },
+                    })
+                    .collect(),
+            ),
+            ..message("assistant", "")
+        }
+    }
+
+    fn result(id: &str) -> StoredMessage {
+        StoredMessage {
+            tool_call_id: Some(id.to_string()),
+            ..message("tool", "[]")
+        }
+    }
+
+    #[test]
+    fn test_decodes_both_versions() {
+        let v1 = decode_session(br#"[{"role":"user","content":"hi"}]"#).unwrap();
+        assert_eq!(v1.version, 1);
+        assert_eq!(v1.messages[0].content, "hi");
+        assert!(v1.messages[0].tool_calls.is_none());
+
+        let messages = vec![message("user", "errors?"), calls(&["a"]), result("a")];
+        let v2 = decode_session(&encode_session(&messages).unwrap()).unwrap();
+        assert_eq!(v2.version, SESSION_FORMAT_VERSION);
+        assert_eq!(v2.messages.len(), 3);
+        assert_eq!(v2.messages[1].tool_calls.as_ref().unwrap()[0].id, "a");
+        assert_eq!(v2.messages[2].tool_call_id.as_deref(), Some("a"));
+
+        assert!(decode_session(br#"{"version":99,"messages":[]}"#).is_err());
+    }
+
+    #[test]
+    fn test_repair_tool_exchanges() {
+        let messages = vec![
+            message("user", "errors?"),
+            calls(&["a", "b"]),
+            result("b"),
+            result("stray"),
+            message("assistant", "Two services fail."),
+            result("a"),
+            message("user", "and now?"),
+            calls(&["c"]),
+        ];
+
+        let repaired = repair_tool_exchanges(messages);
+        let shape: Vec<(&str, Option<&str>)> = repaired
+            .iter()
+            .map(|m| (m.role.as_str(), m.tool_call_id.as_deref()))
+            .collect();
+        assert_eq!(
+            shape,
+            vec![
+                ("user", None),
+                ("assistant", None),
+                ("tool", Some("b")),
+                ("tool", Some("a")),
+                ("assistant", None),
+                ("user", None),
+                ("assistant", None),
+                ("tool", Some("c")),
+            ]
+        );
+        assert_eq!(repaired[3].content, MISSING_RESULT);
+    }
+}
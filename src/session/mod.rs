@@ -0,0 +1,591 @@
+mod file;
+mod format;
+mod redis;
+mod search;
+mod summary;
+
+pub use file::FileStorage;
+pub use format::{
+    decode_session, encode_session, repair_tool_exchanges, SessionData, SESSION_FORMAT_VERSION,
+};
+pub use redis::RedisStorage;
+pub use search::{parse_tags, search_sessions, SessionEntry};
+pub use summary::{summarize, SessionSummary};
+
+use crate::config::{Config, SessionConfig, SessionStorageKind};
+use crate::providers::ToolCall;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// A serializable chat message for session storage
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoredMessage {
+    pub role: String,
+    pub content: String,
+    /// Tools an assistant message asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// The call a tool message answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<DateTime<Utc>>,
+    /// Log backend (or MCP server) a tool result came from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+}
+
+/// Conversation summary listed by `/resume`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversationInfo {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub message_count: usize,
+    /// Labels such as incident IDs, set from `/resume`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// One paragraph on what was investigated, written by a model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// The title was set by hand; generated titles don't replace it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub renamed: bool,
+}
+
+/// Where saved conversations live
+#[async_trait]
+pub trait SessionStorage: Send + Sync {
+    /// Short name for messages ("file", "redis")
+    fn name(&self) -> &str;
+
+    /// Create or replace the session `info.id`, in the current format
+    async fn save(&self, info: &ConversationInfo, messages: &[StoredMessage]) -> Result<()>;
+
+    /// Messages of a session as saved; `None` when it doesn't exist (or
+    /// has expired)
+    async fn load(&self, session_id: &str) -> Result<Option<SessionData>>;
+
+    /// Summary of one session; `None` when it doesn't exist
+    async fn info(&self, session_id: &str) -> Result<Option<ConversationInfo>>;
+
+    /// Replace a session's summary, leaving its messages alone
+    async fn save_info(&self, info: &ConversationInfo) -> Result<()>;
+
+    /// Every stored session, in no particular order
+    async fn list(&self) -> Result<Vec<ConversationInfo>>;
+
+    /// Remove a session; deleting a missing one is not an error
+    async fn delete(&self, session_id: &str) -> Result<()>;
+}
+
+/// The conversation being saved, on top of a [`SessionStorage`]
+pub struct SessionStore {
+    storage: Arc<dyn SessionStorage>,
+    current_session_id: String,
+}
+
+impl SessionStore {
+    pub fn new(storage: Arc<dyn SessionStorage>) -> Self {
+        Self {
+            storage,
+            current_session_id: Uuid::new_v4().to_string(),
+        }
+    }
+
+    /// Get the current session ID
+    pub fn current_session_id(&self) -> &str {
+        &self.current_session_id
+    }
+
+    /// Set the current session ID (for resuming sessions)
+    pub fn set_current_session_id(&mut self, id: String) {
+        self.current_session_id = id;
+    }
+
+    /// Save messages to the current session. The creation time, tags and
+    /// title (once there is one) of an existing session are kept.
+    pub async fn save_messages(&self, messages: &[StoredMessage]) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let mut info = self
+            .storage
+            .info(&self.current_session_id)
+            .await?
+            .unwrap_or_else(|| ConversationInfo {
+                id: self.current_session_id.clone(),
+                title: UNTITLED.to_string(),
+                created_at: now,
+                updated_at: now,
+                message_count: 0,
+                tags: Vec::new(),
+                summary: None,
+                renamed: false,
+            });
+
+        if info.title == UNTITLED {
+            if let Some(first_msg) = messages.iter().find(|m| m.role == "user") {
+                let title: String = first_msg.content.chars().take(50).collect();
+                info.title = if first_msg.content.chars().count() > 50 {
+                    format!("{}...", title)
+                } else {
+                    title
+                };
+            }
+        }
+        info.updated_at = now;
+        info.message_count = messages.len();
+
+        self.storage.save(&info, messages).await
+    }
+
+    /// Load messages from a session, ready to continue: sessions saved in
+    /// older formats are upgraded and broken tool exchanges repaired
+    pub async fn load_messages(&self, session_id: &str) -> Result<Vec<StoredMessage>> {
+        Ok(self
+            .storage
+            .load(session_id)
+            .await?
+            .map(|data| repair_tool_exchanges(data.messages))
+            .unwrap_or_default())
+    }
+
+    /// List all sessions, most recently updated first
+    pub async fn list_sessions(&self) -> Result<Vec<ConversationInfo>> {
+        let mut result = self.storage.list().await?;
+
+        result.sort_by_key(|b| std::cmp::Reverse(b.updated_at));
+
+        Ok(result)
+    }
+
+    /// All sessions with their text, for [`search_sessions`]
+    pub async fn search_index(&self) -> Result<Vec<SessionEntry>> {
+        let mut entries = Vec::new();
+        for info in self.list_sessions().await? {
+            let text = match self.storage.load(&info.id).await {
+                Ok(Some(data)) => data
+                    .messages
+                    .iter()
+                    .filter(|m| m.role == "user" || m.role == "assistant")
+                    .map(|m| m.content.to_lowercase())
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                // Still listed so it can be renamed or deleted
+                Ok(None) | Err(_) => String::new(),
+            };
+            entries.push(SessionEntry { info, text });
+        }
+        Ok(entries)
+    }
+
+    /// Give a session a new title
+    pub async fn rename_session(&self, session_id: &str, title: &str) -> Result<ConversationInfo> {
+        let title = title.trim();
+        if title.is_empty() {
+            bail!("The title can't be empty");
+        }
+        self.update_info(session_id, |info| {
+            info.title = title.to_string();
+            info.renamed = true;
+        })
+        .await
+    }
+
+    /// Store a generated summary, and its title unless the session was
+    /// renamed
+    pub async fn apply_summary(
+        &self,
+        session_id: &str,
+        summary: &SessionSummary,
+    ) -> Result<ConversationInfo> {
+        self.update_info(session_id, |info| {
+            if !info.renamed {
+                info.title = summary.title.clone();
+            }
+            info.summary = Some(summary.summary.clone());
+        })
+        .await
+    }
+
+    /// Summary of a session; `None` when it hasn't been saved
+    pub async fn session_info(&self, session_id: &str) -> Result<Option<ConversationInfo>> {
+        self.storage.info(session_id).await
+    }
+
+    /// Replace a session's tags
+    pub async fn tag_session(
+        &self,
+        session_id: &str,
+        tags: Vec<String>,
+    ) -> Result<ConversationInfo> {
+        self.update_info(session_id, |info| info.tags = tags).await
+    }
+
+    async fn update_info(
+        &self,
+        session_id: &str,
+        update: impl FnOnce(&mut ConversationInfo),
+    ) -> Result<ConversationInfo> {
+        let mut info = self
+            .storage
+            .info(session_id)
+            .await?
+            .with_context(|| format!("Session {} not found", session_id))?;
+        update(&mut info);
+        self.storage.save_info(&info).await?;
+        Ok(info)
+    }
+
+    /// Delete a session
+    pub async fn delete_session(&self, session_id: &str) -> Result<()> {
+        self.storage.delete(session_id).await
+    }
+
+    /// Clear the current session's messages
+    pub async fn clear_current_session(&self) -> Result<()> {
+        self.storage.delete(&self.current_session_id).await
+    }
+}
+
+/// Title until the first user message gives the session one
+const UNTITLED: &str = "New conversation";
+
+const DEFAULT_REDIS_URL: &str = "redis://localhost:6379";
+
+/// How long an unconfigured start waits for Redis before using files
+const REDIS_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Open a storage of the given kind; `None` for [`SessionStorageKind::None`]
+pub async fn open_storage(
+    kind: SessionStorageKind,
+    config: Option<&SessionConfig>,
+) -> Result<Option<Arc<dyn SessionStorage>>> {
+    match kind {
+        SessionStorageKind::File => {
+            let dir = match config.and_then(|c| c.path.clone()) {
+                Some(dir) => dir,
+                None => FileStorage::default_dir()?,
+            };
+            Ok(Some(Arc::new(FileStorage::new(dir)?)))
+        }
+        SessionStorageKind::Redis => {
+            let url = config
+                .and_then(|c| c.redis_url.clone())
+                .or_else(|| std::env::var("REDIS_URL").ok())
+                .unwrap_or_else(|| DEFAULT_REDIS_URL.to_string());
+            let storage = RedisStorage::connect(&url)
+                .await
+                .with_context(|| format!("Failed to connect to Redis at {}", url))?;
+            Ok(Some(Arc::new(storage)))
+        }
+        SessionStorageKind::None => Ok(None),
+    }
+}
+
+/// The configured storage. Without a `storage` setting, Redis if it can be
+/// reached (at `REDIS_URL` or localhost, where sessions used to live), else
+/// files.
+pub async fn open_configured_storage(
+    config: Option<&SessionConfig>,
+) -> Result<Option<Arc<dyn SessionStorage>>> {
+    if let Some(kind) = config.and_then(|c| c.storage) {
+        return open_storage(kind, config).await;
+    }
+    match tokio::time::timeout(
+        REDIS_PROBE_TIMEOUT,
+        open_storage(SessionStorageKind::Redis, config),
+    )
+    .await
+    {
+        Ok(Ok(storage)) => Ok(storage),
+        _ => open_storage(SessionStorageKind::File, config).await,
+    }
+}
+
+/// The session store configured in `config`; `Ok(None)` when saving is
+/// turned off.
+pub async fn create_session_store(config: Option<&Config>) -> Result<Option<SessionStore>> {
+    let settings = config.and_then(|c| c.sessions.as_ref());
+    let storage = open_configured_storage(settings).await?;
+    Ok(storage.map(SessionStore::new))
+}
+
+/// Copy every session from one storage to another, keeping ids and
+/// timestamps. Sessions whose messages have expired are skipped. Returns
+/// how many were copied.
+pub async fn migrate(from: &dyn SessionStorage, to: &dyn SessionStorage) -> Result<usize> {
+    let mut copied = 0;
+    for info in from.list().await? {
+        let Some(data) = from
+            .load(&info.id)
+            .await
+            .with_context(|| format!("Failed to read session {}", info.id))?
+        else {
+            continue;
+        };
+        to.save(&info, &data.messages)
+            .await
+            .with_context(|| format!("Failed to write session {}", info.id))?;
+        copied += 1;
+    }
+
+    Ok(copied)
+}
+
+/// Rewrite sessions saved in an older format in the current one. Returns
+/// how many were rewritten.
+pub async fn upgrade(storage: &dyn SessionStorage) -> Result<usize> {
+    let mut upgraded = 0;
+    for info in storage.list().await? {
+        let data = storage
+            .load(&info.id)
+            .await
+            .with_context(|| format!("Failed to read session {}", info.id))?;
+        let Some(data) = data.filter(|data| data.version < SESSION_FORMAT_VERSION) else {
+            continue;
+        };
+        storage
+            .save(&info, &data.messages)
+            .await
+            .with_context(|| format!("Failed to write session {}", info.id))?;
+        upgraded += 1;
+    }
+
+    Ok(upgraded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stored_message_serialization() {
+        let msg = StoredMessage {
+            role: "user".to_string(),
+            content: "Hello world".to_string(),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&msg).unwrap();
+        let deserialized: StoredMessage = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.role, "user");
+        assert_eq!(deserialized.content, "Hello world");
+    }
+
+    #[test]
+    fn test_conversation_info_serialization() {
+        let info = ConversationInfo {
+            id: "test-id".to_string(),
+            title: "Test conversation".to_string(),
+            created_at: 1234567890,
+            updated_at: 1234567890,
+            message_count: 5,
+            tags: vec!["INC-4521".to_string()],
+            summary: None,
+            renamed: false,
+        };
+
+        let json = serde_json::to_string(&info).unwrap();
+        let deserialized: ConversationInfo = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.id, "test-id");
+        assert_eq!(deserialized.title, "Test conversation");
+        assert_eq!(deserialized.message_count, 5);
+        assert_eq!(deserialized.tags, vec!["INC-4521"]);
+    }
+
+    fn temp_storage() -> FileStorage {
+        let dir = std::env::temp_dir().join(format!("zeteo-sessions-{}", Uuid::new_v4().simple()));
+        FileStorage::new(dir).unwrap()
+    }
+
+    fn message(role: &str, content: &str) -> StoredMessage {
+        StoredMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_storage_round_trip() {
+        let storage = Arc::new(temp_storage());
+        let store = SessionStore::new(storage.clone());
+
+        store
+            .save_messages(&[
+                message("user", "Why is checkout failing?"),
+                message("assistant", "Timeouts."),
+            ])
+            .await
+            .unwrap();
+
+        let sessions = store.list_sessions().await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, store.current_session_id());
+        assert_eq!(sessions[0].title, "Why is checkout failing?");
+        assert_eq!(sessions[0].message_count, 2);
+
+        let loaded = store
+            .load_messages(store.current_session_id())
+            .await
+            .unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].content, "Timeouts.");
+
+        assert!(store.load_messages("missing").await.unwrap().is_empty());
+        assert!(store.load_messages("../config").await.is_err());
+
+        store.clear_current_session().await.unwrap();
+        assert!(store.list_sessions().await.unwrap().is_empty());
+        assert!(store
+            .load_messages(store.current_session_id())
+            .await
+            .unwrap()
+            .is_empty());
+
+        std::fs::remove_dir_all(storage.dir()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_save_keeps_creation_time_title_and_tags() {
+        let storage = Arc::new(temp_storage());
+        let store = SessionStore::new(storage.clone());
+        let id = store.current_session_id().to_string();
+
+        store
+            .save_messages(&[message("user", "Checkout errors")])
+            .await
+            .unwrap();
+        let mut info = storage.info(&id).await.unwrap().unwrap();
+        info.created_at -= 3600;
+        storage.save_info(&info).await.unwrap();
+
+        store
+            .rename_session(&id, "INC-4521 checkout")
+            .await
+            .unwrap();
+        store
+            .tag_session(&id, parse_tags("INC-4521 payments"))
+            .await
+            .unwrap();
+        assert!(store.rename_session("missing", "x").await.is_err());
+
+        store
+            .save_messages(&[
+                message("user", "Checkout errors"),
+                message("assistant", "Timeouts."),
+            ])
+            .await
+            .unwrap();
+        let saved = storage.info(&id).await.unwrap().unwrap();
+        assert_eq!(saved.created_at, info.created_at);
+        assert!(saved.updated_at > saved.created_at);
+        assert_eq!(saved.title, "INC-4521 checkout");
+        assert_eq!(saved.tags, vec!["INC-4521", "payments"]);
+        assert_eq!(saved.message_count, 2);
+
+        let summary = SessionSummary {
+            title: "Checkout payment timeouts".to_string(),
+            summary: "Payments to the card processor timed out.".to_string(),
+        };
+        let summarized = store.apply_summary(&id, &summary).await.unwrap();
+        assert_eq!(summarized.title, "INC-4521 checkout");
+        assert_eq!(
+            summarized.summary.as_deref(),
+            Some(summary.summary.as_str())
+        );
+
+        let index = store.search_index().await.unwrap();
+        assert_eq!(search_sessions(&index, "timeouts"), vec![0]);
+        assert_eq!(search_sessions(&index, "processor"), vec![0]);
+
+        store.delete_session(&id).await.unwrap();
+        assert!(store.list_sessions().await.unwrap().is_empty());
+
+        std::fs::remove_dir_all(storage.dir()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_migrate_keeps_sessions() {
+        let from = temp_storage();
+        let to = temp_storage();
+
+        let info = ConversationInfo {
+            id: "first".to_string(),
+            title: "Checkout errors".to_string(),
+            created_at: 100,
+            updated_at: 200,
+            message_count: 1,
+            tags: Vec::new(),
+            summary: None,
+            renamed: false,
+        };
+        from.save(&info, &[message("user", "Checkout errors")])
+            .await
+            .unwrap();
+        // Listed, but its messages are gone (an expired Redis session)
+        let expired = ConversationInfo {
+            id: "expired".to_string(),
+            ..info.clone()
+        };
+        from.save(&expired, &[]).await.unwrap();
+        std::fs::remove_file(from.dir().join("expired.json")).unwrap();
+
+        assert_eq!(migrate(&from, &to).await.unwrap(), 1);
+
+        let sessions = to.list().await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, "first");
+        assert_eq!(sessions[0].created_at, 100);
+        assert_eq!(sessions[0].updated_at, 200);
+        assert_eq!(
+            to.load("first").await.unwrap().unwrap().messages[0].content,
+            "Checkout errors"
+        );
+
+        std::fs::remove_dir_all(from.dir()).unwrap();
+        std::fs::remove_dir_all(to.dir()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_rewrites_old_sessions() {
+        let storage = Arc::new(temp_storage());
+        let info = ConversationInfo {
+            id: "old".to_string(),
+            title: "Checkout errors".to_string(),
+            created_at: 100,
+            updated_at: 200,
+            message_count: 2,
+            tags: Vec::new(),
+            summary: None,
+            renamed: false,
+        };
+        storage.save(&info, &[]).await.unwrap();
+        std::fs::write(
+            storage.dir().join("old.json"),
+            r#"[{"role":"user","content":"Checkout errors"},{"role":"assistant","content":"Timeouts."}]"#,
+        )
+        .unwrap();
+
+        let store = SessionStore::new(storage.clone());
+        let loaded = store.load_messages("old").await.unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].content, "Timeouts.");
+
+        assert_eq!(upgrade(storage.as_ref()).await.unwrap(), 1);
+        let data = storage.load("old").await.unwrap().unwrap();
+        assert_eq!(data.version, SESSION_FORMAT_VERSION);
+        assert_eq!(data.messages.len(), 2);
+        assert_eq!(upgrade(storage.as_ref()).await.unwrap(), 0);
+
+        std::fs::remove_dir_all(storage.dir()).unwrap();
+    }
+}
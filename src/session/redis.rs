@@ -0,0 +1,95 @@
+use super::{
+    decode_session, encode_session, ConversationInfo, SessionData, SessionStorage, StoredMessage,
+};
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::collections::HashMap;
+use tokio::sync::Mutex;
+
+const SESSION_PREFIX: &str = "zeteo:session:";
+const SESSION_LIST_KEY: &str = "zeteo:sessions";
+const SESSION_TTL_SECONDS: u64 = 60 * 60 * 24 * 7;
+
+/// Sessions kept in Redis; messages expire a week after the last save
+pub struct RedisStorage {
+    conn: Mutex<ConnectionManager>,
+}
+
+impl RedisStorage {
+    pub async fn connect(redis_url: &str) -> Result<Self> {
+        let client = redis::Client::open(redis_url)?;
+        let conn = ConnectionManager::new(client).await?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+#[async_trait]
+impl SessionStorage for RedisStorage {
+    fn name(&self) -> &str {
+        "redis"
+    }
+
+    async fn save(&self, info: &ConversationInfo, messages: &[StoredMessage]) -> Result<()> {
+        let key = format!("{}{}", SESSION_PREFIX, info.id);
+        let data = encode_session(messages)?;
+        let info_json = serde_json::to_string(info)?;
+
+        let mut conn = self.conn.lock().await;
+        conn.set_ex::<_, _, ()>(&key, &data, SESSION_TTL_SECONDS)
+            .await?;
+        conn.hset::<_, _, _, ()>(SESSION_LIST_KEY, &info.id, &info_json)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &str) -> Result<Option<SessionData>> {
+        let key = format!("{}{}", SESSION_PREFIX, session_id);
+
+        let mut conn = self.conn.lock().await;
+        let data: Option<Vec<u8>> = conn.get(&key).await?;
+
+        data.map(|data| decode_session(&data)).transpose()
+    }
+
+    async fn info(&self, session_id: &str) -> Result<Option<ConversationInfo>> {
+        let mut conn = self.conn.lock().await;
+        let info: Option<String> = conn.hget(SESSION_LIST_KEY, session_id).await?;
+
+        Ok(info.map(|json| serde_json::from_str(&json)).transpose()?)
+    }
+
+    async fn save_info(&self, info: &ConversationInfo) -> Result<()> {
+        let info_json = serde_json::to_string(info)?;
+
+        let mut conn = self.conn.lock().await;
+        conn.hset::<_, _, _, ()>(SESSION_LIST_KEY, &info.id, &info_json)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn list(&self) -> Result<Vec<ConversationInfo>> {
+        let mut conn = self.conn.lock().await;
+        let sessions: HashMap<String, String> = conn.hgetall(SESSION_LIST_KEY).await?;
+
+        Ok(sessions
+            .values()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    }
+
+    async fn delete(&self, session_id: &str) -> Result<()> {
+        let key = format!("{}{}", SESSION_PREFIX, session_id);
+
+        let mut conn = self.conn.lock().await;
+        conn.del::<_, ()>(&key).await?;
+        conn.hdel::<_, _, ()>(SESSION_LIST_KEY, session_id).await?;
+
+        Ok(())
+    }
+}
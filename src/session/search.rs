@@ -0,0 +1,165 @@
+use super::ConversationInfo;
+
+/// A saved conversation with the text `/resume` searches
+#[derive(Debug, Clone)]
+pub struct SessionEntry {
+    pub info: ConversationInfo,
+    /// Lowercased user and assistant messages (tool output is left out)
+    pub text: String,
+}
+
+/// Indices of the entries matching `query`, best match first; entries
+/// that score the same keep their order. Every word of the query has to
+/// match the title or a tag (fuzzily) or appear in the summary or the
+/// conversation.
+pub fn search_sessions(entries: &[SessionEntry], query: &str) -> Vec<usize> {
+    let words: Vec<String> = query
+        .split_whitespace()
+        .map(|word| word.trim_start_matches('#').to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    let mut matches: Vec<(usize, u32)> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| {
+            let title = entry.info.title.to_lowercase();
+            let tags: Vec<String> = entry.info.tags.iter().map(|t| t.to_lowercase()).collect();
+            let summary = entry.info.summary.as_deref().unwrap_or("").to_lowercase();
+            words
+                .iter()
+                .map(|word| word_score(word, &title, &tags, &summary, &entry.text))
+                .sum::<Option<u32>>()
+                .map(|score| (i, score))
+        })
+        .collect();
+
+    matches.sort_by_key(|&(i, score)| (std::cmp::Reverse(score), i));
+    matches.into_iter().map(|(i, _)| i).collect()
+}
+
+fn word_score(word: &str, title: &str, tags: &[String], summary: &str, text: &str) -> Option<u32> {
+    let tag = tags
+        .iter()
+        .filter_map(|tag| {
+            if tag == word {
+                Some(120)
+            } else if tag.starts_with(word) {
+                Some(90)
+            } else {
+                fuzzy_score(word, tag)
+            }
+        })
+        .max();
+    // Scattered letters match almost any long text, so the summary and
+    // the conversation itself only count whole substrings.
+    let summary = summary.contains(word).then_some(30);
+    let content = text.contains(word).then_some(10);
+
+    [fuzzy_score(word, title), tag, summary, content]
+        .into_iter()
+        .flatten()
+        .max()
+}
+
+/// How well `needle` matches `haystack` (both lowercase): a substring
+/// scores highest, then its characters in order, with bonuses for runs and
+/// word starts. `None` when some character is missing.
+fn fuzzy_score(needle: &str, haystack: &str) -> Option<u32> {
+    if let Some(pos) = haystack.find(needle) {
+        let at_word_start = haystack[..pos]
+            .chars()
+            .next_back()
+            .is_none_or(|c| !c.is_alphanumeric());
+        return Some(if at_word_start { 100 } else { 80 });
+    }
+
+    let mut wanted = needle.chars().peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut in_run = false;
+    for c in haystack.chars() {
+        let Some(&next) = wanted.peek() else {
+            break;
+        };
+        if c == next {
+            wanted.next();
+            score += 1;
+            if in_run {
+                score += 2;
+            }
+            if previous.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            in_run = true;
+        } else {
+            in_run = false;
+        }
+        previous = Some(c);
+    }
+
+    wanted.peek().is_none().then_some(score.min(60))
+}
+
+/// Tags typed by the user: separated by commas or spaces, without a
+/// leading '#', each kept once
+pub fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|tag| tag.trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+    {
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case(tag)) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, tags: &[&str], text: &str) -> SessionEntry {
+        SessionEntry {
+            info: ConversationInfo {
+                id: title.to_string(),
+                title: title.to_string(),
+                created_at: 0,
+                updated_at: 0,
+                message_count: 2,
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                summary: None,
+                renamed: false,
+            },
+            text: text.to_lowercase(),
+        }
+    }
+
+    #[test]
+    fn test_search_sessions() {
+        let entries = vec![
+            entry("Checkout latency", &[], "p99 went up after the deploy"),
+            entry("Payment errors", &["INC-4521"], "card declines from stripe"),
+            entry("Disk usage", &[], "checkout volume is full"),
+        ];
+
+        assert_eq!(search_sessions(&entries, ""), vec![0, 1, 2]);
+        // Title first, then the conversation that mentions it
+        assert_eq!(search_sessions(&entries, "checkout"), vec![0, 2]);
+        assert_eq!(search_sessions(&entries, "chklat"), vec![0]);
+        assert_eq!(search_sessions(&entries, "#inc-4521"), vec![1]);
+        assert_eq!(search_sessions(&entries, "stripe payment"), vec![1]);
+        assert!(search_sessions(&entries, "kafka").is_empty());
+    }
+
+    #[test]
+    fn test_parse_tags() {
+        assert_eq!(
+            parse_tags("#INC-4521, payments  inc-4521,"),
+            vec!["INC-4521", "payments"]
+        );
+        assert!(parse_tags(" , ").is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use super::StoredMessage;
+use crate::agent::extract_json_object;
+use crate::providers::{AiProvider, ChatRequest, ChatResponse, Message, ResponseFormat};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Title and summary of a conversation, written by a model
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SessionSummary {
+    pub title: String,
+    pub summary: String,
+}
+
+/// Each message is cut to this many characters in the prompt
+const MAX_MESSAGE_CHARS: usize = 2_000;
+/// The prompt stops after this many characters of conversation
+const MAX_TRANSCRIPT_CHARS: usize = 12_000;
+const MAX_TITLE_CHARS: usize = 60;
+
+const INSTRUCTIONS: &str = "You name saved log-investigation conversations. \
+Reply with a JSON object with \"title\": at most 8 words naming the service, \
+symptom or incident investigated (no quotes, no trailing period), and \
+\"summary\": one paragraph of two to four sentences on what was asked and \
+what was found.";
+
+fn schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "title": {
+                "type": "string",
+                "description": "At most 8 words naming what was investigated"
+            },
+            "summary": {
+                "type": "string",
+                "description": "What was asked and found, in two to four sentences"
+            }
+        },
+        "required": ["title", "summary"],
+        "additionalProperties": false
+    })
+}
+
+/// The user and assistant messages as plain text; tool output is left out,
+/// the answers already say what mattered in it
+fn transcript(messages: &[StoredMessage]) -> String {
+    let mut transcript = String::new();
+    for message in messages
+        .iter()
+        .filter(|m| m.role == "user" || m.role == "assistant")
+        .filter(|m| !m.content.trim().is_empty())
+    {
+        let content: String = message.content.chars().take(MAX_MESSAGE_CHARS).collect();
+        let line = format!("{}: {}\n\n", message.role, content.trim());
+        if transcript.len() + line.len() > MAX_TRANSCRIPT_CHARS {
+            break;
+        }
+        transcript.push_str(&line);
+    }
+    transcript
+}
+
+/// The request that asks for a title and summary of `messages`
+fn summary_request(messages: &[StoredMessage]) -> ChatRequest {
+    ChatRequest {
+        messages: vec![
+            Message {
+                role: "system".to_string(),
+                content: INSTRUCTIONS.to_string(),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+            Message {
+                role: "user".to_string(),
+                content: transcript(messages),
+                tool_calls: None,
+                tool_call_id: None,
+            },
+        ],
+        temperature: Some(0.2),
+        max_tokens: Some(300),
+        tools: None,
+        response_format: Some(ResponseFormat {
+            name: "session_summary".to_string(),
+            schema: schema(),
+        }),
+    }
+}
+
+/// Read a summary reply, tidying the title into one short line
+fn parse_summary(content: &str) -> Result<SessionSummary> {
+    let json = extract_json_object(content).context("Reply has no JSON object")?;
+    let summary: SessionSummary =
+        serde_json::from_str(json).context("Reply is not a title and summary")?;
+
+    let words: Vec<&str> = summary.title.split_whitespace().collect();
+    let mut title = words
+        .join(" ")
+        .trim_matches(|c: char| c == '"' || c == '\'' || c == '.')
+        .to_string();
+    if title.chars().count() > MAX_TITLE_CHARS {
+        title = format!(
+            "{}...",
+            title.chars().take(MAX_TITLE_CHARS).collect::<String>()
+        );
+    }
+    let text = summary.summary.trim().to_string();
+    if title.is_empty() || text.is_empty() {
+        anyhow::bail!("Reply has an empty title or summary");
+    }
+
+    Ok(SessionSummary {
+        title,
+        summary: text,
+    })
+}
+
+/// Ask `provider` for a title and summary. The response comes back too, for
+/// usage accounting.
+pub async fn summarize(
+    provider: &dyn AiProvider,
+    messages: &[StoredMessage],
+) -> Result<(SessionSummary, ChatResponse)> {
+    let response = provider.chat(summary_request(messages)).await?;
+    let summary = parse_summary(&response.content)?;
+    Ok((summary, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: &str, content: &str) -> StoredMessage {
+        StoredMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_summary_request_leaves_out_tool_output() {
+        let request = summary_request(&[
+            message("user", "why is checkout failing"),
+            message("tool", "[{\"message\": \"secret payload\"}]"),
+            message("assistant", "Payment timeouts since 14:02."),
+        ]);
+
+        let prompt = &request.messages[1].content;
+        assert!(prompt.contains("user: why is checkout failing"));
+        assert!(prompt.contains("assistant: Payment timeouts since 14:02."));
+        assert!(!prompt.contains("secret payload"));
+        assert!(request.tools.is_none());
+    }
+
+    #[test]
+    fn test_parse_summary() {
+        let summary = parse_summary(
+            "```json\n{\"title\": \" \\\"Checkout   payment timeouts.\\\" \", \"summary\": \"Payments timed out. \"}\n```",
+        )
+        .unwrap();
+        assert_eq!(summary.title, "Checkout payment timeouts");
+        assert_eq!(summary.summary, "Payments timed out.");
+
+        assert!(parse_summary("Checkout payment timeouts").is_err());
+        assert!(parse_summary("{\"title\": \"\", \"summary\": \"x\"}").is_err());
+    }
+}
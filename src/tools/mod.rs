@@ -1,9 +1,24 @@
 use anyhow::{Context, Result};
 use chrono::{Duration, Utc};
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use tokio::sync::mpsc::UnboundedSender;
 
-use crate::backends::{LogBackendClient, LogQuery};
+use crate::backends::{LogBackendClient, LogEntry, LogQuery};
+use crate::mcp::registry::McpRegistry;
+use crate::providers::{create_log_tools, Tool, ToolCall};
+
+/// Tool calls run at once when config.json doesn't set `tool_concurrency`
+pub const DEFAULT_TOOL_CONCURRENCY: usize = 4;
+
+/// Progress of one call in a batch, identified by its position in the batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolProgress {
+    Started(usize),
+    Finished(usize),
+    Failed(usize),
+}
 
 /// Tool execution request parsed from AI function call
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +54,8 @@ pub struct LogQueryResult {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LogEntrySummary {
+    /// Lets the model cite this entry as evidence
+    pub id: String,
     pub timestamp: String,
     pub level: String,
     pub message: String,
@@ -52,25 +69,77 @@ pub struct TimeRange {
     pub end: String,
 }
 
+#[derive(Clone)]
 pub struct ToolExecutor {
-    backend: Arc<dyn LogBackendClient>,
+    backend: Option<Arc<dyn LogBackendClient>>,
+    mcp: Option<Arc<McpRegistry>>,
     index_pattern_override: Option<String>,
 }
 
 impl ToolExecutor {
     pub fn new(backend: Arc<dyn LogBackendClient>) -> Self {
         Self {
-            backend,
+            backend: Some(backend),
+            mcp: None,
             index_pattern_override: None,
         }
     }
 
+    /// Executor offering only MCP server tools, for when no log backend is configured
+    pub fn mcp_only(mcp: Arc<McpRegistry>) -> Self {
+        Self {
+            backend: None,
+            mcp: Some(mcp),
+            index_pattern_override: None,
+        }
+    }
+
+    /// Also offer the tools of running MCP servers
+    pub fn with_mcp(mut self, mcp: Arc<McpRegistry>) -> Self {
+        self.mcp = (!mcp.is_empty()).then_some(mcp);
+        self
+    }
+
+    pub fn mcp(&self) -> Option<Arc<McpRegistry>> {
+        self.mcp.clone()
+    }
+
+    pub fn has_backend(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    pub fn backend_client(&self) -> Option<Arc<dyn LogBackendClient>> {
+        self.backend.clone()
+    }
+
+    /// Tool definitions for the model: the log tools when a backend is
+    /// configured, then any MCP server tools
+    pub fn definitions(&self) -> Vec<Tool> {
+        let mut tools = if self.backend.is_some() {
+            create_log_tools()
+        } else {
+            Vec::new()
+        };
+        if let Some(mcp) = &self.mcp {
+            tools.extend(mcp.definitions());
+        }
+        tools
+    }
+
+    fn backend(&self) -> Result<&Arc<dyn LogBackendClient>> {
+        self.backend.as_ref().context("No log backend configured")
+    }
+
     pub fn set_index_pattern(&mut self, pattern: Option<String>) {
         self.index_pattern_override = pattern;
     }
 
     /// Execute a tool call and return the result as a JSON string
     pub async fn execute(&self, tool_name: &str, arguments: &str) -> Result<String> {
+        if let Some(mcp) = self.mcp.as_ref().filter(|mcp| mcp.handles(tool_name)) {
+            return mcp.call(tool_name, arguments).await;
+        }
+
         match tool_name {
             "query_logs" => {
                 let args: QueryLogsArgs = serde_json::from_str(arguments)
@@ -92,6 +161,47 @@ impl ToolExecutor {
         }
     }
 
+    /// Run the tool calls of one model turn concurrently, at most `limit` at a
+    /// time. Results come back in call order whatever order they finish in;
+    /// failures become error text for the model to read.
+    pub async fn execute_all(
+        &self,
+        calls: &[ToolCall],
+        limit: usize,
+        progress: Option<UnboundedSender<ToolProgress>>,
+    ) -> Vec<String> {
+        let report = |event: ToolProgress| {
+            if let Some(progress) = &progress {
+                let _ = progress.send(event);
+            }
+        };
+
+        // Unordered, so a slow call doesn't hold back the ones queued after it
+        let mut results: Vec<(usize, String)> = futures::stream::iter(calls.iter().enumerate())
+            .map(|(index, call)| async move {
+                report(ToolProgress::Started(index));
+                let result = match self
+                    .execute(&call.function.name, &call.function.arguments)
+                    .await
+                {
+                    Ok(result) => {
+                        report(ToolProgress::Finished(index));
+                        result
+                    }
+                    Err(e) => {
+                        report(ToolProgress::Failed(index));
+                        format!("Error executing tool: {:#}", e)
+                    }
+                };
+                (index, result)
+            })
+            .buffer_unordered(limit.max(1))
+            .collect()
+            .await;
+        results.sort_by_key(|(index, _)| *index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+
     /// Parse relative time strings like "1h", "30m", "2d" into ISO timestamps
     fn parse_time(&self, time_str: &str) -> Option<String> {
         let time_str = time_str.trim();
@@ -133,7 +243,7 @@ impl ToolExecutor {
             index_pattern: self.index_pattern_override.clone(),
         };
 
-        let logs = self.backend.query_logs(&query).await?;
+        let logs = self.backend()?.query_logs(&query).await?;
 
         let mut level_distribution = std::collections::HashMap::new();
         let mut services_set = std::collections::HashSet::new();
@@ -160,6 +270,7 @@ impl ToolExecutor {
         let log_summaries: Vec<LogEntrySummary> = logs
             .iter()
             .map(|log| LogEntrySummary {
+                id: log.id.clone().unwrap_or_else(|| derived_log_id(log)),
                 timestamp: log.timestamp.clone(),
                 level: log.level.clone(),
                 message: truncate_message(&log.message, 500),
@@ -188,7 +299,7 @@ impl ToolExecutor {
             index_pattern: self.index_pattern_override.clone(),
         };
 
-        let logs = self.backend.query_logs(&query).await?;
+        let logs = self.backend()?.query_logs(&query).await?;
 
         let services: std::collections::HashSet<String> =
             logs.iter().filter_map(|log| log.service.clone()).collect();
@@ -213,7 +324,7 @@ impl ToolExecutor {
             index_pattern: self.index_pattern_override.clone(),
         };
 
-        let logs = self.backend.query_logs(&query).await?;
+        let logs = self.backend()?.query_logs(&query).await?;
 
         let mut level_counts: std::collections::HashMap<String, usize> =
             std::collections::HashMap::new();
@@ -237,6 +348,17 @@ impl ToolExecutor {
     }
 }
 
+/// Stable ID for backends that don't expose document IDs
+fn derived_log_id(log: &LogEntry) -> String {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    log.timestamp.hash(&mut hasher);
+    log.service.hash(&mut hasher);
+    log.message.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
 fn truncate_message(msg: &str, max_len: usize) -> String {
     if msg.len() <= max_len {
         msg.to_string()
@@ -248,6 +370,86 @@ fn truncate_message(msg: &str, max_len: usize) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::providers::FunctionCall;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Sleeps for the number of milliseconds in the query, tracking how many
+    /// queries are in flight at once
+    #[derive(Default)]
+    struct SlowBackend {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl LogBackendClient for SlowBackend {
+        async fn query_logs(&self, query: &LogQuery) -> Result<Vec<LogEntry>> {
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+
+            let millis: u64 = query.query.parse()?;
+            tokio::time::sleep(std::time::Duration::from_millis(millis)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            Ok(vec![LogEntry {
+                id: Some(query.query.clone()),
+                timestamp: String::new(),
+                level: "INFO".to_string(),
+                message: query.query.clone(),
+                service: None,
+                trace_id: None,
+                labels: Default::default(),
+            }])
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+
+        fn backend_name(&self) -> &str {
+            "slow"
+        }
+    }
+
+    fn call(query: &str) -> ToolCall {
+        ToolCall {
+            id: format!("call_{}", query),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: "query_logs".to_string(),
+                arguments: serde_json::json!({ "query": query }).to_string(),
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_all_is_concurrent_and_ordered() {
+        let backend = Arc::new(SlowBackend::default());
+        let executor = ToolExecutor::new(backend.clone());
+        let calls = vec![call("60"), call("10"), call("30"), call("nope")];
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let results = executor.execute_all(&calls, 2, Some(tx)).await;
+
+        assert_eq!(backend.peak.load(Ordering::SeqCst), 2);
+        for (result, expected) in results.iter().zip(["60", "10", "30"]) {
+            let parsed: LogQueryResult = serde_json::from_str(result).unwrap();
+            assert_eq!(parsed.logs[0].id, expected);
+        }
+        assert!(results[3].starts_with("Error executing tool"));
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(events.len(), 8);
+        assert!(events.contains(&ToolProgress::Failed(3)));
+        // The fast second call finishes before the first
+        let position = |e: ToolProgress| events.iter().position(|x| *x == e).unwrap();
+        assert!(position(ToolProgress::Finished(1)) < position(ToolProgress::Finished(0)));
+        // and frees its slot for the third while the first is still running
+        assert!(position(ToolProgress::Started(2)) < position(ToolProgress::Finished(0)));
+    }
 
     #[test]
     fn test_parse_query_logs_args() {
@@ -1,8 +1,9 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use crossterm::{
     event::{
         self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
-        Event, KeyCode, KeyModifiers,
+        Event, KeyCode, KeyEvent, KeyModifiers,
     },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
@@ -15,17 +16,31 @@ use ratatui::{
     widgets::{Block, Borders, Clear, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState},
     Frame, Terminal,
 };
+use std::collections::{HashSet, VecDeque};
 use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-
-use crate::backends::{
-    elasticsearch::ElasticsearchClient, kibana::KibanaClient, openobserve::OpenObserveClient,
-    LogBackendClient,
+use tokio::sync::{broadcast, mpsc};
+
+use crate::agent::Agent;
+use crate::backends::registry::{build_named_backend, find_backend};
+use crate::backends::{LogEntry, LogQuery};
+use crate::config::{Config, ProviderConfig, ProviderType};
+use crate::logs::export::{self, ExportFormat, ExportWriter};
+use crate::logs::tail::{self, TailEvent, TailOptions};
+use crate::mcp::registry::{
+    prompt_usage, McpRegistry, McpResource, ResourceUpdate, ServerState, ServerStatus,
+};
+use crate::mcp::supervisor::Supervision;
+use crate::providers::{AiProvider, ChatRequest, ChatResponse, Message, ToolCall};
+use crate::report::{InvestigationReport, ReportMessage};
+use crate::session::{
+    create_session_store, parse_tags, search_sessions, summarize, SessionEntry, SessionStore,
+    SessionSummary, StoredMessage,
 };
-use crate::config::{Config, LogBackend};
-use crate::providers::{create_log_tools, AiProvider, ChatRequest, Message, ToolCall};
-use crate::session::{try_create_session_store, ConversationInfo, SessionStore, StoredMessage};
-use crate::tools::ToolExecutor;
+use crate::tools::{ToolExecutor, ToolProgress, DEFAULT_TOOL_CONCURRENCY};
+use crate::usage::UsageLedger;
 
 #[derive(Clone)]
 struct SlashCommand {
@@ -35,6 +50,11 @@ struct SlashCommand {
 }
 
 const SLASH_COMMANDS: &[SlashCommand] = &[
+    SlashCommand {
+        name: "attach",
+        description: "Attach an MCP resource to the conversation",
+        shortcut: Some("a"),
+    },
     SlashCommand {
         name: "backend",
         description: "Switch log backend (kibana/openobserve)",
@@ -50,6 +70,16 @@ const SLASH_COMMANDS: &[SlashCommand] = &[
         description: "Copy last AI response to clipboard",
         shortcut: Some("y"),
     },
+    SlashCommand {
+        name: "detach",
+        description: "Remove attached MCP resources",
+        shortcut: None,
+    },
+    SlashCommand {
+        name: "export",
+        description: "Export logs to a file: /export <file.jsonl|csv|parquet|md> [query]",
+        shortcut: Some("e"),
+    },
     SlashCommand {
         name: "help",
         description: "Show available commands",
@@ -60,20 +90,70 @@ const SLASH_COMMANDS: &[SlashCommand] = &[
         description: "Change index pattern for this session",
         shortcut: Some("i"),
     },
+    SlashCommand {
+        name: "mcp",
+        description: "Show MCP servers, or restart one",
+        shortcut: None,
+    },
+    SlashCommand {
+        name: "model",
+        description: "Switch model of the current provider",
+        shortcut: Some("m"),
+    },
+    SlashCommand {
+        name: "provider",
+        description: "Switch AI provider, keeping the conversation",
+        shortcut: Some("p"),
+    },
     SlashCommand {
         name: "quit",
         description: "Exit the application",
         shortcut: Some("q"),
     },
+    SlashCommand {
+        name: "report",
+        description: "Write the investigation to a Markdown or JSON file: /report [file]",
+        shortcut: None,
+    },
     SlashCommand {
         name: "resume",
         description: "Resume a previous conversation",
         shortcut: Some("r"),
     },
+    SlashCommand {
+        name: "tail",
+        description: "Follow new logs in a live pane (pause, resume, filter, stop)",
+        shortcut: Some("t"),
+    },
+    SlashCommand {
+        name: "usage",
+        description: "Show token usage and cost for this session",
+        shortcut: Some("u"),
+    },
 ];
 
+/// An entry of the slash command menu: a built-in command or an MCP prompt
+struct CommandChoice {
+    name: String,
+    description: String,
+    shortcut: Option<&'static str>,
+}
+
+const DEFAULT_TEMPERATURE: f32 = 0.7;
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+/// Attached resources beyond this many characters are cut off
+const MAX_ATTACHMENT_CHARS: usize = 50_000;
+
+/// Entries kept by the /tail pane, and held back while it is paused
+const TAIL_PANE_CAPACITY: usize = 1000;
+const TAIL_PANE_HEIGHT: u16 = 12;
+
 /// Commands that can be auto-executed without arguments
-const AUTO_EXECUTE_COMMANDS: &[&str] = &["quit", "clear", "help", "resume", "copy"];
+const AUTO_EXECUTE_COMMANDS: &[&str] = &[
+    "quit", "clear", "help", "resume", "copy", "model", "provider", "usage", "attach", "detach",
+    "mcp", "tail", "report",
+];
 
 /// Check if a command should be auto-executed (doesn't require arguments)
 fn is_auto_execute_command(cmd: &str) -> bool {
@@ -416,10 +496,121 @@ struct ChatMessage {
     content: String,
     tool_calls: Option<Vec<ToolCall>>,
     tool_call_id: Option<String>,
+    /// When the message was added; unknown for sessions saved without it
+    at: Option<DateTime<Utc>>,
+    /// Log backend and index a tool result came from
+    backend: Option<String>,
+}
+
+impl ChatMessage {
+    /// A message added now, outside any tool exchange
+    fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+            tool_calls: None,
+            tool_call_id: None,
+            at: Some(Utc::now()),
+            backend: None,
+        }
+    }
+
+    /// A note from zeteo itself (command output, confirmations). Shown in
+    /// the conversation but never sent to the model, saved or reported.
+    fn notice(content: impl Into<String>) -> Self {
+        Self::new("notice", content)
+    }
+}
+
+/// What the picker modal is choosing
+#[derive(Clone, Copy, PartialEq)]
+enum PickerKind {
+    Provider,
+    Model,
+    Resource,
+}
+
+struct PickerItem {
+    value: String,
+    detail: String,
+}
+
+/// What typing in the resume modal edits
+#[derive(Clone, Copy, PartialEq)]
+enum ResumeInput {
+    Search,
+    Rename,
+    Tags,
+}
+
+/// Sessions rows shown by the resume modal at once (and moved by PageUp/PageDown)
+const RESUME_ROWS: usize = 10;
+
+/// State of the /resume modal
+struct ResumeModal {
+    entries: Vec<SessionEntry>,
+    query: String,
+    /// Indices into `entries` matching `query`, best first
+    matches: Vec<usize>,
+    /// Position in `matches`
+    selected: usize,
+    input: ResumeInput,
+    /// New title or tags being typed
+    edit: String,
+    /// Ctrl+D was pressed once on the selected session
+    confirm_delete: bool,
+}
+
+impl ResumeModal {
+    fn new(entries: Vec<SessionEntry>, query: String) -> Self {
+        let mut modal = Self {
+            entries,
+            query,
+            matches: Vec::new(),
+            selected: 0,
+            input: ResumeInput::Search,
+            edit: String::new(),
+            confirm_delete: false,
+        };
+        modal.refilter();
+        modal
+    }
+
+    fn refilter(&mut self) {
+        self.matches = search_sessions(&self.entries, &self.query);
+        self.selected = 0;
+    }
+
+    fn selected_entry(&self) -> Option<&SessionEntry> {
+        self.matches
+            .get(self.selected)
+            .map(|&index| &self.entries[index])
+    }
+
+    /// Re-run the search after `entries` changed, staying on session `id`
+    /// when it still matches
+    fn refilter_keeping(&mut self, id: &str) {
+        let previous = self.selected;
+        self.refilter();
+        self.selected = self
+            .matches
+            .iter()
+            .position(|&index| self.entries[index].info.id == id)
+            .unwrap_or(previous.min(self.matches.len().saturating_sub(1)));
+    }
+}
+
+/// An MCP resource attached to the conversation, sent with the system prompt
+struct Attachment {
+    server: String,
+    uri: String,
+    name: String,
+    text: String,
 }
 
 pub struct TuiApp {
     provider: Arc<dyn AiProvider>,
+    provider_profile: String,
     tool_executor: Option<ToolExecutor>,
     config: Option<Config>,
     input: String,
@@ -427,6 +618,8 @@ pub struct TuiApp {
     messages: Vec<ChatMessage>,
     scroll_offset: usize,
     is_loading: bool,
+    /// Answer text streamed so far by the request in flight
+    streamed: String,
     show_welcome: bool,
     status_message: Option<String>,
     backend_name: Option<String>,
@@ -437,11 +630,116 @@ pub struct TuiApp {
     slash_selected: usize,
     available_backends: Vec<String>,
     session_store: Option<SessionStore>,
-    show_resume_modal: bool,
-    resume_sessions: Vec<ConversationInfo>,
-    resume_selected: usize,
+    resume: Option<ResumeModal>,
     session_index_pattern: Option<String>,
     selected_message: Option<usize>, // Index of selected message for copying
+    temperature: f32,
+    max_tokens: u32,
+    show_picker_modal: bool,
+    picker_kind: PickerKind,
+    picker_items: Vec<PickerItem>,
+    picker_selected: usize,
+    usage: UsageLedger,
+    /// MCP servers or session storage that failed to start, shown on the welcome screen
+    startup_errors: Vec<String>,
+    /// Resources offered by the open resource picker, in picker order
+    resource_choices: Vec<McpResource>,
+    attachments: Vec<Attachment>,
+    resource_updates: Option<broadcast::Receiver<ResourceUpdate>>,
+    /// Text of an expanded MCP prompt, sent once the slash command returns
+    pending_prompt: Option<String>,
+    tail: Option<TailPane>,
+    export: Option<ExportTask>,
+    summary_task: Option<SummaryTask>,
+    /// Sessions a title and summary were asked for in this run
+    summarized_sessions: HashSet<String>,
+}
+
+/// A background request for a conversation's title and summary
+struct SummaryTask {
+    session_id: String,
+    handle: tokio::task::JoinHandle<Result<(SessionSummary, ChatResponse)>>,
+}
+
+/// A running /export, cancelled with Esc
+struct ExportTask {
+    path: PathBuf,
+    /// Logs written so far
+    written: Arc<AtomicUsize>,
+    handle: tokio::task::JoinHandle<Result<usize>>,
+}
+
+impl Drop for ExportTask {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Counts the logs passed to the writer it wraps, for the progress line
+struct CountingWriter {
+    inner: Box<dyn ExportWriter>,
+    written: Arc<AtomicUsize>,
+}
+
+impl ExportWriter for CountingWriter {
+    fn write(&mut self, entries: &[LogEntry]) -> Result<()> {
+        self.inner.write(entries)?;
+        self.written.fetch_add(entries.len(), Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        self.inner.finish()
+    }
+}
+
+/// The live log pane opened by /tail
+struct TailPane {
+    query: String,
+    events: mpsc::Receiver<TailEvent>,
+    task: tokio::task::JoinHandle<()>,
+    entries: VecDeque<LogEntry>,
+    /// Entries that arrived while paused
+    held: VecDeque<LogEntry>,
+    paused: bool,
+    /// Only entries whose message or service contains this, lowercased
+    filter: Option<String>,
+    warning: Option<String>,
+}
+
+impl TailPane {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        self.filter.as_ref().is_none_or(|filter| {
+            entry.message.to_lowercase().contains(filter)
+                || entry
+                    .service
+                    .as_ref()
+                    .is_some_and(|s| s.to_lowercase().contains(filter))
+        })
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if !paused {
+            let held: Vec<LogEntry> = self.held.drain(..).collect();
+            for entry in held {
+                push_capped(&mut self.entries, entry);
+            }
+        }
+    }
+}
+
+impl Drop for TailPane {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn push_capped(entries: &mut VecDeque<LogEntry>, entry: LogEntry) {
+    if entries.len() == TAIL_PANE_CAPACITY {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
 }
 
 impl TuiApp {
@@ -459,6 +757,7 @@ impl TuiApp {
 
         Self {
             provider,
+            provider_profile: String::new(),
             tool_executor,
             config,
             input: String::new(),
@@ -466,6 +765,7 @@ impl TuiApp {
             messages: Vec::new(),
             scroll_offset: 0,
             is_loading: false,
+            streamed: String::new(),
             show_welcome: true,
             status_message: None,
             backend_name,
@@ -476,14 +776,45 @@ impl TuiApp {
             slash_selected: 0,
             available_backends,
             session_store,
-            show_resume_modal: false,
-            resume_sessions: Vec::new(),
-            resume_selected: 0,
+            resume: None,
             session_index_pattern: None,
             selected_message: None,
+            temperature: DEFAULT_TEMPERATURE,
+            max_tokens: DEFAULT_MAX_TOKENS,
+            show_picker_modal: false,
+            picker_kind: PickerKind::Provider,
+            picker_items: Vec::new(),
+            picker_selected: 0,
+            startup_errors: Vec::new(),
+            resource_choices: Vec::new(),
+            attachments: Vec::new(),
+            resource_updates: None,
+            pending_prompt: None,
+            tail: None,
+            export: None,
+            summary_task: None,
+            summarized_sessions: HashSet::new(),
+            usage: UsageLedger::default(),
         }
     }
 
+    /// Use a provider built from a profile, including its generation settings
+    fn with_provider_choice(mut self, choice: ProviderChoice) -> Self {
+        self.set_provider(choice);
+        self
+    }
+
+    fn set_provider(&mut self, choice: ProviderChoice) {
+        self.provider = choice.provider;
+        self.provider_profile = choice.name;
+        self.temperature = choice.temperature.unwrap_or(DEFAULT_TEMPERATURE);
+        self.max_tokens = choice.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+    }
+
+    fn any_modal_open(&self) -> bool {
+        self.show_slash_modal || self.resume.is_some() || self.show_picker_modal
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         enable_raw_mode()?;
         let mut stdout = io::stdout();
@@ -510,6 +841,7 @@ impl TuiApp {
         result
     }
 
+    #[allow(clippy::collapsible_match)]
     async fn run_app<B: ratatui::backend::Backend>(
         &mut self,
         terminal: &mut Terminal<B>,
@@ -520,6 +852,10 @@ impl TuiApp {
                 self.cursor_visible = !self.cursor_visible;
             }
 
+            self.refresh_attachments().await;
+            self.refresh_tail();
+            self.refresh_export().await;
+            self.refresh_summary().await;
             terminal.draw(|f| self.ui(f))?;
 
             if event::poll(std::time::Duration::from_millis(50))? {
@@ -542,6 +878,16 @@ impl TuiApp {
                             continue;
                         }
 
+                        // Ctrl+P to pause or resume the tail pane
+                        if key.modifiers.contains(KeyModifiers::CONTROL)
+                            && key.code == KeyCode::Char('p')
+                        {
+                            if let Some(tail) = self.tail.as_mut() {
+                                tail.set_paused(!tail.paused);
+                            }
+                            continue;
+                        }
+
                         // Ctrl+Up to select previous message
                         if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Up
                         {
@@ -557,12 +903,17 @@ impl TuiApp {
                             continue;
                         }
 
-                        // Escape clears message selection
-                        if key.code == KeyCode::Esc {
+                        // Escape clears message selection, cancels an export, or
+                        // quits when nothing is open
+                        if key.code == KeyCode::Esc && !self.any_modal_open() {
                             if self.selected_message.is_some() {
                                 self.selected_message = None;
                                 continue;
                             }
+                            if self.export.is_some() {
+                                self.cancel_export();
+                                continue;
+                            }
                             return Ok(());
                         }
 
@@ -587,7 +938,7 @@ impl TuiApp {
                                         self.input = format!("/{}", cmd_name);
                                         self.cursor_position = self.input.len();
 
-                                        if is_auto_execute_command(&cmd_name) {
+                                        if self.is_auto_execute(&cmd_name) {
                                             if let Some(result) = self
                                                 .execute_slash_command(&self.input.clone())
                                                 .await
@@ -598,11 +949,9 @@ impl TuiApp {
                                             }
                                             self.input.clear();
                                             self.cursor_position = 0;
-                                        } else if cmd_name == "backend" {
-                                            self.input = "/backend ".to_string();
-                                            self.cursor_position = self.input.len();
-                                        } else if cmd_name == "index" {
-                                            self.input = "/index ".to_string();
+                                            self.send_pending_prompt(terminal).await?;
+                                        } else {
+                                            self.input = format!("/{} ", cmd_name);
                                             self.cursor_position = self.input.len();
                                         }
                                     }
@@ -643,44 +992,53 @@ impl TuiApp {
                             continue;
                         }
 
-                        if self.show_resume_modal {
+                        if self.resume.is_some() {
+                            self.handle_resume_key(key).await;
+                            continue;
+                        }
+
+                        if self.show_picker_modal {
                             match key.code {
                                 KeyCode::Esc => {
-                                    self.show_resume_modal = false;
-                                    self.resume_selected = 0;
+                                    self.show_picker_modal = false;
+                                    self.picker_selected = 0;
                                 }
                                 KeyCode::Enter => {
-                                    if let Some(session) =
-                                        self.resume_sessions.get(self.resume_selected)
+                                    if let Some(item) = self.picker_items.get(self.picker_selected)
                                     {
-                                        let session_id = session.id.clone();
-                                        self.show_resume_modal = false;
-                                        self.resume_selected = 0;
-
-                                        if let Err(e) = self.resume_session(&session_id).await {
-                                            self.messages.push(ChatMessage {
-                                                role: "error".to_string(),
-                                                content: format!("Failed to resume session: {}", e),
-                                                tool_calls: None,
-                                                tool_call_id: None,
-                                            });
-                                        } else {
-                                            self.show_welcome = false;
-                                            self.status_message =
-                                                Some("Session resumed".to_string());
+                                        let value = item.value.clone();
+                                        let selected = self.picker_selected;
+                                        self.show_picker_modal = false;
+                                        self.picker_selected = 0;
+                                        self.show_welcome = false;
+
+                                        match self.picker_kind {
+                                            PickerKind::Provider => {
+                                                self.switch_provider(&value).await
+                                            }
+                                            PickerKind::Model => self.switch_model(&value).await,
+                                            PickerKind::Resource => {
+                                                let choices =
+                                                    std::mem::take(&mut self.resource_choices);
+                                                if let Some(resource) =
+                                                    choices.into_iter().nth(selected)
+                                                {
+                                                    self.attach_resource(resource).await;
+                                                }
+                                            }
                                         }
                                     }
                                 }
                                 KeyCode::Up => {
-                                    if self.resume_selected > 0 {
-                                        self.resume_selected -= 1;
+                                    if self.picker_selected > 0 {
+                                        self.picker_selected -= 1;
                                     }
                                 }
                                 KeyCode::Down => {
-                                    if self.resume_selected
-                                        < self.resume_sessions.len().saturating_sub(1)
+                                    if self.picker_selected
+                                        < self.picker_items.len().saturating_sub(1)
                                     {
-                                        self.resume_selected += 1;
+                                        self.picker_selected += 1;
                                     }
                                 }
                                 _ => {}
@@ -704,34 +1062,11 @@ impl TuiApp {
                                                 return Ok(());
                                             }
                                         }
+                                        self.send_pending_prompt(terminal).await?;
                                         continue;
                                     }
 
-                                    self.messages.push(ChatMessage {
-                                        role: "user".to_string(),
-                                        content: input.clone(),
-                                        tool_calls: None,
-                                        tool_call_id: None,
-                                    });
-                                    self.is_loading = true;
-                                    self.status_message = Some("Thinking...".to_string());
-                                    self.scroll_to_bottom();
-
-                                    terminal.draw(|f| self.ui(f))?;
-
-                                    if let Err(e) = self.process_message(input).await {
-                                        self.messages.push(ChatMessage {
-                                            role: "error".to_string(),
-                                            content: e.to_string(),
-                                            tool_calls: None,
-                                            tool_call_id: None,
-                                        });
-                                    }
-                                    self.is_loading = false;
-                                    self.status_message = None;
-                                    self.scroll_to_bottom();
-
-                                    self.save_session().await;
+                                    self.send_message(terminal, input).await?;
                                 }
                             }
                             KeyCode::Char('/') if self.input.is_empty() => {
@@ -809,10 +1144,88 @@ impl TuiApp {
         }
     }
 
-    fn get_filtered_commands(&self) -> Vec<&SlashCommand> {
+    /// Send a user message and run the turn it starts
+    async fn send_message<B: ratatui::backend::Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        input: String,
+    ) -> Result<()> {
+        self.messages.push(ChatMessage::new("user", input.clone()));
+        self.is_loading = true;
+        self.status_message = Some("Thinking... (Esc to cancel)".to_string());
+        self.scroll_to_bottom();
+
+        terminal.draw(|f| self.ui(f))?;
+
+        if let Err(e) = self.process_message(terminal, input).await {
+            self.messages
+                .push(ChatMessage::new("error", format!("{:#}", e)));
+        }
+        self.is_loading = false;
+        self.status_message = None;
+        self.scroll_to_bottom();
+
+        self.save_session().await;
+        self.start_summary().await;
+        Ok(())
+    }
+
+    /// Send the MCP prompt a slash command expanded, if any
+    async fn send_pending_prompt<B: ratatui::backend::Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+    ) -> Result<()> {
+        match self.pending_prompt.take() {
+            Some(prompt) => {
+                self.show_welcome = false;
+                self.send_message(terminal, prompt).await
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn mcp(&self) -> Option<Arc<McpRegistry>> {
+        self.tool_executor.as_ref().and_then(|e| e.mcp())
+    }
+
+    /// Built-in commands run without arguments, and so do MCP prompts that
+    /// need none
+    fn is_auto_execute(&self, cmd: &str) -> bool {
+        if is_auto_execute_command(cmd) {
+            return true;
+        }
+        self.mcp()
+            .and_then(|mcp| {
+                mcp.prompt(cmd)
+                    .map(|p| p.info.arguments.iter().all(|a| !a.required))
+            })
+            .unwrap_or(false)
+    }
+
+    fn get_filtered_commands(&self) -> Vec<CommandChoice> {
         let filter = self.slash_filter.to_lowercase();
-        SLASH_COMMANDS
+        let mut commands: Vec<CommandChoice> = SLASH_COMMANDS
             .iter()
+            .map(|cmd| CommandChoice {
+                name: cmd.name.to_string(),
+                description: cmd.description.to_string(),
+                shortcut: cmd.shortcut,
+            })
+            .collect();
+        if let Some(mcp) = self.mcp() {
+            commands.extend(mcp.prompts().iter().map(|prompt| CommandChoice {
+                name: prompt.command.clone(),
+                description: if prompt.info.description.is_empty() {
+                    format!("Prompt from MCP server '{}'", prompt.server)
+                } else {
+                    prompt.info.description.clone()
+                },
+                shortcut: None,
+            }));
+        }
+
+        commands
+            .into_iter()
             .filter(|cmd| {
                 if filter.is_empty() {
                     true
@@ -832,14 +1245,7 @@ impl TuiApp {
         match *cmd {
             "quit" | "q" => Some("quit".to_string()),
             "clear" | "c" => {
-                self.messages.clear();
-                self.scroll_offset = 0;
-                self.show_welcome = true;
-
-                if let Some(ref session_store) = self.session_store {
-                    let _ = session_store.clear_current_session().await;
-                }
-
+                self.clear_conversation().await;
                 self.status_message = Some("Session cleared".to_string());
                 Some("cleared".to_string())
             }
@@ -852,30 +1258,34 @@ impl TuiApp {
                     })
                     .collect::<Vec<_>>()
                     .join("\n");
+                let prompts_text = self
+                    .mcp()
+                    .map(|mcp| {
+                        mcp.prompts()
+                            .iter()
+                            .map(|p| format!("**{}** - {}", prompt_usage(p), p.info.description))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    })
+                    .filter(|text| !text.is_empty())
+                    .map(|text| format!("\n\n## MCP Prompts\n\n{}", text))
+                    .unwrap_or_default();
 
-                self.messages.push(ChatMessage {
-                    role: "assistant".to_string(),
-                    content: format!(
-                        "## Available Commands\n\n{}\n\n*Tip: Type `/` to see command suggestions*",
-                        help_text
-                    ),
-                    tool_calls: None,
-                    tool_call_id: None,
-                });
+                let content = format!(
+                    "## Available Commands\n\n{}{}\n\n*Tip: Type `/` to see command suggestions*",
+                    help_text, prompts_text
+                );
+                self.messages.push(ChatMessage::notice(content));
                 Some("help".to_string())
             }
             "index" | "i" => {
                 if args.is_empty() {
                     let current_index = self.get_current_index_pattern();
-                    self.messages.push(ChatMessage {
-                        role: "assistant".to_string(),
-                        content: format!(
-                            "## Index Pattern\n\nCurrent: **{}**\n\n*Usage: `/index <pattern>` to change for this session*\n\n*Example: `/index logs-prod-*`*",
-                            current_index.as_deref().unwrap_or("not set")
-                        ),
-                        tool_calls: None,
-                        tool_call_id: None,
-                    });
+                    let content = format!(
+                        "## Index Pattern\n\nCurrent: **{}**\n\n*Usage: `/index <pattern>` to change for this session*\n\n*Example: `/index logs-prod-*`*",
+                        current_index.as_deref().unwrap_or("not set")
+                    );
+                    self.messages.push(ChatMessage::notice(content));
                     Some("index_show".to_string())
                 } else {
                     let new_pattern = args.join(" ");
@@ -883,63 +1293,109 @@ impl TuiApp {
                     if let Some(ref mut executor) = self.tool_executor {
                         executor.set_index_pattern(Some(new_pattern.clone()));
                     }
-                    self.messages.push(ChatMessage {
-                        role: "assistant".to_string(),
-                        content: format!(
-                            "✓ Index pattern changed to **{}** for this session",
-                            new_pattern
-                        ),
-                        tool_calls: None,
-                        tool_call_id: None,
-                    });
+                    self.messages.push(ChatMessage::notice(format!(
+                        "✓ Index pattern changed to **{}** for this session",
+                        new_pattern
+                    )));
                     Some("index_set".to_string())
                 }
             }
+            "usage" | "u" => {
+                self.messages
+                    .push(ChatMessage::notice(self.usage.breakdown()));
+                Some("usage".to_string())
+            }
             "resume" | "r" => {
                 if let Some(ref session_store) = self.session_store {
-                    match session_store.list_sessions().await {
+                    match session_store.search_index().await {
                         Ok(sessions) => {
                             if sessions.is_empty() {
-                                self.messages.push(ChatMessage {
-                                    role: "assistant".to_string(),
-                                    content: "No previous conversations found.".to_string(),
-                                    tool_calls: None,
-                                    tool_call_id: None,
-                                });
+                                self.messages
+                                    .push(ChatMessage::notice("No previous conversations found."));
                                 Some("resume_empty".to_string())
                             } else {
-                                self.resume_sessions = sessions;
-                                self.resume_selected = 0;
-                                self.show_resume_modal = true;
+                                self.resume = Some(ResumeModal::new(sessions, args.join(" ")));
                                 Some("resume_modal".to_string())
                             }
                         }
                         Err(e) => {
-                            self.messages.push(ChatMessage {
-                                role: "error".to_string(),
-                                content: format!("Failed to load sessions: {}", e),
-                                tool_calls: None,
-                                tool_call_id: None,
-                            });
+                            self.messages.push(ChatMessage::new(
+                                "error",
+                                format!("Failed to load sessions: {}", e),
+                            ));
                             Some("resume_error".to_string())
                         }
                     }
                 } else {
-                    self.messages.push(ChatMessage {
-                        role: "error".to_string(),
-                        content:
-                            "Redis not configured. Set REDIS_URL to enable session persistence."
-                                .to_string(),
-                        tool_calls: None,
-                        tool_call_id: None,
-                    });
-                    Some("resume_no_redis".to_string())
+                    self.messages.push(ChatMessage::new(
+                        "error",
+                        "Session saving is off. Set \"sessions\" in config.json to enable /resume.",
+                    ));
+                    Some("resume_disabled".to_string())
+                }
+            }
+            "provider" | "p" => {
+                if let Some(name) = args.first() {
+                    self.switch_provider(name).await;
+                    Some("provider_switch".to_string())
+                } else {
+                    self.open_picker(PickerKind::Provider, self.available_providers());
+                    Some("provider_picker".to_string())
+                }
+            }
+            "model" | "m" => {
+                if args.is_empty() {
+                    self.open_picker(PickerKind::Model, self.available_models());
+                    Some("model_picker".to_string())
+                } else {
+                    self.switch_model(&args.join(" ")).await;
+                    Some("model_switch".to_string())
                 }
             }
             "copy" | "y" => {
                 self.copy_response();
                 Some("copied".to_string())
             }
+            "attach" | "a" => {
+                self.open_resource_picker(&args.join(" ")).await;
+                Some("attach".to_string())
+            }
+            "detach" => {
+                self.detach_resources(&args.join(" ")).await;
+                Some("detach".to_string())
+            }
+            "report" => {
+                self.write_report(args.first().copied());
+                Some("report".to_string())
+            }
+            "export" | "e" => {
+                self.export_command(&args);
+                Some("export".to_string())
+            }
+            "tail" | "t" => {
+                self.tail_command(&args);
+                Some("tail".to_string())
+            }
+            "mcp" => {
+                let Some(mcp) = self.mcp() else {
+                    self.push_error("No MCP servers configured".to_string());
+                    return Some("mcp".to_string());
+                };
+                match args.as_slice() {
+                    ["restart", name] => match mcp.restart(name) {
+                        Ok(()) => self.messages.push(ChatMessage::notice(format!(
+                            "✓ Restarting MCP server **{}**",
+                            name
+                        ))),
+                        Err(e) => self.push_error(e.to_string()),
+                    },
+                    [] => self
+                        .messages
+                        .push(ChatMessage::notice(mcp_status_report(&mcp.status()))),
+                    _ => self.push_error("Usage: /mcp or /mcp restart <server>".to_string()),
+                }
+                Some("mcp".to_string())
+            }
             "backend" | "b" => {
                 if args.is_empty() {
                     let backends_list = if self.available_backends.is_empty() {
@@ -959,109 +1415,666 @@ impl TuiApp {
                             .join("\n")
                     };
 
-                    self.messages.push(ChatMessage {
-                        role: "assistant".to_string(),
-                        content: format!(
-                            "## Log Backends\n\nCurrent: **{}**\n\nAvailable:\n{}\n\n*Usage: `/backend <name>` to switch*",
-                            self.backend_name.as_deref().unwrap_or("none"),
-                            backends_list
-                        ),
-                        tool_calls: None,
-                        tool_call_id: None,
-                    });
+                    let content = format!(
+                        "## Log Backends\n\nCurrent: **{}**\n\nAvailable:\n{}\n\n*Usage: `/backend <name>` to switch*",
+                        self.backend_name.as_deref().unwrap_or("none"),
+                        backends_list
+                    );
+                    self.messages.push(ChatMessage::notice(content));
                     Some("backend_list".to_string())
                 } else {
                     let backend_name = args[0].to_lowercase();
-                    if self.switch_backend(&backend_name) {
-                        self.messages.push(ChatMessage {
-                            role: "assistant".to_string(),
-                            content: format!("✓ Switched to **{}** backend", backend_name),
-                            tool_calls: None,
-                            tool_call_id: None,
-                        });
+                    if let Err(e) = self.switch_backend(&backend_name) {
+                        self.messages.push(ChatMessage::new(
+                            "error",
+                            format!("{:#}. Available: {}", e, self.available_backends.join(", ")),
+                        ));
                     } else {
-                        self.messages.push(ChatMessage {
-                            role: "error".to_string(),
-                            content: format!(
-                                "Backend '{}' not found. Available: {}",
-                                backend_name,
-                                self.available_backends.join(", ")
-                            ),
-                            tool_calls: None,
-                            tool_call_id: None,
-                        });
+                        self.messages.push(ChatMessage::notice(format!(
+                            "✓ Switched to **{}** backend",
+                            backend_name
+                        )));
                     }
                     Some("backend_switch".to_string())
                 }
             }
+            _ if self.mcp().is_some_and(|mcp| mcp.prompt(cmd).is_some()) => {
+                let text = input
+                    .trim_start()
+                    .trim_start_matches('/')
+                    .strip_prefix(*cmd)
+                    .unwrap_or_default()
+                    .trim();
+                self.run_prompt(cmd, text).await;
+                Some("prompt".to_string())
+            }
             _ => {
-                self.messages.push(ChatMessage {
-                    role: "error".to_string(),
-                    content: format!(
+                self.messages.push(ChatMessage::new(
+                    "error",
+                    format!(
                         "Unknown command: /{}. Type /help for available commands.",
                         cmd
                     ),
-                    tool_calls: None,
-                    tool_call_id: None,
-                });
+                ));
                 Some("unknown".to_string())
             }
         }
     }
 
-    fn switch_backend(&mut self, name: &str) -> bool {
-        if let Some(config) = &self.config {
-            if let Some((client, backend_name)) = try_backend(name, config) {
-                self.tool_executor = Some(ToolExecutor::new(client));
-                self.backend_name = Some(backend_name);
-                return true;
+    fn switch_backend(&mut self, name: &str) -> Result<()> {
+        let config = self
+            .config
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Backend '{}' not found: no config.json", name))?;
+        let client = build_named_backend(config, name)?;
+        let mut executor = ToolExecutor::new(client);
+        if let Some(mcp) = self.tool_executor.as_ref().and_then(|e| e.mcp()) {
+            executor = executor.with_mcp(mcp);
+        }
+        self.tool_executor = Some(executor);
+        self.backend_name = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Expand an MCP prompt into the conversation. Leading messages are added
+    /// as they are; a final user message is sent like typed input.
+    async fn run_prompt(&mut self, command: &str, text: &str) {
+        let Some(mcp) = self.mcp() else { return };
+        let mut messages = match mcp.expand_prompt(command, text).await {
+            Ok(messages) => messages,
+            Err(e) => {
+                self.push_error(format!("{:#}", e));
+                return;
             }
+        };
+
+        if messages.last().is_some_and(|m| m.role == "user") {
+            self.pending_prompt = messages.pop().map(|m| m.text());
+        }
+        for message in messages {
+            let role = if message.role == "assistant" {
+                "assistant"
+            } else {
+                "user"
+            };
+            self.messages.push(ChatMessage::new(role, message.text()));
         }
-        false
+        self.scroll_to_bottom();
     }
 
-    /// Get assistant message indices for selection
-    fn get_assistant_message_indices(&self) -> Vec<usize> {
-        self.messages
+    /// `/attach` alone opens a picker of every server's resources; with an
+    /// argument, the resource with that URI or name is attached directly
+    async fn open_resource_picker(&mut self, wanted: &str) {
+        let Some(mcp) = self.mcp() else {
+            self.push_error("No MCP servers configured".to_string());
+            return;
+        };
+        let (resources, errors) = mcp.list_resources().await;
+        for error in errors {
+            self.push_error(error);
+        }
+
+        if !wanted.is_empty() {
+            match resources
+                .into_iter()
+                .find(|r| r.info.uri == wanted || r.info.name == wanted)
+            {
+                Some(resource) => self.attach_resource(resource).await,
+                None => self.push_error(format!("No MCP resource '{}'", wanted)),
+            }
+            return;
+        }
+
+        let items = resources
             .iter()
-            .enumerate()
-            .filter(|(_, m)| m.role == "assistant" && !m.content.is_empty())
-            .map(|(i, _)| i)
-            .collect()
+            .map(|r| PickerItem {
+                value: r.info.uri.clone(),
+                detail: format!("{} · {}", r.info.name, r.server),
+            })
+            .collect();
+        self.resource_choices = resources;
+        self.open_picker(PickerKind::Resource, items);
     }
 
-    /// Select previous assistant message
-    fn select_previous_message(&mut self) {
-        let indices = self.get_assistant_message_indices();
-        if indices.is_empty() {
+    async fn attach_resource(&mut self, resource: McpResource) {
+        let Some(mcp) = self.mcp() else { return };
+        let McpResource { server, info } = resource;
+        if self
+            .attachments
+            .iter()
+            .any(|a| a.server == server && a.uri == info.uri)
+        {
+            self.push_error(format!("{} is already attached", info.uri));
             return;
         }
 
-        self.selected_message = match self.selected_message {
-            None => Some(*indices.last().unwrap()),
-            Some(current) => {
-                // Find the previous assistant message index
-                indices
-                    .iter()
-                    .rev()
-                    .find(|&&i| i < current)
-                    .copied()
-                    .or(Some(*indices.last().unwrap()))
+        let text = match mcp.read_resource(&server, &info.uri).await {
+            Ok(text) => text,
+            Err(e) => {
+                self.push_error(format!("{:#}", e));
+                return;
             }
         };
-        self.status_message = Some("Use Ctrl+Y to copy, Esc to cancel".to_string());
+        let followed = match mcp.subscribe(&server, &info.uri).await {
+            Ok(true) => " and will be kept up to date",
+            Ok(false) => "",
+            Err(e) => {
+                self.push_error(format!("{:#}", e));
+                ""
+            }
+        };
+
+        self.messages.push(ChatMessage::notice(format!(
+            "✓ Attached **{}** ({}){}",
+            info.name, info.uri, followed
+        )));
+        self.attachments.push(Attachment {
+            server,
+            uri: info.uri,
+            name: info.name,
+            text: truncate_attachment(text),
+        });
     }
 
-    /// Select next assistant message
-    fn select_next_message(&mut self) {
-        let indices = self.get_assistant_message_indices();
-        if indices.is_empty() {
+    /// Remove the attachment with this URI or name, or all of them
+    async fn detach_resources(&mut self, wanted: &str) {
+        let (detached, kept): (Vec<Attachment>, Vec<Attachment>) =
+            std::mem::take(&mut self.attachments)
+                .into_iter()
+                .partition(|a| wanted.is_empty() || a.uri == wanted || a.name == wanted);
+        self.attachments = kept;
+
+        if detached.is_empty() {
+            self.push_error(if wanted.is_empty() {
+                "No resources are attached".to_string()
+            } else {
+                format!("'{}' is not attached", wanted)
+            });
             return;
         }
 
-        self.selected_message = match self.selected_message {
-            None => Some(*indices.first().unwrap()),
-            Some(current) => {
+        if let Some(mcp) = self.mcp() {
+            for attachment in &detached {
+                let _ = mcp.unsubscribe(&attachment.server, &attachment.uri).await;
+            }
+        }
+        let names: Vec<String> = detached.iter().map(|a| a.name.clone()).collect();
+        self.messages.push(ChatMessage::notice(format!(
+            "✓ Detached {}",
+            names.join(", ")
+        )));
+    }
+
+    /// Re-read attached resources their servers reported as changed
+    async fn refresh_attachments(&mut self) {
+        let Some(updates) = self.resource_updates.as_mut() else {
+            return;
+        };
+        let mut changed: Vec<ResourceUpdate> = Vec::new();
+        loop {
+            match updates.try_recv() {
+                Ok(update) => {
+                    if !changed.contains(&update) {
+                        changed.push(update);
+                    }
+                }
+                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+        let Some(mcp) = self.mcp().filter(|_| !changed.is_empty()) else {
+            return;
+        };
+
+        for update in changed {
+            let Some(index) = self
+                .attachments
+                .iter()
+                .position(|a| a.server == update.server && a.uri == update.uri)
+            else {
+                continue;
+            };
+            match mcp.read_resource(&update.server, &update.uri).await {
+                Ok(text) => self.attachments[index].text = truncate_attachment(text),
+                Err(e) => self.push_error(format!("Failed to refresh {}: {:#}", update.uri, e)),
+            }
+        }
+    }
+
+    fn open_picker(&mut self, kind: PickerKind, items: Vec<PickerItem>) {
+        if items.is_empty() {
+            self.push_error(match kind {
+                PickerKind::Resource => "No MCP resources available".to_string(),
+                _ => "No AI providers configured".to_string(),
+            });
+            return;
+        }
+
+        let current = match kind {
+            PickerKind::Provider => self.provider_profile.as_str(),
+            PickerKind::Model => self.provider.model_name(),
+            PickerKind::Resource => "",
+        };
+        self.picker_selected = items.iter().position(|i| i.value == current).unwrap_or(0);
+        self.picker_kind = kind;
+        self.picker_items = items;
+        self.show_picker_modal = true;
+    }
+
+    /// Profiles from config.json plus the provider types the environment
+    /// has keys for. Nothing is built here, so credential commands only run
+    /// for the profile that gets picked.
+    fn available_providers(&self) -> Vec<PickerItem> {
+        let mut items: Vec<PickerItem> = Vec::new();
+        if let Some(config) = self.config.as_ref() {
+            let mut names: Vec<&String> = config.providers.keys().collect();
+            names.sort();
+            items.extend(names.into_iter().map(|name| PickerItem {
+                value: name.clone(),
+                detail: profile_detail(&config.providers[name]),
+            }));
+        }
+
+        for builtin in BUILTIN_PROVIDERS {
+            let Some(provider_type) = ProviderType::from_name(builtin) else {
+                continue;
+            };
+            if items.iter().any(|item| item.value == *builtin) {
+                continue;
+            }
+            let usable = match provider_type.default_api_key_env() {
+                Some(var) => std::env::var_os(var).is_some(),
+                // Types without a key have no credential lookup to run
+                None => try_provider(builtin, None, None).is_some(),
+            };
+            if usable {
+                items.push(PickerItem {
+                    value: builtin.to_string(),
+                    detail: profile_detail(&ProviderConfig::for_type(provider_type)),
+                });
+            }
+        }
+
+        items
+    }
+
+    /// The current model plus any models listed in the active profile
+    fn available_models(&self) -> Vec<PickerItem> {
+        let current = self.provider.model_name().to_string();
+        let mut models = vec![current.clone()];
+
+        if let Some(profile) =
+            crate::providers::resolve_profile(&self.provider_profile, self.config.as_ref())
+        {
+            models.extend(profile.model.into_iter().chain(profile.models));
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        models.retain(|m| !m.is_empty() && seen.insert(m.clone()));
+
+        models
+            .into_iter()
+            .map(|model| PickerItem {
+                detail: if model == current {
+                    "current".to_string()
+                } else {
+                    String::new()
+                },
+                value: model,
+            })
+            .collect()
+    }
+
+    /// Swap the AI provider mid-conversation; the message history is kept as-is
+    async fn switch_provider(&mut self, name: &str) {
+        match select_provider(name, self.config.as_ref(), None).await {
+            Ok(choice) => {
+                self.set_provider(choice);
+                let capabilities = self.describe_capabilities().await;
+                self.messages.push(ChatMessage::notice(format!(
+                    "✓ Switched to **{}** ({} · {}). Conversation history carried over.{}",
+                    self.provider_profile,
+                    self.provider.provider_name(),
+                    self.provider.model_name(),
+                    capabilities
+                )));
+            }
+            Err(e) => self.push_error(format!("Failed to switch provider: {}", e)),
+        }
+    }
+
+    /// Rebuild the current provider profile with a different model
+    async fn switch_model(&mut self, model: &str) {
+        let profile = self.provider_profile.clone();
+        match select_provider(&profile, self.config.as_ref(), Some(model)).await {
+            Ok(choice) => {
+                self.set_provider(choice);
+                let capabilities = self.describe_capabilities().await;
+                self.messages.push(ChatMessage::notice(format!(
+                    "✓ Now using model **{}** on {}. Conversation history carried over.{}",
+                    self.provider.model_name(),
+                    self.provider_profile,
+                    capabilities
+                )));
+            }
+            Err(e) => self.push_error(format!("Failed to switch model: {}", e)),
+        }
+    }
+
+    /// One line on tool support and context size, empty if the provider can't be probed
+    async fn describe_capabilities(&self) -> String {
+        let probe = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            self.provider.capabilities(),
+        );
+        let Ok(Ok(capabilities)) = probe.await else {
+            return String::new();
+        };
+
+        let tools = if capabilities.tools {
+            "native"
+        } else {
+            "JSON prompt (no native support)"
+        };
+        let context = capabilities
+            .context_length
+            .map(|n| format!(" · Context: {}k tokens", n / 1000))
+            .unwrap_or_default();
+
+        format!("\n\n*Tool calling: {}{}*", tools, context)
+    }
+
+    /// `/report [file]` writes the conversation as an investigation report,
+    /// Markdown unless the file ends in .json
+    fn write_report(&mut self, path: Option<&str>) {
+        let path = path.map(std::path::PathBuf::from).unwrap_or_else(|| {
+            format!(
+                "zeteo-report-{}.md",
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            )
+            .into()
+        });
+        let messages: Vec<ReportMessage> = self
+            .messages
+            .iter()
+            .map(|m| ReportMessage {
+                message: Message {
+                    role: m.role.clone(),
+                    content: m.content.clone(),
+                    tool_calls: m.tool_calls.clone(),
+                    tool_call_id: m.tool_call_id.clone(),
+                },
+                at: m.at,
+                backend: m.backend.clone(),
+            })
+            .collect();
+        let report = InvestigationReport::new(
+            &messages,
+            self.provider.provider_name(),
+            self.provider.model_name(),
+            self.backend_label(),
+        );
+        if report.is_empty() {
+            self.push_error("Nothing to report yet".to_string());
+            return;
+        }
+
+        let is_json = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let written = if is_json {
+            report.to_json().map_err(anyhow::Error::from)
+        } else {
+            Ok(report.to_markdown())
+        }
+        .and_then(|text| {
+            std::fs::write(&path, text)
+                .with_context(|| format!("Failed to write {}", path.display()))
+        });
+        match written {
+            Ok(()) => self.messages.push(ChatMessage::notice(format!(
+                "✓ Wrote the report ({} steps) to `{}`",
+                report.steps.len(),
+                path.display()
+            ))),
+            Err(e) => self.push_error(format!("{:#}", e)),
+        }
+    }
+
+    /// `/export <file> [query]` writes matching logs, in the format the file
+    /// extension names. The export runs in the background until done or
+    /// cancelled with Esc.
+    fn export_command(&mut self, args: &[&str]) {
+        let Some((path, query)) = args.split_first() else {
+            self.push_error("Usage: /export <file.jsonl|csv|parquet|md> [query]".to_string());
+            return;
+        };
+        if let Some(running) = &self.export {
+            self.push_error(format!(
+                "Already exporting to {}. Press Esc to cancel it first.",
+                running.path.display()
+            ));
+            return;
+        }
+        let path = PathBuf::from(path);
+        let Some(format) = ExportFormat::from_path(&path) else {
+            self.push_error(format!(
+                "Can't tell the format of {}. Use a .jsonl, .csv, .parquet or .md file.",
+                path.display()
+            ));
+            return;
+        };
+        let Some(client) = self
+            .tool_executor
+            .as_ref()
+            .and_then(|executor| executor.backend_client())
+        else {
+            self.push_error("No log backend configured. Use /backend to pick one.".to_string());
+            return;
+        };
+        let query = LogQuery {
+            query: if query.is_empty() {
+                "*".to_string()
+            } else {
+                query.join(" ")
+            },
+            max_results: export::DEFAULT_PAGE_SIZE,
+            start_time: None,
+            end_time: None,
+            level: None,
+            service: None,
+            index_pattern: self.get_current_index_pattern(),
+        };
+
+        let written = Arc::new(AtomicUsize::new(0));
+        let counter = written.clone();
+        let file_path = path.clone();
+        let handle = tokio::spawn(async move {
+            let file = std::fs::File::create(&file_path)
+                .with_context(|| format!("Failed to create {}", file_path.display()))?;
+            let mut writer: Box<dyn ExportWriter> = Box::new(CountingWriter {
+                inner: export::export_writer(format, io::BufWriter::new(file))?,
+                written: counter,
+            });
+            let written = export::export_logs(
+                client.as_ref(),
+                &query,
+                None,
+                export::DEFAULT_PAGE_SIZE,
+                writer.as_mut(),
+            )
+            .await?;
+            writer.finish()?;
+            Ok(written)
+        });
+        self.export = Some(ExportTask {
+            path,
+            written,
+            handle,
+        });
+    }
+
+    /// Report the export once it has finished
+    async fn refresh_export(&mut self) {
+        if !self
+            .export
+            .as_ref()
+            .is_some_and(|export| export.handle.is_finished())
+        {
+            return;
+        }
+        let Some(mut export) = self.export.take() else {
+            return;
+        };
+        match (&mut export.handle).await {
+            Ok(Ok(written)) => self.messages.push(ChatMessage::notice(format!(
+                "✓ Exported {} logs to `{}`",
+                written,
+                export.path.display()
+            ))),
+            Ok(Err(e)) => self.push_error(format!("Export failed: {:#}", e)),
+            Err(e) => self.push_error(format!("Export failed: {}", e)),
+        }
+    }
+
+    /// Stop the running export and remove the partial file
+    fn cancel_export(&mut self) {
+        let Some(export) = self.export.take() else {
+            return;
+        };
+        let written = export.written.load(Ordering::Relaxed);
+        let path = export.path.clone();
+        drop(export);
+        let _ = std::fs::remove_file(&path);
+        self.messages.push(ChatMessage::notice(format!(
+            "Export to `{}` cancelled after {} logs",
+            path.display(),
+            written
+        )));
+    }
+
+    /// `/tail [query]` starts or replaces the pane; `pause`, `resume`,
+    /// `filter [text]` and `stop` act on it
+    fn tail_command(&mut self, args: &[&str]) {
+        match (args, self.tail.as_mut()) {
+            (["pause"], Some(tail)) => tail.set_paused(true),
+            (["resume"], Some(tail)) => tail.set_paused(false),
+            (["filter", text @ ..], Some(tail)) => {
+                let text = text.join(" ").to_lowercase();
+                tail.filter = (!text.is_empty()).then_some(text);
+            }
+            (["stop"], Some(_)) => self.tail = None,
+            (["pause" | "resume" | "filter" | "stop", ..], None) => {
+                self.push_error("No tail running. Start one with /tail [query]".to_string())
+            }
+            (query, _) => self.start_tail(&query.join(" ")),
+        }
+    }
+
+    fn start_tail(&mut self, query: &str) {
+        let Some(client) = self
+            .tool_executor
+            .as_ref()
+            .and_then(|executor| executor.backend_client())
+        else {
+            self.push_error("No log backend configured. Use /backend to pick one.".to_string());
+            return;
+        };
+        let query = if query.is_empty() { "*" } else { query };
+        let log_query = LogQuery {
+            query: query.to_string(),
+            max_results: 0,
+            start_time: None,
+            end_time: None,
+            level: None,
+            service: None,
+            index_pattern: self.get_current_index_pattern(),
+        };
+        let options = TailOptions {
+            backlog: TAIL_PANE_HEIGHT as usize,
+            ..Default::default()
+        };
+
+        let (events_tx, events) = mpsc::channel(256);
+        let task = tokio::spawn(tail::tail(client, log_query, options, events_tx));
+        self.tail = Some(TailPane {
+            query: query.to_string(),
+            events,
+            task,
+            entries: VecDeque::new(),
+            held: VecDeque::new(),
+            paused: false,
+            filter: None,
+            warning: None,
+        });
+    }
+
+    /// Take in what the tail has sent since the last frame
+    fn refresh_tail(&mut self) {
+        let Some(tail) = self.tail.as_mut() else {
+            return;
+        };
+        while let Ok(event) = tail.events.try_recv() {
+            match event {
+                TailEvent::Entry(entry) => {
+                    tail.warning = None;
+                    if tail.paused {
+                        push_capped(&mut tail.held, entry);
+                    } else {
+                        push_capped(&mut tail.entries, entry);
+                    }
+                }
+                TailEvent::Warning(warning) => tail.warning = Some(warning),
+            }
+        }
+    }
+
+    fn push_error(&mut self, content: String) {
+        self.messages.push(ChatMessage::new("error", content));
+    }
+
+    /// Get assistant message indices for selection
+    fn get_assistant_message_indices(&self) -> Vec<usize> {
+        self.messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.role == "assistant" && !m.content.is_empty())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Select previous assistant message
+    fn select_previous_message(&mut self) {
+        let indices = self.get_assistant_message_indices();
+        if indices.is_empty() {
+            return;
+        }
+
+        self.selected_message = match self.selected_message {
+            None => Some(*indices.last().unwrap()),
+            Some(current) => {
+                // Find the previous assistant message index
+                indices
+                    .iter()
+                    .rev()
+                    .find(|&&i| i < current)
+                    .copied()
+                    .or(Some(*indices.last().unwrap()))
+            }
+        };
+        self.status_message = Some("Use Ctrl+Y to copy, Esc to cancel".to_string());
+    }
+
+    /// Select next assistant message
+    fn select_next_message(&mut self) {
+        let indices = self.get_assistant_message_indices();
+        if indices.is_empty() {
+            return;
+        }
+
+        self.selected_message = match self.selected_message {
+            None => Some(*indices.first().unwrap()),
+            Some(current) => {
                 // Find the next assistant message index
                 indices
                     .iter()
@@ -1132,6 +2145,15 @@ impl TuiApp {
         }
     }
 
+    /// The current backend with its index pattern, as shown in reports
+    fn backend_label(&self) -> Option<String> {
+        let backend = self.backend_name.clone()?;
+        Some(match self.get_current_index_pattern() {
+            Some(pattern) => format!("{} ({})", backend, pattern),
+            None => backend,
+        })
+    }
+
     /// Get the current index pattern (session override or from config)
     fn get_current_index_pattern(&self) -> Option<String> {
         if let Some(ref pattern) = self.session_index_pattern {
@@ -1141,31 +2163,51 @@ impl TuiApp {
         if let Some(ref config) = self.config {
             if let Some(ref backend_name) = self.backend_name {
                 if let Some(backend) = config.backends.get(backend_name) {
-                    return match backend {
-                        LogBackend::Elasticsearch { index_pattern, .. } => {
-                            Some(index_pattern.clone())
-                        }
-                        LogBackend::Kibana { index_pattern, .. } => Some(index_pattern.clone()),
-                        LogBackend::OpenObserve { stream, .. } => Some(stream.clone()),
-                    };
+                    return backend.index_pattern().map(str::to_string);
                 }
             }
         }
         None
     }
 
-    /// Save the current session to Redis
+    /// The conversation as saved: everything but errors and notices
+    fn stored_messages(&self) -> Vec<StoredMessage> {
+        self.messages
+            .iter()
+            .filter(|m| m.role == "user" || m.role == "assistant" || m.role == "tool")
+            .map(|m| StoredMessage {
+                role: m.role.clone(),
+                content: m.content.clone(),
+                tool_calls: m.tool_calls.clone(),
+                tool_call_id: m.tool_call_id.clone(),
+                at: m.at,
+                backend: m.backend.clone(),
+            })
+            .collect()
+    }
+
+    /// Start over: empty the screen and drop the saved current session
+    async fn clear_conversation(&mut self) {
+        self.messages.clear();
+        self.usage.clear();
+        self.scroll_offset = 0;
+        self.show_welcome = true;
+
+        if let Some(ref session_store) = self.session_store {
+            let _ = session_store.clear_current_session().await;
+            // The id is reused, so the next conversation gets its own title
+            if let Some(task) = self.summary_task.take() {
+                task.handle.abort();
+            }
+            self.summarized_sessions
+                .remove(session_store.current_session_id());
+        }
+    }
+
+    /// Save the current session to the session store
     async fn save_session(&self) {
         if let Some(ref session_store) = self.session_store {
-            let stored_messages: Vec<StoredMessage> = self
-                .messages
-                .iter()
-                .filter(|m| m.role == "user" || m.role == "assistant")
-                .map(|m| StoredMessage {
-                    role: m.role.clone(),
-                    content: m.content.clone(),
-                })
-                .collect();
+            let stored_messages = self.stored_messages();
 
             if !stored_messages.is_empty() {
                 let _ = session_store.save_messages(&stored_messages).await;
@@ -1173,31 +2215,350 @@ impl TuiApp {
         }
     }
 
-    /// Resume a previous session
-    async fn resume_session(&mut self, session_id: &str) -> Result<()> {
-        if let Some(ref mut session_store) = self.session_store {
-            let stored_messages = session_store.load_messages(session_id).await?;
+    /// Resume a previous session
+    async fn resume_session(&mut self, session_id: &str) -> Result<()> {
+        if let Some(ref mut session_store) = self.session_store {
+            let stored_messages = session_store.load_messages(session_id).await?;
+
+            self.messages.clear();
+            for stored in stored_messages {
+                self.messages.push(ChatMessage {
+                    role: stored.role,
+                    content: stored.content,
+                    tool_calls: stored.tool_calls,
+                    tool_call_id: stored.tool_call_id,
+                    at: stored.at,
+                    backend: stored.backend,
+                });
+            }
+
+            session_store.set_current_session_id(session_id.to_string());
+
+            self.scroll_to_bottom();
+            Ok(())
+        } else {
+            anyhow::bail!("No session store available")
+        }
+    }
+
+    /// Once the conversation has an answer, ask a model in the background
+    /// for its title and summary, unless `auto_title` is off or the session
+    /// already has them
+    async fn start_summary(&mut self) {
+        let settings = self.config.as_ref().and_then(|c| c.sessions.as_ref());
+        if settings.and_then(|s| s.auto_title) == Some(false) || self.summary_task.is_some() {
+            return;
+        }
+        let Some(session_store) = self.session_store.as_ref() else {
+            return;
+        };
+        let session_id = session_store.current_session_id().to_string();
+        let answered = self.messages.iter().any(|m| {
+            m.role == "assistant" && m.tool_calls.is_none() && !m.content.trim().is_empty()
+        });
+        if !answered || self.summarized_sessions.contains(&session_id) {
+            return;
+        }
+        match session_store.session_info(&session_id).await {
+            Ok(Some(info)) if info.summary.is_none() => {}
+            _ => return,
+        }
+        // One attempt per session: a failure isn't worth a request every turn
+        self.summarized_sessions.insert(session_id.clone());
+
+        let provider = match settings
+            .filter(|s| s.title_provider.is_some() || s.title_model.is_some())
+        {
+            Some(s) => {
+                let name = s
+                    .title_provider
+                    .as_deref()
+                    .unwrap_or(&self.provider_profile);
+                match select_provider(name, self.config.as_ref(), s.title_model.as_deref()).await {
+                    Ok(choice) => choice.provider,
+                    Err(e) => {
+                        self.status_message = Some(format!("Can't title conversations: {}", e));
+                        return;
+                    }
+                }
+            }
+            None => self.provider.clone(),
+        };
+        let messages = self.stored_messages();
+        self.summary_task = Some(SummaryTask {
+            session_id,
+            handle: tokio::spawn(async move { summarize(provider.as_ref(), &messages).await }),
+        });
+    }
+
+    /// Store the title and summary once the background request is done
+    async fn refresh_summary(&mut self) {
+        if !self
+            .summary_task
+            .as_ref()
+            .is_some_and(|task| task.handle.is_finished())
+        {
+            return;
+        }
+        let Some(task) = self.summary_task.take() else {
+            return;
+        };
+        let (summary, response) = match task.handle.await {
+            Ok(Ok(done)) => done,
+            Ok(Err(e)) => {
+                self.status_message = Some(format!("Couldn't title the conversation: {}", e));
+                return;
+            }
+            Err(_) => return,
+        };
+
+        // Its own turn, so /usage doesn't credit it to the last question
+        let price = self
+            .config
+            .as_ref()
+            .and_then(|c| c.price_for(&response.model));
+        self.usage.start_turn("(conversation title and summary)");
+        self.usage.record(&response, price);
+
+        if let Some(session_store) = self.session_store.as_ref() {
+            if let Err(e) = session_store
+                .apply_summary(&task.session_id, &summary)
+                .await
+            {
+                self.status_message = Some(format!("Couldn't title the conversation: {}", e));
+            }
+        }
+    }
+
+    /// Keys in the resume modal: typing searches, Enter opens, Ctrl+R
+    /// renames, Ctrl+T edits tags and Ctrl+D (twice) deletes
+    async fn handle_resume_key(&mut self, key: KeyEvent) {
+        let Some(modal) = self.resume.as_mut() else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+
+        if modal.input != ResumeInput::Search {
+            match key.code {
+                KeyCode::Esc => {
+                    modal.input = ResumeInput::Search;
+                    modal.edit.clear();
+                }
+                KeyCode::Enter => {
+                    let Some(id) = modal.selected_entry().map(|entry| entry.info.id.clone()) else {
+                        return;
+                    };
+                    let edit = std::mem::take(&mut modal.edit);
+                    let input = std::mem::replace(&mut modal.input, ResumeInput::Search);
+                    let Some(session_store) = self.session_store.as_ref() else {
+                        return;
+                    };
+                    let updated = if input == ResumeInput::Rename {
+                        session_store.rename_session(&id, &edit).await
+                    } else {
+                        session_store.tag_session(&id, parse_tags(&edit)).await
+                    };
+                    match updated {
+                        Ok(info) => {
+                            if let Some(entry) =
+                                modal.entries.iter_mut().find(|entry| entry.info.id == id)
+                            {
+                                entry.info = info;
+                            }
+                            modal.refilter_keeping(&id);
+                        }
+                        Err(e) => {
+                            self.status_message = Some(format!("Failed to update session: {}", e))
+                        }
+                    }
+                }
+                KeyCode::Backspace => {
+                    modal.edit.pop();
+                }
+                KeyCode::Char(c) if !ctrl => modal.edit.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        if !(ctrl && key.code == KeyCode::Char('d')) {
+            modal.confirm_delete = false;
+        }
+
+        match key.code {
+            KeyCode::Esc => self.resume = None,
+            KeyCode::Enter => {
+                let Some(session_id) = modal.selected_entry().map(|entry| entry.info.id.clone())
+                else {
+                    return;
+                };
+                self.resume = None;
+
+                if let Err(e) = self.resume_session(&session_id).await {
+                    self.messages.push(ChatMessage::new(
+                        "error",
+                        format!("Failed to resume session: {}", e),
+                    ));
+                } else {
+                    self.show_welcome = false;
+                    self.status_message = Some("Session resumed".to_string());
+                }
+            }
+            KeyCode::Up => modal.selected = modal.selected.saturating_sub(1),
+            KeyCode::Down => {
+                modal.selected = (modal.selected + 1).min(modal.matches.len().saturating_sub(1))
+            }
+            KeyCode::PageUp => modal.selected = modal.selected.saturating_sub(RESUME_ROWS),
+            KeyCode::PageDown => {
+                modal.selected =
+                    (modal.selected + RESUME_ROWS).min(modal.matches.len().saturating_sub(1))
+            }
+            KeyCode::Char('r') if ctrl => {
+                if let Some(title) = modal.selected_entry().map(|e| e.info.title.clone()) {
+                    modal.edit = title;
+                    modal.input = ResumeInput::Rename;
+                }
+            }
+            KeyCode::Char('t') if ctrl => {
+                if let Some(tags) = modal.selected_entry().map(|e| e.info.tags.join(", ")) {
+                    modal.edit = tags;
+                    modal.input = ResumeInput::Tags;
+                }
+            }
+            KeyCode::Char('d') if ctrl => {
+                let Some(id) = modal.selected_entry().map(|entry| entry.info.id.clone()) else {
+                    return;
+                };
+                if !modal.confirm_delete {
+                    modal.confirm_delete = true;
+                    return;
+                }
+                modal.confirm_delete = false;
+                let Some(session_store) = self.session_store.as_ref() else {
+                    return;
+                };
+                match session_store.delete_session(&id).await {
+                    Ok(()) => {
+                        modal.entries.retain(|entry| entry.info.id != id);
+                        modal.refilter_keeping(&id);
+                        // Otherwise the next turn would save it again
+                        if id == session_store.current_session_id() {
+                            self.clear_conversation().await;
+                        }
+                        self.status_message = Some("Conversation deleted".to_string());
+                    }
+                    Err(e) => {
+                        self.status_message = Some(format!("Failed to delete session: {}", e))
+                    }
+                }
+            }
+            KeyCode::Backspace if modal.query.pop().is_some() => modal.refilter(),
+            KeyCode::Char(c) if !ctrl => {
+                modal.query.push(c);
+                modal.refilter();
+            }
+            _ => {}
+        }
+    }
+
+    /// Send a request to the provider, showing the answer as it streams in.
+    /// Esc or Ctrl+C abandons it, along with any retries still waiting out a
+    /// backoff.
+    async fn ask<B: ratatui::backend::Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        request: ChatRequest,
+    ) -> Result<ChatResponse> {
+        let provider = self.provider.clone();
+        let streamed = Arc::new(std::sync::Mutex::new(String::new()));
+        let on_delta = {
+            let streamed = streamed.clone();
+            move |delta: &str| streamed.lock().unwrap().push_str(delta)
+        };
+        let request = provider.chat_stream(request, &on_delta);
+        let cancel = wait_for_cancel_key();
+        tokio::pin!(request, cancel);
+        let mut redraw = tokio::time::interval(std::time::Duration::from_millis(100));
+
+        let response = loop {
+            tokio::select! {
+                response = &mut request => break response,
+                _ = &mut cancel => break Err(anyhow::anyhow!("Request cancelled")),
+                _ = redraw.tick() => {
+                    let text = streamed.lock().unwrap().clone();
+                    if text != self.streamed {
+                        self.streamed = text;
+                        self.scroll_to_bottom();
+                        terminal.draw(|f| self.ui(f))?;
+                    }
+                }
+            }
+        };
+        self.streamed.clear();
+        let response = response?;
+
+        let price = self
+            .config
+            .as_ref()
+            .and_then(|c| c.price_for(&response.model));
+        self.usage.record(&response, price);
+
+        if let Some(answered_by) = &response.provider {
+            self.messages.push(ChatMessage::notice(format!(
+                "↪ {} unavailable, answered by {}",
+                self.provider_profile, answered_by
+            )));
+        }
+
+        Ok(response)
+    }
+
+    /// Run one turn's tool calls concurrently, redrawing the status line as
+    /// each call starts and finishes. Esc or Ctrl+C abandons the batch.
+    async fn run_tools<B: ratatui::backend::Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        calls: &[ToolCall],
+    ) -> Result<Vec<String>> {
+        let Some(executor) = self.tool_executor.clone() else {
+            return Ok(vec!["No tools configured".to_string(); calls.len()]);
+        };
+        let limit = self
+            .config
+            .as_ref()
+            .and_then(|c| c.tool_concurrency)
+            .unwrap_or(DEFAULT_TOOL_CONCURRENCY);
 
-            self.messages.clear();
-            for stored in stored_messages {
-                self.messages.push(ChatMessage {
-                    role: stored.role,
-                    content: stored.content,
-                    tool_calls: None,
-                    tool_call_id: None,
-                });
-            }
+        let mut states = vec![None; calls.len()];
+        self.status_message = Some(tool_progress_status(calls, &states));
+        terminal.draw(|f| self.ui(f))?;
 
-            session_store.set_current_session_id(session_id.to_string());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let run = executor.execute_all(calls, limit, Some(tx));
+        let cancel = wait_for_cancel_key();
+        tokio::pin!(run, cancel);
 
-            self.scroll_to_bottom();
-            Ok(())
-        } else {
-            anyhow::bail!("No session store available")
+        loop {
+            tokio::select! {
+                results = &mut run => return Ok(results),
+                Some(event) = rx.recv() => {
+                    let (ToolProgress::Started(i) | ToolProgress::Finished(i) | ToolProgress::Failed(i)) = event;
+                    states[i] = Some(event);
+                    self.status_message = Some(tool_progress_status(calls, &states));
+                    terminal.draw(|f| self.ui(f))?;
+                }
+                _ = &mut cancel => anyhow::bail!("Request cancelled"),
+            }
         }
     }
 
-    async fn process_message(&mut self, _input: String) -> Result<()> {
+    async fn process_message<B: ratatui::backend::Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        input: String,
+    ) -> Result<()> {
+        self.usage.start_turn(&input);
+
         let mut api_messages: Vec<Message> = self
             .messages
             .iter()
@@ -1221,55 +2582,56 @@ impl TuiApp {
             },
         );
 
-        let tools = if self.tool_executor.is_some() {
-            Some(create_log_tools())
-        } else {
-            None
-        };
+        let tools = self
+            .tool_executor
+            .as_ref()
+            .map(|executor| executor.definitions())
+            .filter(|tools| !tools.is_empty());
 
         let request = ChatRequest {
             messages: api_messages.clone(),
-            temperature: Some(0.7),
-            max_tokens: Some(4096),
+            temperature: Some(self.temperature),
+            max_tokens: Some(self.max_tokens),
             tools,
+            response_format: None,
         };
 
-        let response = self.provider.chat(request).await?;
+        let response = self.ask(terminal, request).await?;
 
         if let Some(tool_calls) = response.tool_calls {
             if !tool_calls.is_empty() && self.tool_executor.is_some() {
+                // The calls join the history only with their results: a turn
+                // cancelled while tools run must not leave calls unanswered,
+                // which providers reject in every later request.
+                let results = self.run_tools(terminal, &tool_calls).await?;
                 self.messages.push(ChatMessage {
                     role: "assistant".to_string(),
                     content: response.content.clone(),
                     tool_calls: Some(tool_calls.clone()),
                     tool_call_id: None,
+                    at: Some(Utc::now()),
+                    backend: None,
                 });
-
-                for tool_call in &tool_calls {
-                    self.status_message =
-                        Some(format!("Querying logs ({})...", tool_call.function.name));
-
-                    let tool_result = if let Some(executor) = &self.tool_executor {
-                        match executor
-                            .execute(&tool_call.function.name, &tool_call.function.arguments)
-                            .await
-                        {
-                            Ok(result) => result,
-                            Err(e) => format!("Error executing tool: {}", e),
-                        }
-                    } else {
-                        "No log backend configured".to_string()
+                for (tool_call, tool_result) in tool_calls.iter().zip(results) {
+                    let tool_backend = match self
+                        .mcp()
+                        .and_then(|mcp| mcp.tool_server(&tool_call.function.name))
+                    {
+                        Some(server) => Some(format!("MCP server {}", server)),
+                        None => self.backend_label(),
                     };
-
                     self.messages.push(ChatMessage {
                         role: "tool".to_string(),
                         content: tool_result,
                         tool_calls: None,
                         tool_call_id: Some(tool_call.id.clone()),
+                        at: Some(Utc::now()),
+                        backend: tool_backend,
                     });
                 }
 
-                self.status_message = Some("Analyzing results...".to_string());
+                self.status_message = Some("Analyzing results... (Esc to cancel)".to_string());
+                terminal.draw(|f| self.ui(f))?;
 
                 let mut followup_messages: Vec<Message> = self
                     .messages
@@ -1295,81 +2657,57 @@ impl TuiApp {
 
                 let followup_request = ChatRequest {
                     messages: followup_messages,
-                    temperature: Some(0.7),
-                    max_tokens: Some(4096),
+                    temperature: Some(self.temperature),
+                    max_tokens: Some(self.max_tokens),
                     tools: None,
+                    response_format: None,
                 };
 
-                let followup_response = self.provider.chat(followup_request).await?;
+                let followup_response = self.ask(terminal, followup_request).await?;
 
-                self.messages.push(ChatMessage {
-                    role: "assistant".to_string(),
-                    content: followup_response.content,
-                    tool_calls: None,
-                    tool_call_id: None,
-                });
+                self.messages
+                    .push(ChatMessage::new("assistant", followup_response.content));
             }
         } else {
-            self.messages.push(ChatMessage {
-                role: "assistant".to_string(),
-                content: response.content,
-                tool_calls: None,
-                tool_call_id: None,
-            });
+            self.messages
+                .push(ChatMessage::new("assistant", response.content));
         }
 
         Ok(())
     }
 
     fn build_system_message(&self) -> String {
-        let mut msg = String::from(
-            "You are Zeteo, an AI assistant specialized in log analysis and observability.\n\n",
+        let mcp_tools = self
+            .tool_executor
+            .as_ref()
+            .and_then(|executor| executor.mcp())
+            .map(|mcp| mcp.definitions())
+            .unwrap_or_default();
+        let mut prompt = crate::agent::system_prompt(
+            self.has_log_backend()
+                .then(|| self.backend_name.as_deref().unwrap_or("logs")),
+            &mcp_tools,
         );
 
-        if self.tool_executor.is_some() {
-            msg.push_str(&format!(
-                "## Your Capabilities\n\
-                You have access to a log backend ({backend}) and can query logs to help users investigate issues, \
-                find errors, analyze patterns, and troubleshoot problems.\n\n\
-                ## Available Tools\n\n\
-                ### 1. query_logs\n\
-                Search and retrieve logs from the backend.\n\
-                - **query** (required): Search string. Use '*' for all logs, or terms like 'error', 'timeout', 'failed'.\n\
-                - **max_results** (optional): Number of results (default: 50, max: 200). Start with 20-50 for initial queries.\n\
-                - **level** (optional): Filter by severity - must be exactly one of: ERROR, WARN, INFO, DEBUG\n\
-                - **service** (optional): Filter by service name (use list_services first if unsure).\n\
-                - **start_time** (optional): Relative time like '1h', '30m', '2d' or ISO 8601 format.\n\
-                - **end_time** (optional): Defaults to now.\n\n\
-                ### 2. list_services\n\
-                Get available service names. No parameters required. Call this first if you need to filter by service.\n\n\
-                ### 3. get_log_stats\n\
-                Get aggregated statistics (counts by level, service distribution).\n\
-                - **start_time** (optional): Start of time range.\n\
-                - **end_time** (optional): End of time range.\n\n\
-                ## Tool Usage Guidelines\n\n\
-                1. **Start broad, then narrow**: Begin with a general query, then refine based on results.\n\
-                2. **Use appropriate time ranges**: Default to '1h' for recent issues, '24h' for patterns, '7d' for trends.\n\
-                3. **Check services first**: If filtering by service, call list_services to get valid names.\n\
-                4. **Combine filters wisely**: Use level + query together for targeted results.\n\
-                5. **Handle empty results**: If no results, try broadening the query or time range.\n\n\
-                ## Response Format\n\n\
-                - Summarize findings clearly with key insights first.\n\
-                - Highlight error patterns, anomalies, or concerning trends.\n\
-                - Provide actionable recommendations when issues are found.\n\
-                - Format log snippets in code blocks for readability.\n\
-                - If results are truncated, suggest how to narrow the search.",
-                backend = self.backend_name.as_deref().unwrap_or("logs")
-            ));
-        } else {
-            msg.push_str(
-                "You can help with general questions about observability, logging best practices, \
-                and troubleshooting strategies.\n\n\
-                **Note**: No log backend is currently configured. To enable log analysis, \
-                configure a backend (kibana, openobserve, or elasticsearch) in your config file.",
+        if !self.attachments.is_empty() {
+            prompt.push_str(
+                "\n\n## Attached context\n\n\
+                The user attached these documents from their MCP servers. Use them where they are relevant.",
             );
+            for attachment in &self.attachments {
+                prompt.push_str(&format!(
+                    "\n\n### {} ({})\n\n{}",
+                    attachment.name, attachment.uri, attachment.text
+                ));
+            }
         }
+        prompt
+    }
 
-        msg
+    fn has_log_backend(&self) -> bool {
+        self.tool_executor
+            .as_ref()
+            .is_some_and(|executor| executor.has_backend())
     }
 
     fn scroll_to_bottom(&mut self) {
@@ -1377,25 +2715,38 @@ impl TuiApp {
     }
 
     fn ui(&mut self, f: &mut Frame) {
+        let tail_height = if self.tail.is_some() {
+            TAIL_PANE_HEIGHT
+        } else {
+            0
+        };
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(2),
                 Constraint::Min(1),
+                Constraint::Length(tail_height),
                 Constraint::Length(3),
             ])
             .split(f.area());
 
         self.render_header(f, chunks[0]);
         self.render_chat(f, chunks[1]);
-        self.render_input(f, chunks[2]);
+        if let Some(tail) = &self.tail {
+            render_tail(tail, f, chunks[2]);
+        }
+        self.render_input(f, chunks[3]);
 
         if self.show_slash_modal {
-            self.render_slash_modal(f, chunks[2]);
+            self.render_slash_modal(f, chunks[3]);
         }
 
-        if self.show_resume_modal {
-            self.render_resume_modal(f, chunks[1]);
+        if let Some(modal) = &self.resume {
+            render_resume_modal(modal, f, chunks[1]);
+        }
+
+        if self.show_picker_modal {
+            self.render_picker_modal(f, chunks[1]);
         }
     }
 
@@ -1463,12 +2814,12 @@ impl TuiApp {
         f.render_widget(modal, modal_area);
     }
 
-    fn render_resume_modal(&self, f: &mut Frame, chat_area: Rect) {
-        if self.resume_sessions.is_empty() {
+    fn render_picker_modal(&self, f: &mut Frame, chat_area: Rect) {
+        if self.picker_items.is_empty() {
             return;
         }
 
-        let modal_height = (self.resume_sessions.len() + 2).min(12) as u16;
+        let modal_height = (self.picker_items.len() + 2).min(12) as u16;
         let modal_width = 60u16.min(chat_area.width.saturating_sub(8));
 
         let modal_area = Rect {
@@ -1480,35 +2831,48 @@ impl TuiApp {
 
         f.render_widget(Clear, modal_area);
 
-        let mut lines: Vec<Line> = Vec::new();
+        let visible = modal_height.saturating_sub(2) as usize;
+        let first = self
+            .picker_selected
+            .saturating_sub(visible.saturating_sub(1));
 
-        for (i, session) in self.resume_sessions.iter().enumerate() {
-            let is_selected = i == self.resume_selected;
+        let lines: Vec<Line> = self
+            .picker_items
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(visible)
+            .map(|(i, item)| {
+                let is_selected = i == self.picker_selected;
 
-            let style = if is_selected {
-                Style::default()
-                    .fg(Color::Black)
-                    .bg(Color::Rgb(0, 122, 255))
-            } else {
-                Style::default().fg(Color::White)
-            };
+                let style = if is_selected {
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Rgb(0, 122, 255))
+                } else {
+                    Style::default().fg(Color::White)
+                };
 
-            let time_style = if is_selected {
-                Style::default()
-                    .fg(Color::Rgb(200, 200, 200))
-                    .bg(Color::Rgb(0, 122, 255))
-            } else {
-                Style::default().fg(Color::Rgb(142, 142, 147))
-            };
+                let detail_style = if is_selected {
+                    Style::default()
+                        .fg(Color::Rgb(200, 200, 200))
+                        .bg(Color::Rgb(0, 122, 255))
+                } else {
+                    Style::default().fg(Color::Rgb(142, 142, 147))
+                };
 
-            let time_ago = format_time_ago(session.updated_at);
-            let msg_count = format!("{} msgs", session.message_count);
+                Line::from(vec![
+                    Span::styled(format!(" {} ", item.value), style),
+                    Span::styled(format!("  {}", item.detail), detail_style),
+                ])
+            })
+            .collect();
 
-            lines.push(Line::from(vec![
-                Span::styled(format!(" {} ", session.title), style),
-                Span::styled(format!("  {} • {}", time_ago, msg_count), time_style),
-            ]));
-        }
+        let title = match self.picker_kind {
+            PickerKind::Provider => " Switch Provider ",
+            PickerKind::Model => " Switch Model ",
+            PickerKind::Resource => " Attach Resource ",
+        };
 
         let modal = Paragraph::new(lines)
             .block(
@@ -1516,7 +2880,7 @@ impl TuiApp {
                     .borders(Borders::ALL)
                     .border_style(Style::default().fg(Color::Rgb(58, 58, 60)))
                     .border_type(ratatui::widgets::BorderType::Rounded)
-                    .title(" Resume Conversation ")
+                    .title(title)
                     .title_style(Style::default().fg(Color::Rgb(142, 142, 147))),
             )
             .style(Style::default().bg(Color::Rgb(30, 30, 30)));
@@ -1525,7 +2889,7 @@ impl TuiApp {
     }
 
     fn render_header(&self, f: &mut Frame, area: Rect) {
-        let backend_indicator = if self.tool_executor.is_some() {
+        let backend_indicator = if self.has_log_backend() {
             format!(" [{}]", self.backend_name.as_deref().unwrap_or("logs"))
         } else {
             String::new()
@@ -1543,6 +2907,17 @@ impl TuiApp {
                 backend_indicator,
                 Style::default().fg(Color::Rgb(142, 142, 147)),
             ),
+            Span::styled(
+                format!(" · {}", self.provider.model_name()),
+                Style::default().fg(Color::Rgb(100, 100, 100)),
+            ),
+            Span::styled(
+                self.usage
+                    .summary()
+                    .map(|s| format!(" · {}", s))
+                    .unwrap_or_default(),
+                Style::default().fg(Color::Rgb(100, 100, 100)),
+            ),
         ]))
         .alignment(Alignment::Center);
 
@@ -1607,6 +2982,13 @@ impl TuiApp {
                         Style::default().fg(Color::Rgb(142, 142, 147)),
                     )));
                 }
+                "notice" => {
+                    lines.push(Line::from(""));
+                    lines.extend(markdown::parse_markdown_to_lines(
+                        &msg.content,
+                        inner.width.saturating_sub(2) as usize,
+                    ));
+                }
                 "error" => {
                     lines.push(Line::from(""));
                     lines.push(Line::from(Span::styled(
@@ -1626,6 +3008,10 @@ impl TuiApp {
                     .fg(Color::Rgb(0, 122, 255))
                     .add_modifier(Modifier::BOLD),
             )]));
+            lines.extend(markdown::parse_markdown_to_lines(
+                &self.streamed,
+                inner.width.saturating_sub(2) as usize,
+            ));
             let loading_text = self.status_message.as_deref().unwrap_or("...");
             lines.push(Line::from(Span::styled(
                 loading_text,
@@ -1633,6 +3019,18 @@ impl TuiApp {
             )));
         }
 
+        if let Some(export) = &self.export {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "  ⇣ Exporting to {}: {} logs so far (Esc to cancel)",
+                    export.path.display(),
+                    export.written.load(Ordering::Relaxed)
+                ),
+                Style::default().fg(Color::Rgb(142, 142, 147)),
+            )));
+        }
+
         let total = lines.len();
         let visible = inner.height as usize;
         let max_scroll = total.saturating_sub(visible);
@@ -1658,7 +3056,7 @@ impl TuiApp {
     fn render_welcome(&self, f: &mut Frame, area: Rect) {
         let center_y = area.height / 2;
 
-        let backend_status = if self.tool_executor.is_some() {
+        let mut backend_status = if self.has_log_backend() {
             format!(
                 "Connected to {}",
                 self.backend_name.as_deref().unwrap_or("log backend")
@@ -1666,8 +3064,14 @@ impl TuiApp {
         } else {
             "No log backend configured".to_string()
         };
+        if let Some(mcp) = self.mcp() {
+            backend_status.push_str(&format!(" · {} MCP tools", mcp.tool_count()));
+            if !mcp.prompts().is_empty() {
+                backend_status.push_str(&format!(" · {} MCP prompts", mcp.prompts().len()));
+            }
+        }
 
-        let welcome = Paragraph::new(vec![
+        let mut lines = vec![
             Line::from(""),
             Line::from(vec![Span::styled(
                 "●",
@@ -1683,14 +3087,21 @@ impl TuiApp {
                 backend_status,
                 Style::default().fg(Color::Rgb(100, 100, 100)),
             )),
-        ])
-        .alignment(Alignment::Center);
+        ];
+        lines.extend(self.startup_errors.iter().map(|error| {
+            Line::from(Span::styled(
+                error.clone(),
+                Style::default().fg(Color::Rgb(255, 69, 58)),
+            ))
+        }));
+        let height = lines.len() as u16 + 1;
+        let welcome = Paragraph::new(lines).alignment(Alignment::Center);
 
         let welcome_area = Rect {
             x: area.x,
             y: area.y + center_y.saturating_sub(3),
             width: area.width,
-            height: 7,
+            height: height.min(area.height),
         };
 
         f.render_widget(welcome, welcome_area);
@@ -1807,6 +3218,136 @@ fn wrap_text(text: &str, width: usize) -> Vec<String> {
     lines
 }
 
+fn render_resume_modal(modal: &ResumeModal, f: &mut Frame, chat_area: Rect) {
+    let rows = modal.matches.len().clamp(1, RESUME_ROWS);
+    let summary = modal
+        .selected_entry()
+        .and_then(|entry| entry.info.summary.as_deref());
+    // Borders, the search line, the selected summary and the key hints
+    let modal_height = (rows + 4 + usize::from(summary.is_some())) as u16;
+    let modal_width = 70u16.min(chat_area.width.saturating_sub(8));
+
+    let modal_area = Rect {
+        x: chat_area.x + (chat_area.width.saturating_sub(modal_width)) / 2,
+        y: chat_area.y + (chat_area.height.saturating_sub(modal_height)) / 2,
+        width: modal_width,
+        height: modal_height,
+    };
+
+    f.render_widget(Clear, modal_area);
+
+    let grey = Style::default().fg(Color::Rgb(142, 142, 147));
+    let (label, text) = match modal.input {
+        ResumeInput::Search => (" Search: ", &modal.query),
+        ResumeInput::Rename => (" Rename: ", &modal.edit),
+        ResumeInput::Tags => (" Tags: ", &modal.edit),
+    };
+    let mut lines = vec![Line::from(vec![
+        Span::styled(label, grey),
+        Span::styled(
+            format!("{}▏", text),
+            Style::default().fg(Color::Rgb(229, 229, 234)),
+        ),
+    ])];
+
+    if modal.matches.is_empty() {
+        lines.push(Line::from(Span::styled(" No matching conversations", grey)));
+    }
+
+    let first = modal.selected.saturating_sub(RESUME_ROWS - 1);
+    for (i, &index) in modal
+        .matches
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(RESUME_ROWS)
+    {
+        let session = &modal.entries[index].info;
+        let is_selected = i == modal.selected;
+
+        let style = if is_selected {
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Rgb(0, 122, 255))
+        } else {
+            Style::default().fg(Color::White)
+        };
+
+        let time_style = if is_selected {
+            Style::default()
+                .fg(Color::Rgb(200, 200, 200))
+                .bg(Color::Rgb(0, 122, 255))
+        } else {
+            grey
+        };
+
+        let tag_style = if is_selected {
+            time_style
+        } else {
+            Style::default().fg(Color::Rgb(255, 214, 10))
+        };
+
+        let time_ago = format_time_ago(session.updated_at);
+        let msg_count = format!("{} msgs", session.message_count);
+
+        let mut spans = vec![Span::styled(format!(" {} ", session.title), style)];
+        if !session.tags.is_empty() {
+            let tags: Vec<String> = session.tags.iter().map(|t| format!("#{}", t)).collect();
+            spans.push(Span::styled(format!(" {}", tags.join(" ")), tag_style));
+        }
+        spans.push(Span::styled(
+            format!("  {} • {}", time_ago, msg_count),
+            time_style,
+        ));
+        lines.push(Line::from(spans));
+    }
+
+    if let Some(summary) = summary {
+        let width = modal_width.saturating_sub(4) as usize;
+        let mut text: String = summary.chars().take(width).collect();
+        if summary.chars().count() > width {
+            text.pop();
+            text.push('…');
+        }
+        lines.push(Line::from(Span::styled(
+            format!(" {}", text),
+            grey.add_modifier(Modifier::ITALIC),
+        )));
+    }
+
+    let hint = if modal.confirm_delete {
+        Span::styled(
+            " Press Ctrl+D again to delete this conversation",
+            Style::default().fg(Color::Rgb(255, 69, 58)),
+        )
+    } else if modal.input == ResumeInput::Search {
+        Span::styled(
+            " ↵ open · ^R rename · ^T tags · ^D delete · Esc close",
+            Style::default().fg(Color::Rgb(100, 100, 100)),
+        )
+    } else {
+        Span::styled(
+            " ↵ save · Esc cancel",
+            Style::default().fg(Color::Rgb(100, 100, 100)),
+        )
+    };
+    lines.push(Line::from(hint));
+
+    let title = format!(" Resume Conversation ({}) ", modal.matches.len());
+    let modal = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Rgb(58, 58, 60)))
+                .border_type(ratatui::widgets::BorderType::Rounded)
+                .title(title)
+                .title_style(grey),
+        )
+        .style(Style::default().bg(Color::Rgb(30, 30, 30)));
+
+    f.render_widget(modal, modal_area);
+}
+
 /// Formats a Unix timestamp (seconds since epoch) as a human-readable relative time string.
 /// Returns strings like "just now", "5m ago", "2h ago", or "3d ago".
 fn format_time_ago(timestamp: i64) -> String {
@@ -1827,161 +3368,422 @@ fn format_time_ago(timestamp: i64) -> String {
     }
 }
 
-fn try_provider(name: &str) -> Option<Arc<dyn AiProvider>> {
-    match name {
-        "openai" => {
-            let key = std::env::var("OPENAI_API_KEY").ok()?;
-            Some(Arc::new(crate::providers::OpenAiProvider::new(key, None)))
-        }
-        "google" => {
-            let key = std::env::var("GOOGLE_API_KEY").ok()?;
-            Some(Arc::new(crate::providers::GoogleProvider::new(key, None)))
-        }
-        "azure" => {
-            let key = std::env::var("AZURE_OPENAI_API_KEY").ok()?;
-            let endpoint = std::env::var("AZURE_OPENAI_ENDPOINT").ok()?;
-            let deployment = std::env::var("AZURE_OPENAI_DEPLOYMENT").ok()?;
-            Some(Arc::new(crate::providers::AzureProvider::new(
-                key, endpoint, deployment,
-            )))
-        }
-        "vertex" => {
-            let project = std::env::var("GOOGLE_CLOUD_PROJECT").ok()?;
-            let location = std::env::var("GOOGLE_CLOUD_LOCATION")
-                .unwrap_or_else(|_| "us-central1".to_string());
-            Some(Arc::new(crate::providers::VertexProvider::new(
-                project, location, None,
-            )))
-        }
-        "ollama" => {
-            let model = std::env::var("OLLAMA_MODEL").ok();
-            let base_url = std::env::var("OLLAMA_BASE_URL").ok();
-            Some(Arc::new(crate::providers::OllamaProvider::new(
-                model, base_url,
-            )))
-        }
-        _ => None,
+/// Status line for a batch of tool calls, e.g.
+/// "Querying logs 1/3: query_logs ✓ · list_services … · get_log_stats ·"
+fn tool_progress_status(calls: &[ToolCall], states: &[Option<ToolProgress>]) -> String {
+    let done = states
+        .iter()
+        .filter(|s| matches!(s, Some(ToolProgress::Finished(_) | ToolProgress::Failed(_))))
+        .count();
+
+    let calls = calls
+        .iter()
+        .zip(states)
+        .map(|(call, state)| {
+            let mark = match state {
+                None => "·",
+                Some(ToolProgress::Started(_)) => "…",
+                Some(ToolProgress::Finished(_)) => "✓",
+                Some(ToolProgress::Failed(_)) => "✗",
+            };
+            format!("{} {}", call.function.name, mark)
+        })
+        .collect::<Vec<_>>()
+        .join(" · ");
+
+    format!(
+        "Querying logs {}/{}: {} (Esc to cancel)",
+        done,
+        states.len(),
+        calls
+    )
+}
+
+/// Resolve once Esc or Ctrl+C is pressed; other keys are discarded while waiting
+async fn wait_for_cancel_key() {
+    loop {
+        while event::poll(std::time::Duration::ZERO).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                let ctrl_c =
+                    key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+                if key.code == KeyCode::Esc || ctrl_c {
+                    return;
+                }
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
     }
 }
 
-fn find_provider() -> Option<Arc<dyn AiProvider>> {
-    ["openai", "google", "azure", "vertex", "ollama"]
-        .iter()
-        .find_map(|p| try_provider(p))
+/// A provider built from a profile, plus the generation settings it carries
+struct ProviderChoice {
+    name: String,
+    provider: Arc<dyn AiProvider>,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
 }
 
-fn try_backend(name: &str, config: &Config) -> Option<(Arc<dyn LogBackendClient>, String)> {
-    let backend_config = config.backends.get(name)?;
-
-    match backend_config {
-        LogBackend::Elasticsearch {
-            url,
-            username,
-            password,
-            index_pattern,
-            verify_ssl,
-        } => ElasticsearchClient::new(
-            url.clone(),
-            username.clone(),
-            password.clone(),
-            index_pattern.clone(),
-            *verify_ssl,
-        )
-        .ok()
-        .map(|c| {
-            (
-                Arc::new(c) as Arc<dyn LogBackendClient>,
-                "elasticsearch".to_string(),
-            )
-        }),
-        LogBackend::Kibana {
-            url,
-            auth_token,
-            index_pattern,
-            verify_ssl,
-            version,
-        } => KibanaClient::new(
-            url.clone(),
-            auth_token.clone(),
-            index_pattern.clone(),
-            version.clone(),
-            *verify_ssl,
-        )
+/// Provider types detected from environment variables, in auto-detection order
+const BUILTIN_PROVIDERS: &[&str] = &["openai", "anthropic", "google", "azure", "vertex", "ollama"];
+
+/// Build a provider on the blocking pool: a profile's `api_key_command` (and
+/// those of its fallbacks) may take a while to answer
+async fn select_provider(
+    name: &str,
+    config: Option<&Config>,
+    model: Option<&str>,
+) -> Result<ProviderChoice> {
+    let (name, config, model) = (name.to_string(), config.cloned(), model.map(String::from));
+    tokio::task::spawn_blocking(move || build_provider(&name, config.as_ref(), model.as_deref()))
+        .await?
+}
+
+/// `find_provider` on the blocking pool, like `select_provider`
+async fn detect_provider(config: Option<&Config>, model: Option<&str>) -> Option<ProviderChoice> {
+    let (config, model) = (config.cloned(), model.map(String::from));
+    tokio::task::spawn_blocking(move || find_provider(config.as_ref(), model.as_deref()))
+        .await
         .ok()
-        .map(|c| {
-            (
-                Arc::new(c) as Arc<dyn LogBackendClient>,
-                "kibana".to_string(),
-            )
-        }),
-        LogBackend::OpenObserve {
-            url,
-            username,
-            password,
-            organization,
-            stream,
-            verify_ssl,
-        } => OpenObserveClient::new(
-            url.clone(),
-            username.clone(),
-            password.clone(),
-            organization.clone(),
-            stream.clone(),
-            *verify_ssl,
-        )
+        .flatten()
+}
+
+fn build_provider(
+    name: &str,
+    config: Option<&Config>,
+    model: Option<&str>,
+) -> Result<ProviderChoice> {
+    // Provider types are lowercase; config profiles match as written first
+    let name = if config.is_some_and(|c| c.providers.contains_key(name)) {
+        name.to_string()
+    } else {
+        name.to_lowercase()
+    };
+    let profile = crate::providers::resolve_profile(&name, config)
+        .ok_or_else(|| anyhow::anyhow!("Provider '{}' not configured", name))?;
+    let provider = crate::providers::with_fallbacks(&name, &profile, config, model)?;
+
+    Ok(ProviderChoice {
+        name,
+        provider,
+        temperature: profile.temperature,
+        max_tokens: profile.max_tokens,
+    })
+}
+
+/// "type · model" for the provider picker
+fn profile_detail(profile: &ProviderConfig) -> String {
+    let provider_type = serde_json::to_value(profile.provider_type)
         .ok()
-        .map(|c| {
-            (
-                Arc::new(c) as Arc<dyn LogBackendClient>,
-                "openobserve".to_string(),
-            )
-        }),
+        .and_then(|value| value.as_str().map(String::from))
+        .unwrap_or_default();
+    match profile.model.as_ref().or(profile.deployment.as_ref()) {
+        Some(model) => format!("{} · {}", provider_type, model),
+        None => provider_type,
+    }
+}
+
+fn try_provider(
+    name: &str,
+    config: Option<&Config>,
+    model: Option<&str>,
+) -> Option<ProviderChoice> {
+    build_provider(name, config, model).ok()
+}
+
+fn find_provider(config: Option<&Config>, model: Option<&str>) -> Option<ProviderChoice> {
+    if let Some(default) = config.and_then(|c| c.default_provider.as_deref()) {
+        if let Some(choice) = try_provider(default, config, model) {
+            return Some(choice);
+        }
     }
+
+    // Config profiles come before the environment: Ollama needs no key, so
+    // it would otherwise always win
+    let profiles = config.and_then(|config| {
+        let mut names: Vec<&String> = config.providers.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .find_map(|p| try_provider(p, Some(config), model))
+    });
+    profiles.or_else(|| {
+        BUILTIN_PROVIDERS
+            .iter()
+            .find_map(|p| try_provider(p, None, model))
+    })
+}
+
+/// The requested log backend, or the first configured one when `name` is `None`
+fn select_backend(
+    config: Option<&Config>,
+    name: Option<&str>,
+) -> Result<Option<(ToolExecutor, String)>> {
+    let Some(config) = config else {
+        return match name {
+            Some(name) => anyhow::bail!("Backend '{}' not found: no config.json", name),
+            None => Ok(None),
+        };
+    };
+
+    let backend = match name {
+        Some(name) => {
+            let name = name.to_lowercase();
+            Some((build_named_backend(config, &name)?, name))
+        }
+        None => find_backend(config),
+    };
+
+    Ok(backend.map(|(client, name)| (ToolExecutor::new(client), name)))
 }
 
-fn find_backend(config: &Config) -> Option<(Arc<dyn LogBackendClient>, String)> {
-    ["openobserve", "kibana", "elasticsearch"]
+fn render_tail(tail: &TailPane, f: &mut Frame, area: Rect) {
+    let mut title = format!(" tail: {} ", tail.query);
+    if tail.paused {
+        title.push_str(&format!(
+            "· paused, {} new · Ctrl+P resumes ",
+            tail.held.len()
+        ));
+    } else {
+        title.push_str("· Ctrl+P pauses ");
+    }
+    if let Some(filter) = &tail.filter {
+        title.push_str(&format!("· filter: {} ", filter));
+    }
+
+    let rows = area.height.saturating_sub(2) as usize;
+    let mut lines: Vec<Line> = tail
+        .entries
         .iter()
-        .find_map(|name| try_backend(name, config))
+        .rev()
+        .filter(|entry| tail.matches(entry))
+        .take(rows)
+        .map(|entry| {
+            let level_color = match entry.level.to_lowercase().as_str() {
+                "error" | "fatal" | "critical" => Color::Rgb(255, 69, 58),
+                "warn" | "warning" => Color::Rgb(255, 214, 10),
+                "info" => Color::Rgb(48, 209, 88),
+                "debug" | "trace" => Color::Rgb(10, 132, 255),
+                _ => Color::Rgb(142, 142, 147),
+            };
+            let time = tail::parse_timestamp(&entry.timestamp)
+                .map(|t| {
+                    t.with_timezone(&chrono::Local)
+                        .format("%H:%M:%S%.3f")
+                        .to_string()
+                })
+                .unwrap_or_else(|| entry.timestamp.clone());
+            let mut spans = vec![
+                Span::styled(
+                    format!("{} ", time),
+                    Style::default().fg(Color::Rgb(100, 100, 100)),
+                ),
+                Span::styled(
+                    format!("{:<5} ", entry.level.to_uppercase()),
+                    Style::default()
+                        .fg(level_color)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ];
+            if let Some(service) = &entry.service {
+                spans.push(Span::styled(
+                    format!("{} ", service),
+                    Style::default().fg(Color::Rgb(100, 210, 255)),
+                ));
+            }
+            spans.push(Span::styled(
+                entry.message.replace('\n', " "),
+                Style::default().fg(Color::Rgb(229, 229, 234)),
+            ));
+            Line::from(spans)
+        })
+        .collect();
+    lines.reverse();
+    if let Some(warning) = &tail.warning {
+        if lines.len() == rows {
+            lines.remove(0);
+        }
+        lines.push(Line::from(Span::styled(
+            warning.clone(),
+            Style::default().fg(Color::Rgb(255, 214, 10)),
+        )));
+    }
+
+    let pane = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::TOP | Borders::BOTTOM)
+            .border_style(Style::default().fg(Color::Rgb(58, 58, 60)))
+            .title(Span::styled(
+                title,
+                Style::default().fg(Color::Rgb(142, 142, 147)),
+            )),
+    );
+    f.render_widget(pane, area);
 }
 
-pub async fn create_tui_session(
+/// Cut an attached resource down to what is reasonable to send every turn
+fn truncate_attachment(text: String) -> String {
+    match text.char_indices().nth(MAX_ATTACHMENT_CHARS) {
+        Some((end, _)) => format!("{}\n[truncated]", &text[..end]),
+        None => text,
+    }
+}
+
+/// Start the MCP servers in config.json. Servers that fail are reported
+/// without stopping the rest.
+async fn start_mcp_servers(config: Option<&Config>) -> (Option<Arc<McpRegistry>>, Vec<String>) {
+    let Some(config) = config.filter(|c| !c.servers.is_empty()) else {
+        return (None, Vec::new());
+    };
+    let (registry, errors) = McpRegistry::start(&config.servers).await;
+    let registry = Arc::new(registry);
+    registry.supervise(Supervision::default());
+    (Some(registry), errors)
+}
+
+/// Lines of stderr shown per server by `/mcp`
+const MCP_STATUS_STDERR_LINES: usize = 5;
+
+/// Markdown for `/mcp`: each server's state, what it offers and its recent stderr
+fn mcp_status_report(servers: &[ServerStatus]) -> String {
+    let mut report = String::from("## MCP Servers");
+    for server in servers {
+        let state = match &server.state {
+            ServerState::Running => "● running".to_string(),
+            ServerState::Restarting { attempt, retry_at } => format!(
+                "○ restarting in {}s (attempt {})",
+                retry_at
+                    .saturating_duration_since(std::time::Instant::now())
+                    .as_secs(),
+                attempt
+            ),
+            ServerState::Stopped => "○ stopped".to_string(),
+        };
+        report.push_str(&format!(
+            "\n\n**{}** {} · {} tools · {} prompts · {} restarts\n`{}`",
+            server.name, state, server.tools, server.prompts, server.restarts, server.target
+        ));
+        if let Some(error) = &server.last_error {
+            report.push_str(&format!("\nLast error: {}", error));
+        }
+        let skip = server.stderr.len().saturating_sub(MCP_STATUS_STDERR_LINES);
+        if skip < server.stderr.len() {
+            report.push_str(&format!("\n```\n{}\n```", server.stderr[skip..].join("\n")));
+        }
+    }
+    report.push_str("\n\n*Usage: `/mcp restart <server>` to restart one now*");
+    report
+}
+
+/// Combine the log backend's executor with MCP server tools
+fn with_mcp_tools(
+    executor: Option<ToolExecutor>,
+    mcp: Option<Arc<McpRegistry>>,
+) -> Option<ToolExecutor> {
+    match (executor, mcp) {
+        (Some(executor), Some(mcp)) => Some(executor.with_mcp(mcp)),
+        (None, Some(mcp)) => Some(ToolExecutor::mcp_only(mcp)),
+        (executor, None) => executor,
+    }
+}
+
+/// Log tools for `zeteo mcp-serve`, which has nothing to offer without a backend
+pub fn create_log_tool_executor(backend: Option<String>) -> Result<ToolExecutor> {
+    let config = Config::load().ok();
+    select_backend(config.as_ref(), backend.as_deref())?
+        .map(|(executor, _)| executor)
+        .ok_or_else(|| {
+            anyhow::anyhow!("No log backend configured. Add one to config.json or pass --backend.")
+        })
+}
+
+/// Build the headless agent behind `zeteo ask`. Unlike the TUI, a requested
+/// backend that can't be found is an error rather than a silent fallback.
+pub async fn create_agent(
     provider: Option<String>,
+    model: Option<String>,
     backend: Option<String>,
-) -> Result<TuiApp> {
-    let provider = match provider {
-        Some(name) => try_provider(&name.to_lowercase())
-            .ok_or_else(|| anyhow::anyhow!("Provider '{}' not configured", name))?,
-        None => find_provider()
-            .ok_or_else(|| anyhow::anyhow!("No AI provider configured. Set OPENAI_API_KEY, AZURE_OPENAI_API_KEY, or GOOGLE_API_KEY."))?,
+) -> Result<Agent> {
+    let config = Config::load().ok();
+
+    let choice = match provider {
+        Some(name) => select_provider(&name, config.as_ref(), model.as_deref()).await?,
+        None => detect_provider(config.as_ref(), model.as_deref())
+            .await
+            .ok_or_else(|| anyhow::anyhow!("No AI provider configured. Set OPENAI_API_KEY, ANTHROPIC_API_KEY, AZURE_OPENAI_API_KEY, or GOOGLE_API_KEY, or add a provider to config.json."))?,
     };
 
+    let (tool_executor, backend_name) =
+        select_backend(config.as_ref(), backend.as_deref())?.unzip();
+    let (mcp, mcp_errors) = start_mcp_servers(config.as_ref()).await;
+    for error in mcp_errors {
+        eprintln!("Warning: {}", error);
+    }
+    let tool_executor = with_mcp_tools(tool_executor, mcp);
+    let tool_concurrency = config
+        .as_ref()
+        .and_then(|c| c.tool_concurrency)
+        .unwrap_or(DEFAULT_TOOL_CONCURRENCY);
+
+    Ok(Agent::new(choice.provider, tool_executor, backend_name)
+        .with_sampling(choice.temperature, choice.max_tokens)
+        .with_tool_concurrency(tool_concurrency))
+}
+
+pub async fn create_tui_session(
+    provider: Option<String>,
+    model: Option<String>,
+    backend: Option<String>,
+) -> Result<TuiApp> {
     let config = Config::load().ok();
 
-    let (tool_executor, backend_name) = if let Some(ref cfg) = config {
-        let backend_result = match backend {
-            Some(name) => try_backend(&name.to_lowercase(), cfg)
-                .ok_or_else(|| anyhow::anyhow!("Backend '{}' not found in config", name))
-                .ok(),
-            None => find_backend(cfg),
-        };
+    let choice = match provider {
+        Some(name) => select_provider(&name, config.as_ref(), model.as_deref()).await?,
+        None => detect_provider(config.as_ref(), model.as_deref())
+            .await
+            .ok_or_else(|| anyhow::anyhow!("No AI provider configured. Set OPENAI_API_KEY, ANTHROPIC_API_KEY, AZURE_OPENAI_API_KEY, or GOOGLE_API_KEY, or add a provider to config.json."))?,
+    };
 
-        if let Some((client, name)) = backend_result {
-            (Some(ToolExecutor::new(client)), Some(name))
-        } else {
-            (None, None)
+    let (tool_executor, backend_name) = select_backend(config.as_ref(), backend.as_deref())
+        .ok()
+        .flatten()
+        .unzip();
+    let (mcp, mut startup_errors) = start_mcp_servers(config.as_ref()).await;
+    let tool_executor = with_mcp_tools(tool_executor, mcp);
+
+    let session_store = match create_session_store(config.as_ref()).await {
+        Ok(store) => store,
+        Err(e) => {
+            startup_errors.push(format!("Sessions won't be saved: {:#}", e));
+            None
         }
-    } else {
-        (None, None)
     };
 
-    let session_store = try_create_session_store().await;
-
-    Ok(TuiApp::new(
-        provider,
+    let mut app = TuiApp::new(
+        choice.provider.clone(),
         tool_executor,
         backend_name,
         config,
         session_store,
-    ))
+    )
+    .with_provider_choice(choice);
+    app.startup_errors = startup_errors;
+    app.resource_updates = app.mcp().map(|mcp| mcp.resource_updates());
+    Ok(app)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProviderType;
+
+    #[test]
+    fn test_find_provider_prefers_config_profiles() {
+        let mut config: Config = serde_json::from_str(r#"{"servers": {}}"#).unwrap();
+        let mut profile = ProviderConfig::for_type(ProviderType::Openai);
+        profile.api_key = Some("sk-test".to_string());
+        config.providers.insert("work".to_string(), profile);
+
+        let choice = find_provider(Some(&config), None).unwrap();
+        assert_eq!(choice.name, "work");
+    }
 }
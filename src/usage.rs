@@ -0,0 +1,267 @@
+//! Token and cost accounting across a chat session.
+
+use crate::config::ModelPrice;
+use crate::providers::{ChatResponse, Usage};
+
+/// Cost in USD of `usage` at `price`
+pub fn cost(price: &ModelPrice, usage: &Usage) -> f64 {
+    let cached = usage.cached_tokens.min(usage.prompt_tokens);
+    let uncached = usage.prompt_tokens - cached;
+
+    (uncached as f64 * price.input
+        + cached as f64 * price.cached_input.unwrap_or(price.input)
+        + usage.completion_tokens as f64 * price.output)
+        / 1_000_000.0
+}
+
+/// Usage of one user turn: the first completion plus any follow-up after tool calls
+#[derive(Debug, Clone, Default)]
+pub struct TurnUsage {
+    pub prompt: String,
+    pub model: String,
+    pub usage: Usage,
+    /// `None` when no completion in the turn had a known price
+    pub cost: Option<f64>,
+    pub tool_calls: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct UsageLedger {
+    turns: Vec<TurnUsage>,
+}
+
+impl UsageLedger {
+    pub fn start_turn(&mut self, prompt: &str) {
+        self.turns.push(TurnUsage {
+            prompt: prompt.to_string(),
+            ..Default::default()
+        });
+    }
+
+    /// Add a completion to the current turn
+    pub fn record(&mut self, response: &ChatResponse, price: Option<&ModelPrice>) {
+        let Some(turn) = self.turns.last_mut() else {
+            return;
+        };
+
+        turn.model = response.model.clone();
+        turn.tool_calls += response.tool_calls.as_ref().map_or(0, Vec::len);
+
+        if let Some(usage) = response.usage {
+            turn.usage += usage;
+            if let Some(price) = price {
+                *turn.cost.get_or_insert(0.0) += cost(price, &usage);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.turns.clear();
+    }
+
+    pub fn total(&self) -> Usage {
+        let mut total = Usage::default();
+        for turn in &self.turns {
+            total += turn.usage;
+        }
+        total
+    }
+
+    /// Sum of all priced turns, `None` if nothing could be priced
+    pub fn total_cost(&self) -> Option<f64> {
+        self.turns
+            .iter()
+            .filter_map(|t| t.cost)
+            .fold(None, |acc, c| Some(acc.unwrap_or(0.0) + c))
+    }
+
+    /// Compact form for the header bar, e.g. "12.3k tok · $0.042"
+    pub fn summary(&self) -> Option<String> {
+        let total = self.total().total();
+        if total == 0 {
+            return None;
+        }
+
+        Some(match self.total_cost() {
+            Some(cost) => format!("{} tok · {}", format_tokens(total), format_cost(cost)),
+            None => format!("{} tok", format_tokens(total)),
+        })
+    }
+
+    /// Markdown report splitting spend between turns that queried logs and those that didn't
+    pub fn breakdown(&self) -> String {
+        if self.turns.is_empty() {
+            return "## Session Usage\n\nNo requests yet.".to_string();
+        }
+
+        let total = self.total();
+        let mut out = format!(
+            "## Session Usage\n\n**Total:** {} tokens ({} prompt, {} cached, {} completion)",
+            total.total(),
+            total.prompt_tokens,
+            total.cached_tokens,
+            total.completion_tokens
+        );
+        if let Some(cost) = self.total_cost() {
+            out.push_str(&format!(" · **{}**", format_cost(cost)));
+        }
+        out.push_str("\n\n");
+
+        let (tool_heavy, plain): (Vec<&TurnUsage>, Vec<&TurnUsage>) =
+            self.turns.iter().partition(|t| t.tool_calls > 0);
+        out.push_str(&group_line("Turns with log queries", &tool_heavy));
+        out.push_str(&group_line("Turns without tools", &plain));
+
+        let mut costliest: Vec<&TurnUsage> = self.turns.iter().collect();
+        costliest.sort_by(|a, b| {
+            b.cost
+                .unwrap_or(0.0)
+                .total_cmp(&a.cost.unwrap_or(0.0))
+                .then(b.usage.total().cmp(&a.usage.total()))
+        });
+
+        out.push_str("\n### Most expensive turns\n\n");
+        for turn in costliest.iter().take(5) {
+            let cost = turn
+                .cost
+                .map(format_cost)
+                .unwrap_or_else(|| "—".to_string());
+            out.push_str(&format!(
+                "- {} · {} tok · {} tool call{} · \"{}\"\n",
+                cost,
+                format_tokens(turn.usage.total()),
+                turn.tool_calls,
+                if turn.tool_calls == 1 { "" } else { "s" },
+                truncate(&turn.prompt, 50)
+            ));
+        }
+
+        let mut unpriced: Vec<&str> = self
+            .turns
+            .iter()
+            .filter(|t| t.cost.is_none() && t.usage.total() > 0)
+            .map(|t| t.model.as_str())
+            .collect();
+        unpriced.sort();
+        unpriced.dedup();
+        if !unpriced.is_empty() {
+            out.push_str(&format!(
+                "\n*No price configured for: {}. Add them under \"pricing\" in config.json.*",
+                unpriced.join(", ")
+            ));
+        }
+
+        out
+    }
+}
+
+fn group_line(label: &str, turns: &[&TurnUsage]) -> String {
+    let tokens: u64 = turns.iter().map(|t| t.usage.total()).sum();
+    let cost: f64 = turns.iter().filter_map(|t| t.cost).sum();
+    format!(
+        "- **{}:** {} · {} tok · {}\n",
+        label,
+        turns.len(),
+        format_tokens(tokens),
+        format_cost(cost)
+    )
+}
+
+fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.2}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+fn format_cost(cost: f64) -> String {
+    if cost < 0.01 {
+        format!("${:.4}", cost)
+    } else {
+        format!("${:.2}", cost)
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() > max {
+        format!("{}…", line.chars().take(max).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{FunctionCall, ToolCall};
+
+    fn response(prompt: u64, cached: u64, completion: u64, tool_calls: usize) -> ChatResponse {
+        ChatResponse {
+            content: String::new(),
+            model: "gpt-4o".to_string(),
+            tool_calls: (tool_calls > 0).then(|| {
+                (0..tool_calls)
+                    .map(|i| ToolCall {
+                        id: format!("call_{}", i),
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: "query_logs".to_string(),
+                            arguments: "{}".to_string(),
+                        },
+                    })
+                    .collect()
+            }),
+            provider: None,
+            usage: Some(Usage {
+                prompt_tokens: prompt,
+                completion_tokens: completion,
+                cached_tokens: cached,
+            }),
+        }
+    }
+
+    const PRICE: ModelPrice = ModelPrice {
+        input: 2.5,
+        output: 10.0,
+        cached_input: Some(1.25),
+    };
+
+    #[test]
+    fn test_cost_discounts_cached_tokens() {
+        let usage = Usage {
+            prompt_tokens: 1_000_000,
+            completion_tokens: 100_000,
+            cached_tokens: 400_000,
+        };
+        // 600k * 2.5 + 400k * 1.25 + 100k * 10, per million
+        assert!((cost(&PRICE, &usage) - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ledger_accumulates_turns() {
+        let mut ledger = UsageLedger::default();
+
+        ledger.start_turn("why are checkout pods restarting?");
+        ledger.record(&response(10_000, 0, 200, 2), Some(&PRICE));
+        ledger.record(&response(14_000, 8_000, 600, 0), Some(&PRICE));
+
+        ledger.start_turn("thanks");
+        ledger.record(&response(15_000, 14_000, 50, 0), None);
+
+        let total = ledger.total();
+        assert_eq!(total.prompt_tokens, 39_000);
+        assert_eq!(total.cached_tokens, 22_000);
+        assert_eq!(total.completion_tokens, 850);
+
+        let cost = ledger.total_cost().unwrap();
+        assert!((cost - 0.058).abs() < 1e-9);
+        assert_eq!(ledger.summary().unwrap(), "39.9k tok · $0.06");
+
+        let report = ledger.breakdown();
+        assert!(report.contains("**Turns with log queries:** 1"));
+        assert!(report.contains("**Turns without tools:** 1"));
+        assert!(report.contains("No price configured for: gpt-4o"));
+    }
+}
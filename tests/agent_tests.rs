@@ -0,0 +1,264 @@
+/// Tests for the headless agent's structured report against a stub model and log backend
+mod common;
+
+use async_trait::async_trait;
+use common::{StubResponse, StubServer};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use zeteo::agent::Agent;
+use zeteo::backends::{LogBackendClient, LogEntry, LogQuery};
+use zeteo::providers::OpenAiProvider;
+use zeteo::tools::ToolExecutor;
+
+struct FixedLogs;
+
+#[async_trait]
+impl LogBackendClient for FixedLogs {
+    async fn query_logs(&self, _query: &LogQuery) -> anyhow::Result<Vec<LogEntry>> {
+        Ok(vec![LogEntry {
+            id: Some("log-42".to_string()),
+            timestamp: "2026-10-18T09:00:00Z".to_string(),
+            level: "ERROR".to_string(),
+            message: "payment-db: connection pool exhausted".to_string(),
+            service: Some("payment".to_string()),
+            trace_id: None,
+            labels: HashMap::new(),
+        }])
+    }
+
+    async fn health_check(&self) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+
+    fn backend_name(&self) -> &str {
+        "fixed"
+    }
+}
+
+fn reply(message: serde_json::Value) -> StubResponse {
+    StubResponse::json(200, json!({"choices": [{"message": message}]}))
+}
+
+fn report(evidence: &str) -> serde_json::Value {
+    json!({
+        "role": "assistant",
+        "content": json!({
+            "summary": "Checkout requests fail because payments time out.",
+            "suspected_root_cause": "payment-db connection pool exhausted",
+            "affected_services": ["checkout", "payment"],
+            "evidence_log_ids": [evidence],
+            "confidence": 0.8
+        }).to_string()
+    })
+}
+
+#[tokio::test]
+async fn test_json_report_with_repair_retry() {
+    let calls = AtomicUsize::new(0);
+    let server = StubServer::start(move |_| match calls.fetch_add(1, Ordering::SeqCst) {
+        0 => reply(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": {"name": "query_logs", "arguments": "{\"query\": \"error\"}"}
+            }]
+        })),
+        1 => reply(json!({"role": "assistant", "content": "The payment DB pool is exhausted."})),
+        // Cites a log the model never saw, so the agent asks for a repair
+        2 => reply(report("log-7")),
+        _ => reply(report("log-42")),
+    })
+    .await;
+
+    let provider = OpenAiProvider::compatible(server.url.clone(), "test-model".to_string(), None);
+    let agent = Agent::new(
+        Arc::new(provider),
+        Some(ToolExecutor::new(Arc::new(FixedLogs))),
+        Some("fixed".to_string()),
+    );
+
+    let report = agent.ask_json("Why is checkout failing?").await.unwrap();
+    assert_eq!(report.evidence_log_ids, vec!["log-42"]);
+    assert_eq!(report.affected_services, vec!["checkout", "payment"]);
+    assert_eq!(report.confidence, 0.8);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 4);
+
+    let investigate = requests[1].json();
+    let tool_result = investigate["messages"][3]["content"].as_str().unwrap();
+    assert!(tool_result.contains("\"id\": \"log-42\""));
+    assert!(investigate.get("response_format").is_none());
+
+    let structured = requests[2].json();
+    assert_eq!(structured["response_format"]["type"], "json_schema");
+    assert_eq!(structured["response_format"]["json_schema"]["strict"], true);
+    assert!(structured.get("tools").is_none());
+
+    let repair = requests[3].json();
+    let last = repair["messages"]
+        .as_array()
+        .unwrap()
+        .last()
+        .unwrap()
+        .clone();
+    assert!(last["content"].as_str().unwrap().contains("log-7"));
+}
+
+#[tokio::test]
+async fn test_plain_answer_without_backend() {
+    let server = StubServer::start(|_| {
+        reply(json!({"role": "assistant", "content": "Use structured logging."}))
+    })
+    .await;
+
+    let provider = OpenAiProvider::compatible(server.url.clone(), "test-model".to_string(), None);
+    let agent = Agent::new(Arc::new(provider), None, None);
+
+    let answer = agent.ask("How should I log?").await.unwrap();
+    assert_eq!(answer, "Use structured logging.");
+
+    let body = server.requests()[0].json();
+    assert!(body.get("tools").is_none());
+    let system = body["messages"][0]["content"].as_str().unwrap();
+    assert!(system.contains("No log backend is currently configured"));
+}
+
+/// tests/fixtures/fake_mcp_server.sh, which offers `recent_deploys`
+#[cfg(unix)]
+fn fake_mcp_server() -> zeteo::config::McpServer {
+    zeteo::config::McpServer {
+        command: "sh".to_string(),
+        args: vec![concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/fake_mcp_server.sh"
+        )
+        .to_string()],
+        env: HashMap::new(),
+        url: None,
+        headers: HashMap::new(),
+        bearer_token_env: None,
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_mcp_server_tools_are_offered_and_called() {
+    use zeteo::config::McpServer;
+    use zeteo::mcp::registry::McpRegistry;
+
+    let servers = HashMap::from([
+        ("deploys".to_string(), fake_mcp_server()),
+        (
+            "broken".to_string(),
+            McpServer {
+                command: "/nonexistent/mcp-server".to_string(),
+                args: vec![],
+                env: HashMap::new(),
+                url: None,
+                headers: HashMap::new(),
+                bearer_token_env: None,
+            },
+        ),
+    ]);
+    let (registry, errors) = McpRegistry::start(&servers).await;
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("broken"));
+
+    let calls = AtomicUsize::new(0);
+    let server = StubServer::start(move |_| match calls.fetch_add(1, Ordering::SeqCst) {
+        0 => reply(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": {
+                    "name": "deploys__recent_deploys",
+                    "arguments": "{\"service\": \"payment\"}"
+                }
+            }]
+        })),
+        _ => reply(json!({"role": "assistant", "content": "payment was deployed at 08:55."})),
+    })
+    .await;
+
+    let provider = OpenAiProvider::compatible(server.url.clone(), "test-model".to_string(), None);
+    let executor = ToolExecutor::new(Arc::new(FixedLogs)).with_mcp(Arc::new(registry));
+    let agent = Agent::new(
+        Arc::new(provider),
+        Some(executor),
+        Some("fixed".to_string()),
+    );
+
+    let answer = agent
+        .ask("Did anything ship before the errors?")
+        .await
+        .unwrap();
+    assert_eq!(answer, "payment was deployed at 08:55.");
+
+    let requests = server.requests();
+    let first = requests[0].json();
+    let names: Vec<&str> = first["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["function"]["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "query_logs",
+            "list_services",
+            "get_log_stats",
+            "deploys__recent_deploys"
+        ]
+    );
+    assert_eq!(
+        first["tools"][3]["function"]["parameters"]["required"][0],
+        "service"
+    );
+    let system = first["messages"][0]["content"].as_str().unwrap();
+    assert!(system.contains("deploys__recent_deploys"));
+
+    let tool_result = requests[1].json()["messages"][3]["content"].clone();
+    assert_eq!(tool_result, "payment v2.3.1 deployed at 08:55");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_mcp_tool_errors_reach_the_model() {
+    use zeteo::mcp::registry::McpRegistry;
+    use zeteo::providers::{FunctionCall, ToolCall};
+
+    let servers = HashMap::from([("deploys".to_string(), fake_mcp_server())]);
+    let (registry, errors) = McpRegistry::start(&servers).await;
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let executor = ToolExecutor::new(Arc::new(FixedLogs)).with_mcp(Arc::new(registry));
+    let calls = vec![ToolCall {
+        id: "call_1".to_string(),
+        call_type: "function".to_string(),
+        function: FunctionCall {
+            name: "deploys__recent_deploys".to_string(),
+            arguments: "{\"service\": \"unknown\"}".to_string(),
+        },
+    }];
+
+    let results = executor.execute_all(&calls, 1, None).await;
+    assert!(
+        results[0].starts_with("Error executing tool"),
+        "{}",
+        results[0]
+    );
+    assert!(results[0].contains("deploys"));
+    assert!(
+        results[0].contains("no deploy history for unknown"),
+        "{}",
+        results[0]
+    );
+}
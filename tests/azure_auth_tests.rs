@@ -0,0 +1,118 @@
+/// Tests for Azure OpenAI authentication with API keys and Entra ID tokens
+mod common;
+
+use common::{form_value, user_request, RecordedRequest, StubResponse, StubServer};
+use serde_json::json;
+use zeteo::providers::auth::{AzureAuth, AzureCredential};
+use zeteo::providers::{AiProvider, AzureProvider};
+
+fn respond(req: &RecordedRequest) -> StubResponse {
+    if req.path.ends_with("/oauth2/v2.0/token") {
+        StubResponse::json(
+            200,
+            json!({"token_type": "Bearer", "expires_in": 3599, "access_token": "entra-token"}),
+        )
+    } else {
+        StubResponse::json(
+            200,
+            json!({"choices": [{"message": {"role": "assistant", "content": "None."}}]}),
+        )
+    }
+}
+
+#[tokio::test]
+async fn test_client_secret_tokens_are_cached() {
+    let server = StubServer::start(respond).await;
+
+    let auth = AzureAuth::new(AzureCredential::ClientSecret {
+        tenant_id: "tenant-1".to_string(),
+        client_id: "app-1".to_string(),
+        client_secret: "s3cret".to_string(),
+    })
+    .with_authority_host(server.url.clone());
+    let provider = AzureProvider::with_entra_id(auth, server.url.clone(), "gpt-4o".to_string())
+        .with_api_version("2024-10-21".to_string());
+
+    for _ in 0..2 {
+        let response = provider.chat(user_request("Any errors?")).await.unwrap();
+        assert_eq!(response.content, "None.");
+    }
+
+    let requests = server.requests();
+    let tokens: Vec<_> = requests
+        .iter()
+        .filter(|r| r.path == "/tenant-1/oauth2/v2.0/token")
+        .collect();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(
+        form_value(&tokens[0].body, "grant_type"),
+        Some("client_credentials")
+    );
+    assert_eq!(form_value(&tokens[0].body, "client_secret"), Some("s3cret"));
+    assert_eq!(
+        form_value(&tokens[0].body, "scope"),
+        Some("https%3A%2F%2Fcognitiveservices.azure.com%2F.default")
+    );
+
+    let chats: Vec<_> = requests
+        .iter()
+        .filter(|r| r.path.starts_with("/openai/"))
+        .collect();
+    assert_eq!(chats.len(), 2);
+    assert_eq!(
+        chats[0].path,
+        "/openai/deployments/gpt-4o/chat/completions?api-version=2024-10-21"
+    );
+    assert_eq!(chats[1].header("authorization"), Some("Bearer entra-token"));
+    assert_eq!(chats[1].header("api-key"), None);
+}
+
+#[tokio::test]
+async fn test_workload_identity_sends_federated_token() {
+    let server = StubServer::start(respond).await;
+
+    let token_file =
+        std::env::temp_dir().join(format!("zeteo-federated-{}", uuid::Uuid::new_v4().simple()));
+    std::fs::write(&token_file, "federated.jwt.token\n").unwrap();
+
+    let auth = AzureAuth::new(AzureCredential::WorkloadIdentity {
+        tenant_id: "tenant-2".to_string(),
+        client_id: "app-2".to_string(),
+        token_file: token_file.clone(),
+    })
+    .with_authority_host(server.url.clone());
+    assert_eq!(auth.access_token().await.unwrap(), "entra-token");
+    std::fs::remove_file(&token_file).ok();
+
+    let body = &server.requests()[0].body;
+    assert_eq!(form_value(body, "client_id"), Some("app-2"));
+    assert_eq!(
+        form_value(body, "client_assertion_type"),
+        Some("urn%3Aietf%3Aparams%3Aoauth%3Aclient-assertion-type%3Ajwt-bearer")
+    );
+    assert_eq!(
+        form_value(body, "client_assertion"),
+        Some("federated.jwt.token")
+    );
+}
+
+#[tokio::test]
+async fn test_api_key_auth_is_unchanged() {
+    let server = StubServer::start(respond).await;
+
+    let provider = AzureProvider::new(
+        "azure-key".to_string(),
+        server.url.clone(),
+        "gpt-4o-mini".to_string(),
+    );
+    provider.chat(user_request("Any errors?")).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].path,
+        "/openai/deployments/gpt-4o-mini/chat/completions?api-version=2024-02-15-preview"
+    );
+    assert_eq!(requests[0].header("api-key"), Some("azure-key"));
+    assert_eq!(requests[0].header("authorization"), None);
+}
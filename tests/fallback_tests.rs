@@ -0,0 +1,159 @@
+/// Tests for retrying and falling back between providers against local HTTP stubs
+mod common;
+
+use common::{user_request, StubResponse, StubServer};
+use serde_json::json;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use zeteo::providers::{AiProvider, FallbackProvider, OpenAiProvider, ProviderError};
+use zeteo::retry::RetryConfig;
+
+fn answer(content: &str) -> StubResponse {
+    StubResponse::json(
+        200,
+        json!({"choices": [{"message": {"role": "assistant", "content": content}}]}),
+    )
+}
+
+fn provider(server: &StubServer) -> Arc<dyn AiProvider> {
+    Arc::new(OpenAiProvider::compatible(
+        server.url.clone(),
+        "test-model".to_string(),
+        None,
+    ))
+}
+
+fn fast_retry() -> RetryConfig {
+    RetryConfig {
+        max_retries: 3,
+        initial_delay: Duration::from_millis(10),
+        max_delay: Duration::from_secs(30),
+        multiplier: 2.0,
+    }
+}
+
+#[tokio::test]
+async fn test_retries_rate_limit_honouring_retry_after() {
+    let calls = Arc::new(AtomicU32::new(0));
+    let counter = calls.clone();
+    let server = StubServer::start(move |_| {
+        if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+            StubResponse::json(429, json!({"error": "slow down"})).with_header("Retry-After", "1")
+        } else {
+            answer("All quiet.")
+        }
+    })
+    .await;
+
+    let chain = FallbackProvider::new(vec![("primary".to_string(), provider(&server))])
+        .with_retry_config(fast_retry());
+
+    let started = std::time::Instant::now();
+    let response = chain.chat(user_request("Any errors?")).await.unwrap();
+
+    assert_eq!(response.content, "All quiet.");
+    assert_eq!(response.provider, None);
+    assert_eq!(server.requests().len(), 2);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn test_falls_back_after_persistent_server_errors() {
+    let primary =
+        StubServer::start(|_| StubResponse::json(503, json!({"error": "overloaded"}))).await;
+    let backup = StubServer::start(|_| answer("From the backup.")).await;
+
+    let chain = FallbackProvider::new(vec![
+        ("azure".to_string(), provider(&primary)),
+        ("ollama".to_string(), provider(&backup)),
+    ])
+    .with_retry_config(fast_retry());
+
+    let response = chain.chat(user_request("Any errors?")).await.unwrap();
+
+    assert_eq!(response.content, "From the backup.");
+    assert_eq!(response.provider.as_deref(), Some("ollama"));
+    assert_eq!(primary.requests().len(), 3);
+    assert_eq!(backup.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_client_errors_are_not_retried() {
+    let primary = StubServer::start(|_| StubResponse::json(401, json!({"error": "bad key"}))).await;
+    let backup =
+        StubServer::start(|_| StubResponse::json(400, json!({"error": "bad request"}))).await;
+
+    let chain = FallbackProvider::new(vec![
+        ("azure".to_string(), provider(&primary)),
+        ("openai".to_string(), provider(&backup)),
+    ])
+    .with_retry_config(fast_retry());
+
+    let err = chain
+        .chat(user_request("Any errors?"))
+        .await
+        .unwrap_err()
+        .to_string();
+
+    assert!(err.contains("azure: OpenAI-compatible API error"));
+    assert!(err.contains("openai: OpenAI-compatible API error"));
+    assert_eq!(primary.requests().len(), 1);
+    assert_eq!(backup.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_exhausted_retries_keep_the_provider_error() {
+    let server =
+        StubServer::start(|_| StubResponse::json(503, json!({"error": "overloaded"}))).await;
+
+    let chain = FallbackProvider::new(vec![("primary".to_string(), provider(&server))])
+        .with_retry_config(fast_retry());
+
+    let err = chain.chat(user_request("Any errors?")).await.unwrap_err();
+    let provider_error = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<ProviderError>())
+        .expect("ProviderError in the chain");
+    assert_eq!(provider_error.status, 503);
+    assert_eq!(server.requests().len(), 3);
+}
+
+#[tokio::test]
+async fn test_dropping_the_call_cancels_pending_retries() {
+    let server = StubServer::start(|_| {
+        StubResponse::json(503, json!({"error": "overloaded"})).with_header("Retry-After", "5")
+    })
+    .await;
+
+    let chain = FallbackProvider::new(vec![("primary".to_string(), provider(&server))])
+        .with_retry_config(fast_retry());
+
+    let result = tokio::time::timeout(
+        Duration::from_millis(300),
+        chain.chat(user_request("Any errors?")),
+    )
+    .await;
+    assert!(result.is_err());
+
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn test_zero_max_retries_makes_a_single_attempt() {
+    let server =
+        StubServer::start(|_| StubResponse::json(503, json!({"error": "overloaded"}))).await;
+
+    let profile: zeteo::config::ProviderConfig = serde_json::from_value(json!({
+        "type": "openai-compatible",
+        "base_url": server.url,
+        "model": "test-model",
+        "max_retries": 0
+    }))
+    .unwrap();
+    let provider = zeteo::providers::with_fallbacks("local", &profile, None, None).unwrap();
+
+    assert!(provider.chat(user_request("Any errors?")).await.is_err());
+    assert_eq!(server.requests().len(), 1);
+}
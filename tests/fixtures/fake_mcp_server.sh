@@ -0,0 +1,26 @@
+#!/bin/sh
+# Minimal stdio MCP server for tests: one "recent_deploys" tool that echoes
+# its service argument (or fails with a JSON-RPC error for service "unknown"),
+# and an "isError" result for any other tool.
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/^{"jsonrpc":"2.0","id":\([0-9]*\),.*/\1/p')
+  [ -z "$id" ] && continue
+  case "$line" in
+    *'"method":"initialize"'*)
+      echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"protocolVersion\":\"2024-11-05\",\"capabilities\":{\"tools\":{}},\"serverInfo\":{\"name\":\"fake\",\"version\":\"0.0.1\"}}}"
+      ;;
+    *'"method":"tools/list"'*)
+      echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"tools\":[{\"name\":\"recent_deploys\",\"description\":\"Deploys in the last day\",\"inputSchema\":{\"type\":\"object\",\"properties\":{\"service\":{\"type\":\"string\"}},\"required\":[\"service\"]}}]}}"
+      ;;
+    *'"service":"unknown"'*)
+      echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"error\":{\"code\":-32602,\"message\":\"no deploy history for unknown\"}}"
+      ;;
+    *'"name":"recent_deploys"'*)
+      service=$(printf '%s' "$line" | sed -n 's/.*"service":"\([^"]*\)".*/\1/p')
+      echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"content\":[{\"type\":\"text\",\"text\":\"$service v2.3.1 deployed at 08:55\"}]}}"
+      ;;
+    *)
+      echo "{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{\"content\":[{\"type\":\"text\",\"text\":\"no such tool\"}],\"isError\":true}}"
+      ;;
+  esac
+done
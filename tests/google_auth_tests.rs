@@ -0,0 +1,139 @@
+/// Tests for Google credential exchange against a local token endpoint
+mod common;
+
+use common::{form_value, StubResponse, StubServer};
+use serde_json::json;
+use std::path::PathBuf;
+use zeteo::providers::auth::GoogleAuth;
+
+const PRIVATE_KEY: &str = include_str!("fixtures/test_service_account.pem");
+const PUBLIC_KEY: &str = include_str!("fixtures/test_service_account.pub.pem");
+
+fn write_credentials(name: &str, credentials: serde_json::Value) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "zeteo-{}-{}.json",
+        name,
+        uuid::Uuid::new_v4().simple()
+    ));
+    std::fs::write(&path, credentials.to_string()).unwrap();
+    path
+}
+
+#[derive(serde::Deserialize)]
+struct Claims {
+    iss: String,
+    scope: String,
+    aud: String,
+}
+
+#[tokio::test]
+async fn test_service_account_jwt_exchange_is_cached() {
+    let server = StubServer::start(|_| {
+        StubResponse::json(
+            200,
+            json!({"access_token": "ya29.test", "expires_in": 3599, "token_type": "Bearer"}),
+        )
+    })
+    .await;
+
+    let token_uri = format!("{}/token", server.url);
+    let path = write_credentials(
+        "sa",
+        json!({
+            "type": "service_account",
+            "project_id": "demo",
+            "private_key_id": "key-1",
+            "private_key": PRIVATE_KEY,
+            "client_email": "zeteo@demo.iam.gserviceaccount.com",
+            "token_uri": token_uri,
+        }),
+    );
+
+    let auth = GoogleAuth::from_file(&path);
+    assert_eq!(auth.access_token().await.unwrap(), "ya29.test");
+    assert_eq!(auth.access_token().await.unwrap(), "ya29.test");
+    std::fs::remove_file(&path).ok();
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/token");
+    assert_eq!(
+        form_value(&requests[0].body, "grant_type"),
+        Some("urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer")
+    );
+
+    let assertion = form_value(&requests[0].body, "assertion").unwrap();
+    let mut validation = jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::RS256);
+    validation.set_audience(&[token_uri.as_str()]);
+    let decoded = jsonwebtoken::decode::<Claims>(
+        assertion,
+        &jsonwebtoken::DecodingKey::from_rsa_pem(PUBLIC_KEY.as_bytes()).unwrap(),
+        &validation,
+    )
+    .unwrap();
+
+    assert_eq!(decoded.header.kid.as_deref(), Some("key-1"));
+    assert_eq!(decoded.claims.iss, "zeteo@demo.iam.gserviceaccount.com");
+    assert_eq!(
+        decoded.claims.scope,
+        "https://www.googleapis.com/auth/cloud-platform"
+    );
+    assert_eq!(decoded.claims.aud, token_uri);
+}
+
+#[tokio::test]
+async fn test_authorized_user_refresh() {
+    let server = StubServer::start(|_| {
+        StubResponse::json(
+            200,
+            json!({"access_token": "ya29.user", "expires_in": 3600}),
+        )
+    })
+    .await;
+
+    let path = write_credentials(
+        "user",
+        json!({
+            "type": "authorized_user",
+            "client_id": "client.apps.googleusercontent.com",
+            "client_secret": "s3cret",
+            "refresh_token": "1//refresh",
+            "token_uri": format!("{}/token", server.url),
+        }),
+    );
+
+    let auth = GoogleAuth::from_file(&path);
+    assert_eq!(auth.access_token().await.unwrap(), "ya29.user");
+    std::fs::remove_file(&path).ok();
+
+    let body = &server.requests()[0].body;
+    assert_eq!(form_value(body, "grant_type"), Some("refresh_token"));
+    assert_eq!(form_value(body, "refresh_token"), Some("1%2F%2Frefresh"));
+    assert_eq!(form_value(body, "client_secret"), Some("s3cret"));
+}
+
+#[tokio::test]
+async fn test_token_endpoint_errors_are_reported() {
+    let server =
+        StubServer::start(|_| StubResponse::json(400, json!({"error": "invalid_grant"}))).await;
+
+    let path = write_credentials(
+        "bad",
+        json!({
+            "type": "authorized_user",
+            "client_id": "id",
+            "client_secret": "secret",
+            "refresh_token": "revoked",
+            "token_uri": format!("{}/token", server.url),
+        }),
+    );
+
+    let err = GoogleAuth::from_file(&path)
+        .access_token()
+        .await
+        .unwrap_err()
+        .to_string();
+    std::fs::remove_file(&path).ok();
+
+    assert!(err.contains("invalid_grant"));
+}
@@ -0,0 +1,423 @@
+/// Tests for the async MCP client against a scripted mock server.
+///
+/// The mock server is the `mock_mcp_server` test below: the other tests start
+/// this test binary again with MOCK_MCP_SERVER set, running just that test,
+/// which then speaks MCP on stdin/stdout.
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use zeteo::config::McpServer;
+use zeteo::mcp::registry::ServerState;
+use zeteo::mcp::registry::{McpRegistry, ResourceUpdate};
+use zeteo::mcp::supervisor::Supervision;
+use zeteo::mcp::McpClient;
+
+const MOCK_ENV: &str = "MOCK_MCP_SERVER";
+/// Path of a marker file: while it doesn't exist, the mock server creates it
+/// and exits right after startup
+const CRASH_ONCE_ENV: &str = "MOCK_MCP_CRASH_ONCE";
+/// When set, the mock server complains on stderr and exits on `initialize`
+const FAIL_INIT_ENV: &str = "MOCK_MCP_FAIL_INIT";
+
+fn tool_result(text: &str) -> Value {
+    json!({"content": [{"type": "text", "text": text}]})
+}
+
+#[test]
+fn mock_mcp_server() {
+    if std::env::var_os(MOCK_ENV).is_none() {
+        return;
+    }
+
+    eprintln!("mock server ready");
+    let stdout = Arc::new(Mutex::new(std::io::stdout()));
+    let write = {
+        let stdout = stdout.clone();
+        move |message: Value| {
+            let mut out = stdout.lock().unwrap();
+            writeln!(out, "{}", message).unwrap();
+            out.flush().unwrap();
+        }
+    };
+    let respond = |id: &Value, result: Value| {
+        write(json!({"jsonrpc": "2.0", "id": id, "result": result}));
+    };
+    // ID of the ping_me call waiting for the client to answer our ping
+    let mut waiting_for_pong = None;
+
+    for line in std::io::stdin().lock().lines() {
+        let message: Value = serde_json::from_str(&line.unwrap()).unwrap();
+        let id = &message["id"];
+
+        match message["method"].as_str() {
+            Some("initialize") if std::env::var_os(FAIL_INIT_ENV).is_some() => {
+                eprintln!("invalid API token");
+                std::process::exit(1);
+            }
+            Some("initialize") => respond(
+                id,
+                json!({
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {
+                        "tools": {},
+                        "resources": {"subscribe": true},
+                        "prompts": {}
+                    },
+                    "serverInfo": {"name": "mock", "version": "0.0.1"}
+                }),
+            ),
+            Some("tools/list") => respond(
+                id,
+                json!({"tools": [{"name": "slow"}, {"name": "fast"}, {"name": "progress"}]}),
+            ),
+            // Two pages, to exercise nextCursor
+            Some("resources/list") => match message["params"]["cursor"].as_str() {
+                None => respond(
+                    id,
+                    json!({
+                        "resources": [{"uri": "runbook://checkout", "name": "Checkout runbook"}],
+                        "nextCursor": "page-2"
+                    }),
+                ),
+                Some(_) => respond(
+                    id,
+                    json!({"resources": [{
+                        "uri": "owners://services",
+                        "name": "Service owners",
+                        "mimeType": "text/csv"
+                    }]}),
+                ),
+            },
+            Some("resources/read") => {
+                let uri = message["params"]["uri"].as_str().unwrap();
+                respond(
+                    id,
+                    json!({"contents": [{"uri": uri, "mimeType": "text/markdown", "text": format!("contents of {}", uri)}]}),
+                );
+            }
+            Some("resources/subscribe") => {
+                respond(id, json!({}));
+                write(json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/resources/updated",
+                    "params": {"uri": message["params"]["uri"]}
+                }));
+            }
+            Some("prompts/list") => {
+                respond(
+                    id,
+                    json!({"prompts": [{
+                        "name": "triage",
+                        "description": "Triage an incident",
+                        "arguments": [{"name": "service", "required": true}]
+                    }]}),
+                );
+                if let Some(marker) = std::env::var_os(CRASH_ONCE_ENV) {
+                    if std::fs::metadata(&marker).is_err() {
+                        std::fs::write(&marker, "").unwrap();
+                        eprintln!("crashing on purpose");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Some("prompts/get") => {
+                let service = message["params"]["arguments"]["service"].as_str().unwrap();
+                respond(
+                    id,
+                    json!({"messages": [
+                        {"role": "user", "content": {"type": "text", "text": format!("Triage {}", service)}},
+                        {"role": "user", "content": {"type": "resource", "resource": {"uri": "runbook://checkout", "text": "Step 1: check the logs"}}}
+                    ]}),
+                );
+            }
+            Some("tools/call") => match message["params"]["name"].as_str().unwrap() {
+                "slow" => {
+                    let write = write.clone();
+                    let id = id.clone();
+                    std::thread::spawn(move || {
+                        std::thread::sleep(Duration::from_millis(300));
+                        write(json!({"jsonrpc": "2.0", "id": id, "result": tool_result("slow")}));
+                    });
+                }
+                "fast" => respond(id, tool_result("fast")),
+                "progress" => {
+                    let token = &message["params"]["_meta"]["progressToken"];
+                    for step in 1..=2 {
+                        write(json!({
+                            "jsonrpc": "2.0",
+                            "method": "notifications/progress",
+                            "params": {"progressToken": token, "progress": step, "total": 2}
+                        }));
+                    }
+                    respond(id, tool_result("done"));
+                }
+                "ping_me" => {
+                    waiting_for_pong = Some(id.clone());
+                    write(json!({"jsonrpc": "2.0", "id": "srv-1", "method": "ping"}));
+                }
+                "exit" => std::process::exit(0),
+                // "hang" never answers
+                _ => {}
+            },
+            Some("notifications/cancelled") => write(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/message",
+                "params": {"data": format!("cancelled {}", message["params"]["requestId"])}
+            })),
+            None if id == "srv-1" && message.get("result").is_some() => {
+                if let Some(call) = waiting_for_pong.take() {
+                    respond(&call, tool_result("pong received"));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn mock_server() -> McpServer {
+    McpServer {
+        command: std::env::current_exe()
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string(),
+        args: ["mock_mcp_server", "--exact", "--nocapture", "-q"]
+            .map(String::from)
+            .to_vec(),
+        env: HashMap::from([(MOCK_ENV.to_string(), "1".to_string())]),
+        url: None,
+        headers: HashMap::new(),
+        bearer_token_env: None,
+    }
+}
+
+async fn mock_client() -> McpClient {
+    let client = McpClient::connect("mock", &mock_server()).unwrap();
+    client.initialize().await.unwrap();
+    client
+}
+
+fn text(result: &Value) -> &str {
+    result["content"][0]["text"].as_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_concurrent_calls_are_matched_by_id() {
+    let client = mock_client().await;
+    let start = Instant::now();
+
+    let timed = |name: &'static str| {
+        let client = &client;
+        async move {
+            let result = client.call_tool(name, json!({})).await.unwrap();
+            (text(&result).to_string(), start.elapsed())
+        }
+    };
+    let ((slow, slow_at), (fast, fast_at)) = tokio::join!(timed("slow"), timed("fast"));
+
+    assert_eq!(slow, "slow");
+    assert_eq!(fast, "fast");
+    assert!(fast_at < slow_at);
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools.len(), 3);
+}
+
+#[tokio::test]
+async fn test_progress_is_forwarded() {
+    let client = mock_client().await;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let result = client
+        .call_tool_with_progress("progress", json!({}), tx)
+        .await
+        .unwrap();
+    assert_eq!(text(&result), "done");
+
+    let mut updates = Vec::new();
+    while let Ok(update) = rx.try_recv() {
+        updates.push((update.progress, update.total));
+    }
+    assert_eq!(updates, vec![(1.0, Some(2.0)), (2.0, Some(2.0))]);
+}
+
+#[tokio::test]
+async fn test_timeout_cancels_request() {
+    let client = mock_client()
+        .await
+        .with_request_timeout(Duration::from_millis(200));
+    let mut notifications = client.notifications();
+
+    let err = client.call_tool("hang", json!({})).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("timed out"));
+
+    let notification = tokio::time::timeout(Duration::from_secs(5), notifications.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(notification.method, "notifications/message");
+    // initialize was request 1
+    assert_eq!(notification.params.unwrap()["data"], "cancelled 2");
+}
+
+#[tokio::test]
+async fn test_server_ping_and_stderr() {
+    let client = mock_client().await;
+
+    let result = client.call_tool("ping_me", json!({})).await.unwrap();
+    assert_eq!(text(&result), "pong received");
+    assert!(client
+        .stderr_tail()
+        .iter()
+        .any(|line| line == "mock server ready"));
+}
+
+#[tokio::test]
+async fn test_server_exit_fails_pending_requests() {
+    let client = mock_client().await;
+
+    let err = client.call_tool("exit", json!({})).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("exited"));
+    assert!(client.call_tool("fast", json!({})).await.is_err());
+}
+
+#[tokio::test]
+async fn test_resources_and_prompts() {
+    let client = mock_client().await;
+    assert!(client.supports("prompts"));
+    assert!(client.supports_resource_subscriptions());
+
+    let resources = client.list_resources().await.unwrap();
+    let uris: Vec<&str> = resources.iter().map(|r| r.uri.as_str()).collect();
+    assert_eq!(uris, vec!["runbook://checkout", "owners://services"]);
+    assert_eq!(resources[1].mime_type.as_deref(), Some("text/csv"));
+
+    let contents = client.read_resource("owners://services").await.unwrap();
+    assert_eq!(
+        contents[0].text.as_deref(),
+        Some("contents of owners://services")
+    );
+
+    let prompts = client.list_prompts().await.unwrap();
+    assert_eq!(prompts[0].name, "triage");
+    assert!(prompts[0].arguments[0].required);
+
+    let arguments = HashMap::from([("service".to_string(), "checkout".to_string())]);
+    let messages = client.get_prompt("triage", &arguments).await.unwrap();
+    assert_eq!(messages[0].text(), "Triage checkout");
+    assert_eq!(messages[1].text(), "Step 1: check the logs");
+}
+
+#[tokio::test]
+async fn test_registry_prompts_and_resource_updates() {
+    let servers = HashMap::from([("ops".to_string(), mock_server())]);
+    let (registry, errors) = McpRegistry::start(&servers).await;
+    assert!(errors.is_empty(), "{:?}", errors);
+
+    let commands: Vec<String> = registry.prompts().into_iter().map(|p| p.command).collect();
+    assert_eq!(commands, vec!["ops:triage"]);
+    let messages = registry
+        .expand_prompt("ops:triage", "payments")
+        .await
+        .unwrap();
+    assert_eq!(messages[0].text(), "Triage payments");
+    assert!(registry.expand_prompt("ops:triage", "").await.is_err());
+
+    let (resources, errors) = registry.list_resources().await;
+    assert!(errors.is_empty());
+    assert_eq!(resources.len(), 2);
+    assert_eq!(resources[0].server, "ops");
+    assert_eq!(
+        registry
+            .read_resource("ops", "runbook://checkout")
+            .await
+            .unwrap(),
+        "contents of runbook://checkout"
+    );
+
+    let mut updates = registry.resource_updates();
+    assert!(registry
+        .subscribe("ops", "runbook://checkout")
+        .await
+        .unwrap());
+    let update = tokio::time::timeout(Duration::from_secs(5), updates.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        update,
+        ResourceUpdate {
+            server: "ops".to_string(),
+            uri: "runbook://checkout".to_string()
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_startup_failure_keeps_stderr() {
+    let mut server = mock_server();
+    server
+        .env
+        .insert(FAIL_INIT_ENV.to_string(), "1".to_string());
+
+    let servers = HashMap::from([("ops".to_string(), server)]);
+    let (registry, errors) = McpRegistry::start(&servers).await;
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("invalid API token"), "{:?}", errors);
+    let status = &registry.status()[0];
+    assert!(status.last_error.is_some());
+    assert!(status.stderr.iter().any(|l| l == "invalid API token"));
+}
+
+/// Wait for the supervised server to have been restarted `restarts` times
+async fn wait_for_restarts(registry: &McpRegistry, restarts: u32) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let status = &registry.status()[0];
+        if status.restarts == restarts && status.state == ServerState::Running {
+            return;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "server not restarted: {:?}",
+            status
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+#[tokio::test]
+async fn test_supervisor_restarts_crashed_servers() {
+    let marker = std::env::temp_dir().join(format!("zeteo-mcp-crash-{}", std::process::id()));
+    let _ = std::fs::remove_file(&marker);
+    let mut server = mock_server();
+    server.env.insert(
+        CRASH_ONCE_ENV.to_string(),
+        marker.to_str().unwrap().to_string(),
+    );
+
+    let servers = HashMap::from([("ops".to_string(), server)]);
+    let (registry, errors) = McpRegistry::start(&servers).await;
+    assert!(errors.is_empty(), "{:?}", errors);
+    let registry = Arc::new(registry);
+    registry.supervise(Supervision {
+        check_interval: Duration::from_millis(20),
+        initial_backoff: Duration::from_millis(50),
+        ..Supervision::default()
+    });
+
+    wait_for_restarts(&registry, 1).await;
+    let status = &registry.status()[0];
+    assert_eq!(status.tools, 3);
+    assert_eq!(status.prompts, 1);
+    assert_eq!(status.last_error.as_deref(), Some("server exited"));
+    assert!(status.stderr.iter().any(|l| l == "crashing on purpose"));
+    assert_eq!(registry.call("ops__fast", "{}").await.unwrap(), "fast");
+
+    registry.restart("ops").unwrap();
+    wait_for_restarts(&registry, 2).await;
+    assert_eq!(registry.call("ops__fast", "{}").await.unwrap(), "fast");
+    let _ = std::fs::remove_file(&marker);
+}
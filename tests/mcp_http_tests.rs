@@ -0,0 +1,219 @@
+/// Tests for remote MCP servers over Streamable HTTP and the legacy HTTP+SSE transport
+mod common;
+
+use common::{read_request, StubResponse, StubServer};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use zeteo::config::McpServer;
+use zeteo::mcp::McpClient;
+
+/// The mock server's answer to a request, or `None` for a notification
+fn answer(message: &Value) -> Option<Value> {
+    let result = match message["method"].as_str()? {
+        "initialize" => json!({
+            "protocolVersion": "2024-11-05",
+            "capabilities": {"tools": {}},
+            "serverInfo": {"name": "remote", "version": "0.0.1"}
+        }),
+        "tools/list" => json!({"tools": [{"name": "feature_flags", "description": "Flag states"}]}),
+        "tools/call" => json!({"content": [{"type": "text", "text": "checkout_v2: on"}]}),
+        _ => return None,
+    };
+    let id = message.get("id")?;
+    Some(json!({"jsonrpc": "2.0", "id": id, "result": result}))
+}
+
+fn remote(url: String) -> McpServer {
+    McpServer {
+        command: String::new(),
+        args: Vec::new(),
+        env: HashMap::new(),
+        url: Some(url),
+        headers: HashMap::from([("X-Team".to_string(), "platform".to_string())]),
+        bearer_token_env: Some("ZETEO_TEST_MCP_TOKEN".to_string()),
+    }
+}
+
+#[tokio::test]
+async fn test_streamable_http_session_and_auth() {
+    std::env::set_var("ZETEO_TEST_MCP_TOKEN", "t0ken");
+    let server = StubServer::start(|req| {
+        if req.method == "DELETE" {
+            return StubResponse::accepted();
+        }
+        let message = req.json();
+        let Some(response) = answer(&message) else {
+            return StubResponse::accepted();
+        };
+        match message["method"].as_str().unwrap() {
+            "initialize" => StubResponse::json(200, response).with_header("Mcp-Session-Id", "sess-1"),
+            // Answered as a stream, with a log notification ahead of the result
+            "tools/list" => StubResponse {
+                status: 200,
+                headers: vec![("Content-Type".to_string(), "text/event-stream".to_string())],
+                body: format!(
+                    "event: message\ndata: {}\n\nevent: message\ndata: {}\n\n",
+                    json!({"jsonrpc": "2.0", "method": "notifications/message", "params": {"data": "listing"}}),
+                    response
+                ),
+            },
+            _ => StubResponse::json(200, response),
+        }
+    })
+    .await;
+
+    let client = McpClient::connect("flags", &remote(format!("{}/mcp", server.url))).unwrap();
+    let mut notifications = client.notifications();
+    client.initialize().await.unwrap();
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(tools[0].name, "feature_flags");
+    let notification = notifications.try_recv().unwrap();
+    assert_eq!(notification.params.unwrap()["data"], "listing");
+
+    let result = client.call_tool("feature_flags", json!({})).await.unwrap();
+    assert_eq!(result["content"][0]["text"], "checkout_v2: on");
+
+    drop(client);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    let requests = server.requests();
+    assert!(requests.iter().all(|r| r.path == "/mcp"));
+    assert!(requests
+        .iter()
+        .all(|r| r.header("authorization") == Some("Bearer t0ken")
+            && r.header("x-team") == Some("platform")));
+
+    let (first, rest) = requests.split_first().unwrap();
+    assert_eq!(first.json()["method"], "initialize");
+    assert_eq!(rest[0].json()["method"], "notifications/initialized");
+    assert_eq!(first.header("mcp-session-id"), None);
+    assert_eq!(
+        first.header("accept"),
+        Some("application/json, text/event-stream")
+    );
+    assert!(rest
+        .iter()
+        .all(|r| r.header("mcp-session-id") == Some("sess-1")));
+    assert_eq!(requests.last().unwrap().method, "DELETE");
+}
+
+#[tokio::test]
+async fn test_http_errors_fail_the_request() {
+    std::env::set_var("ZETEO_TEST_MCP_TOKEN", "t0ken");
+    let server = StubServer::start(|req| {
+        let message = req.json();
+        if message["method"] == "tools/call" {
+            return StubResponse::json(500, json!({"error": "flag store unavailable"}));
+        }
+        answer(&message).map_or_else(StubResponse::accepted, |r| StubResponse::json(200, r))
+    })
+    .await;
+
+    let client = McpClient::connect("flags", &remote(server.url.clone())).unwrap();
+    client.initialize().await.unwrap();
+
+    let err = client
+        .call_tool("feature_flags", json!({}))
+        .await
+        .unwrap_err();
+    let err = format!("{:#}", err);
+    assert!(err.contains("500"), "{}", err);
+    assert!(err.contains("flag store unavailable"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_failed_initialize_closes_the_connection() {
+    std::env::set_var("ZETEO_TEST_MCP_TOKEN", "t0ken");
+    let server = StubServer::start(|req| {
+        let message = req.json();
+        if message["method"] == "initialize" {
+            return StubResponse::json(500, json!({"error": "starting up"}));
+        }
+        answer(&message).map_or_else(StubResponse::accepted, |r| StubResponse::json(200, r))
+    })
+    .await;
+
+    let client = McpClient::connect("flags", &remote(server.url.clone())).unwrap();
+    assert!(client.initialize().await.is_err());
+    // Not sent as a second attempt at picking the transport
+    assert!(client.list_tools().await.is_err());
+    assert_eq!(server.requests().len(), 1);
+}
+
+/// A server that only speaks the 2024-11-05 HTTP+SSE transport: POSTs to the
+/// base URL are refused with `rejection`, GET opens the event stream
+async fn start_legacy_server(rejection: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/sse", listener.local_addr().unwrap());
+    let (events, events_rx) = mpsc::unbounded_channel::<Value>();
+    let mut events_rx = Some(events_rx);
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut stream, _)) = listener.accept().await else {
+                break;
+            };
+            let Some(request) = read_request(&mut stream).await else {
+                continue;
+            };
+
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/sse") => {
+                    let Some(mut rx) = events_rx.take() else {
+                        continue;
+                    };
+                    tokio::spawn(async move {
+                        let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n";
+                        stream.write_all(head.as_bytes()).await.unwrap();
+                        stream
+                            .write_all(b"event: endpoint\ndata: /messages?sessionId=abc\n\n")
+                            .await
+                            .unwrap();
+                        while let Some(message) = rx.recv().await {
+                            let event = format!("event: message\ndata: {}\n\n", message);
+                            if stream.write_all(event.as_bytes()).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                }
+                ("POST", "/messages?sessionId=abc") => {
+                    if let Some(response) = answer(&request.json()) {
+                        events.send(response).unwrap();
+                    }
+                    let _ = stream
+                        .write_all(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n")
+                        .await;
+                }
+                _ => {
+                    let response = format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", rejection);
+                    let _ = stream.write_all(response.as_bytes()).await;
+                }
+            }
+        }
+    });
+
+    url
+}
+
+#[tokio::test]
+async fn test_falls_back_to_sse_transport() {
+    for rejection in ["405 Method Not Allowed", "400 Bad Request"] {
+        let url = start_legacy_server(rejection).await;
+        let mut server = remote(url);
+        server.bearer_token_env = None;
+
+        let client = McpClient::connect("legacy", &server).unwrap();
+        client.initialize().await.unwrap();
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools[0].name, "feature_flags");
+        let result = client.call_tool("feature_flags", json!({})).await.unwrap();
+        assert_eq!(result["content"][0]["text"], "checkout_v2: on");
+    }
+}
@@ -174,12 +174,20 @@ fn test_config_serialization() {
                 env.insert("TEST_VAR".to_string(), "test_value".to_string());
                 env
             },
+            url: None,
+            headers: std::collections::HashMap::new(),
+            bearer_token_env: None,
         },
     );
 
     let config = zeteo::config::Config {
         servers,
         backends: std::collections::HashMap::new(),
+        providers: std::collections::HashMap::new(),
+        default_provider: None,
+        pricing: std::collections::HashMap::new(),
+        tool_concurrency: None,
+        sessions: None,
     };
     let json = serde_json::to_string(&config).unwrap();
 
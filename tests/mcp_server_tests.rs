@@ -0,0 +1,174 @@
+/// Tests for `zeteo mcp-serve`, driven by zeteo's own MCP client over an
+/// in-memory pipe
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio::sync::mpsc;
+use zeteo::backends::{LogBackendClient, LogEntry, LogQuery};
+use zeteo::mcp::transport::Transport;
+use zeteo::mcp::McpClient;
+use zeteo::tools::ToolExecutor;
+
+#[derive(Default)]
+struct Logs {
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Sets its flag when dropped, i.e. when the query future is aborted
+struct SetOnDrop(Arc<AtomicBool>);
+
+impl Drop for SetOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl LogBackendClient for Logs {
+    async fn query_logs(&self, query: &LogQuery) -> Result<Vec<LogEntry>> {
+        match query.query.as_str() {
+            "hang" => {
+                let _guard = SetOnDrop(self.cancelled.clone());
+                std::future::pending::<()>().await;
+                unreachable!()
+            }
+            "broken" => anyhow::bail!("index not found"),
+            _ => Ok(vec![LogEntry {
+                id: Some("doc-1".to_string()),
+                timestamp: "2026-10-18T09:00:00Z".to_string(),
+                level: "ERROR".to_string(),
+                message: "payment timeout".to_string(),
+                service: Some("payment".to_string()),
+                trace_id: None,
+                labels: HashMap::new(),
+            }]),
+        }
+    }
+
+    async fn health_check(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn backend_name(&self) -> &str {
+        "logs"
+    }
+}
+
+struct DuplexTransport(Option<DuplexStream>);
+
+impl Transport for DuplexTransport {
+    fn start(
+        &mut self,
+        mut outgoing: mpsc::UnboundedReceiver<String>,
+        incoming: mpsc::UnboundedSender<String>,
+    ) -> Result<()> {
+        let (read, mut write) = tokio::io::split(self.0.take().unwrap());
+        tokio::spawn(async move {
+            while let Some(line) = outgoing.recv().await {
+                write
+                    .write_all(format!("{}\n", line).as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let _ = incoming.send(line);
+            }
+        });
+        Ok(())
+    }
+
+    fn is_alive(&mut self) -> bool {
+        true
+    }
+
+    fn close(&mut self) {}
+}
+
+async fn connect(backend: Arc<Logs>) -> McpClient {
+    let (client_end, server_end) = tokio::io::duplex(64 * 1024);
+    let (input, output) = tokio::io::split(server_end);
+    tokio::spawn(zeteo::mcp::server::serve(
+        ToolExecutor::new(backend),
+        input,
+        output,
+    ));
+
+    let client =
+        McpClient::with_transport(Box::new(DuplexTransport(Some(client_end))), "zeteo".into())
+            .unwrap();
+    let info = client.initialize().await.unwrap();
+    assert_eq!(info["serverInfo"]["name"], "zeteo");
+    assert_eq!(info["protocolVersion"], "2024-11-05");
+    client
+}
+
+#[tokio::test]
+async fn test_lists_and_calls_log_tools() {
+    let client = connect(Arc::default()).await;
+
+    let tools = client.list_tools().await.unwrap();
+    let names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["query_logs", "list_services", "get_log_stats"]);
+    assert_eq!(
+        tools[0].input_schema.as_ref().unwrap()["required"][0],
+        "query"
+    );
+
+    let result = client
+        .call_tool("query_logs", json!({"query": "timeout", "level": "ERROR"}))
+        .await
+        .unwrap();
+    assert_eq!(result["isError"], false);
+    let text = result["content"][0]["text"].as_str().unwrap();
+    let parsed: serde_json::Value = serde_json::from_str(text).unwrap();
+    assert_eq!(parsed["logs"][0]["id"], "doc-1");
+    assert_eq!(parsed["services"][0], "payment");
+}
+
+#[tokio::test]
+async fn test_tool_failures_and_unknown_tools() {
+    let client = connect(Arc::default()).await;
+
+    let result = client
+        .call_tool("query_logs", json!({"query": "broken"}))
+        .await
+        .unwrap();
+    assert_eq!(result["isError"], true);
+    assert!(result["content"][0]["text"]
+        .as_str()
+        .unwrap()
+        .contains("index not found"));
+
+    let err = client
+        .call_tool("drop_tables", json!({}))
+        .await
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("Unknown tool: drop_tables"));
+
+    let err = client.request("resources/list", None).await.unwrap_err();
+    assert!(format!("{:#}", err).contains("Method not found"));
+}
+
+#[tokio::test]
+async fn test_cancelled_call_stops_the_query() {
+    let backend = Arc::new(Logs::default());
+    let client = connect(backend.clone())
+        .await
+        .with_request_timeout(Duration::from_millis(100));
+
+    assert!(client
+        .call_tool("query_logs", json!({"query": "hang"}))
+        .await
+        .is_err());
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(backend.cancelled.load(Ordering::SeqCst));
+}
@@ -0,0 +1,136 @@
+/// Tests for Ollama capability detection and the prompt-based tool fallback
+mod common;
+
+use common::{user_request, StubResponse, StubServer};
+use serde_json::json;
+use std::sync::Arc;
+use zeteo::providers::{
+    create_log_tools, AiProvider, ChatRequest, Message, OllamaProvider, PromptToolsProvider,
+};
+
+fn request_with_tools() -> ChatRequest {
+    let mut request = ChatRequest {
+        temperature: Some(0.2),
+        max_tokens: Some(512),
+        tools: Some(create_log_tools()),
+        ..user_request("Any timeouts?")
+    };
+    request.messages.insert(
+        0,
+        Message {
+            role: "system".to_string(),
+            content: "You are a log analyst.".to_string(),
+            tool_calls: None,
+            tool_call_id: None,
+        },
+    );
+    request
+}
+
+fn ollama(server: &StubServer, model: &str) -> PromptToolsProvider {
+    PromptToolsProvider::new(Arc::new(OllamaProvider::new(
+        Some(model.to_string()),
+        Some(server.url.clone()),
+    )))
+}
+
+#[tokio::test]
+async fn test_native_tool_calls_without_ids() {
+    let server = StubServer::start(|req| match req.path.as_str() {
+        "/api/show" => StubResponse::json(
+            200,
+            json!({
+                "capabilities": ["completion", "tools"],
+                "model_info": {"llama.context_length": 131072}
+            }),
+        ),
+        _ => StubResponse::json(
+            200,
+            json!({
+                "message": {
+                    "role": "assistant",
+                    "content": "",
+                    "tool_calls": [{
+                        "function": {"name": "query_logs", "arguments": {"query": "timeout"}}
+                    }]
+                },
+                "prompt_eval_count": 300,
+                "eval_count": 20
+            }),
+        ),
+    })
+    .await;
+
+    let provider = ollama(&server, "llama3.1");
+    let capabilities = provider.capabilities().await.unwrap();
+    assert!(capabilities.tools);
+    assert_eq!(capabilities.context_length, Some(131072));
+
+    let response = provider.chat(request_with_tools()).await.unwrap();
+    let tool_calls = response.tool_calls.unwrap();
+    assert!(tool_calls[0].id.starts_with("call_"));
+    assert_eq!(tool_calls[0].function.arguments, r#"{"query":"timeout"}"#);
+
+    // Generated IDs don't repeat across turns
+    let next = provider.chat(request_with_tools()).await.unwrap();
+    assert_ne!(next.tool_calls.unwrap()[0].id, tool_calls[0].id);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 3);
+    let body = requests[1].json();
+    assert_eq!(body["tools"][0]["function"]["name"], "query_logs");
+    assert_eq!(body["options"]["num_predict"], 512);
+}
+
+#[tokio::test]
+async fn test_prompt_protocol_for_models_without_tools() {
+    let server = StubServer::start(|req| match req.path.as_str() {
+        "/api/show" => StubResponse::json(
+            200,
+            json!({"capabilities": ["completion"], "model_info": {}}),
+        ),
+        _ => StubResponse::json(
+            200,
+            json!({
+                "message": {
+                    "role": "assistant",
+                    "content": "```json\n{\"tool\": \"query_logs\", \"arguments\": {\"query\": \"timeout\"}}\n```"
+                }
+            }),
+        ),
+    })
+    .await;
+
+    let provider = ollama(&server, "gemma2:2b");
+    let response = provider.chat(request_with_tools()).await.unwrap();
+
+    assert!(response.content.is_empty());
+    let tool_calls = response.tool_calls.unwrap();
+    assert_eq!(tool_calls[0].function.name, "query_logs");
+    assert_eq!(tool_calls[0].function.arguments, r#"{"query":"timeout"}"#);
+
+    let requests = server.requests();
+    let body = requests.last().unwrap().json();
+    assert!(body.get("tools").is_none());
+    let system = body["messages"][0]["content"].as_str().unwrap();
+    assert!(system.starts_with("You are a log analyst."));
+    assert!(system.contains("### query_logs"));
+}
+
+#[tokio::test]
+async fn test_older_servers_detect_tools_from_template() {
+    let server = StubServer::start(|_| {
+        StubResponse::json(
+            200,
+            json!({"template": "{{ if .Tools }}{{ .Tools }}{{ end }}{{ .Prompt }}"}),
+        )
+    })
+    .await;
+
+    let provider = OllamaProvider::new(Some("mistral".to_string()), Some(server.url.clone()));
+    let capabilities = provider.capabilities().await.unwrap();
+
+    assert!(capabilities.tools);
+    assert_eq!(capabilities.context_length, None);
+    assert_eq!(server.requests()[0].json()["model"], "mistral");
+}
@@ -0,0 +1,149 @@
+/// Tests for the OpenAI-compatible provider against a local chat-completions stub
+mod common;
+
+use common::{user_request, StubResponse, StubServer};
+use serde_json::json;
+use std::collections::HashMap;
+use zeteo::providers::{AiProvider, ChatRequest, OpenAiProvider};
+
+#[tokio::test]
+async fn test_chat_against_compatible_server() {
+    let server = StubServer::start(|_| {
+        StubResponse::json(
+            200,
+            json!({
+                "choices": [{
+                    "message": {"role": "assistant", "content": "All quiet."}
+                }],
+                "usage": {
+                    "prompt_tokens": 120,
+                    "completion_tokens": 8,
+                    "prompt_tokens_details": {"cached_tokens": 64}
+                }
+            }),
+        )
+    })
+    .await;
+
+    let provider = OpenAiProvider::compatible(
+        format!("{}/v1", server.url),
+        "llama-3.1-8b".to_string(),
+        Some("secret".to_string()),
+    );
+
+    let request = ChatRequest {
+        temperature: Some(0.2),
+        max_tokens: Some(256),
+        tools: Some(zeteo::providers::create_log_tools()),
+        ..user_request("Any errors?")
+    };
+    let response = provider.chat(request).await.unwrap();
+    assert_eq!(response.content, "All quiet.");
+    assert_eq!(response.model, "llama-3.1-8b");
+    assert_eq!(provider.provider_name(), "OpenAI-compatible");
+
+    let usage = response.usage.unwrap();
+    assert_eq!(usage.prompt_tokens, 120);
+    assert_eq!(usage.completion_tokens, 8);
+    assert_eq!(usage.cached_tokens, 64);
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/v1/chat/completions");
+    assert_eq!(requests[0].header("authorization"), Some("Bearer secret"));
+
+    let body = requests[0].json();
+    assert_eq!(body["model"], "llama-3.1-8b");
+    assert_eq!(body["messages"][0]["content"], "Any errors?");
+    assert_eq!(body["tools"][0]["function"]["name"], "query_logs");
+}
+
+#[tokio::test]
+async fn test_custom_key_header_and_extra_headers() {
+    let server = StubServer::start(|_| {
+        StubResponse::json(
+            200,
+            json!({
+                "choices": [{
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {"name": "list_services", "arguments": "{}"}
+                        }]
+                    }
+                }]
+            }),
+        )
+    })
+    .await;
+
+    let mut headers = HashMap::new();
+    headers.insert("X-Team".to_string(), "sre".to_string());
+
+    let provider = OpenAiProvider::compatible(
+        server.url.clone(),
+        "gateway-model".to_string(),
+        Some("gw-key".to_string()),
+    )
+    .with_api_key_header("api-key".to_string())
+    .with_headers(headers);
+
+    let response = provider
+        .chat(user_request("Which services?"))
+        .await
+        .unwrap();
+    let tool_calls = response.tool_calls.unwrap();
+    assert_eq!(tool_calls[0].function.name, "list_services");
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path, "/chat/completions");
+    assert_eq!(requests[0].header("api-key"), Some("gw-key"));
+    assert_eq!(requests[0].header("authorization"), None);
+    assert_eq!(requests[0].header("x-team"), Some("sre"));
+}
+
+#[tokio::test]
+async fn test_error_status_is_reported() {
+    let server = StubServer::start(|_| {
+        StubResponse::json(400, json!({"error": {"message": "model not found"}}))
+    })
+    .await;
+
+    let provider = OpenAiProvider::compatible(server.url.clone(), "missing".to_string(), None);
+
+    let err = provider.chat(user_request("hi")).await.unwrap_err();
+    assert!(err.to_string().contains("OpenAI-compatible API error"));
+    assert!(err.to_string().contains("model not found"));
+    assert_eq!(server.requests()[0].header("authorization"), None);
+}
+
+#[tokio::test]
+async fn test_provider_from_config_profile() {
+    let server = StubServer::start(|_| {
+        StubResponse::json(
+            200,
+            json!({"choices": [{"message": {"role": "assistant", "content": "ok"}}]}),
+        )
+    })
+    .await;
+
+    let config: zeteo::config::ProviderConfig = serde_json::from_value(json!({
+        "type": "openai-compatible",
+        "base_url": server.url,
+        "model": "local-model",
+        "api_key": "from-config"
+    }))
+    .unwrap();
+
+    let provider = zeteo::providers::from_config("local", &config, None).unwrap();
+    let response = provider.chat(user_request("ping")).await.unwrap();
+
+    assert_eq!(response.content, "ok");
+    assert_eq!(
+        server.requests()[0].header("authorization"),
+        Some("Bearer from-config")
+    );
+}
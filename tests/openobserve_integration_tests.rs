@@ -47,10 +47,10 @@ async fn test_mcp_client_connection() {
     let args = vec!["-y".to_string(), "otel-mcp-server".to_string()];
 
     match zeteo::mcp::McpClient::new(command, &args, &env, SERVER_NAME.to_string()) {
-        Ok(mut client) => {
+        Ok(client) => {
             println!("✓ MCP client created successfully");
 
-            match client.initialize() {
+            match client.initialize().await {
                 Ok(result) => {
                     println!("✓ MCP client initialized successfully");
                     println!("  Initialization result: {:?}", result);
@@ -77,12 +77,12 @@ async fn test_query_logs_basic() {
     let args = vec!["-y".to_string(), "otel-mcp-server".to_string()];
 
     match zeteo::mcp::McpClient::new(command, &args, &env, SERVER_NAME.to_string()) {
-        Ok(mut client) => match client.initialize() {
+        Ok(client) => match client.initialize().await {
             Ok(_) => {
                 tokio::time::sleep(Duration::from_millis(500)).await;
 
                 println!("  Querying logs with query: '*'");
-                match client.query_logs("*", 10) {
+                match client.query_logs("*", 10).await {
                     Ok(result) => {
                         println!("✓ Query successful!");
                         println!(
@@ -126,12 +126,12 @@ async fn test_query_logs_with_error_filter() {
     let args = vec!["-y".to_string(), "otel-mcp-server".to_string()];
 
     match zeteo::mcp::McpClient::new(command, &args, &env, SERVER_NAME.to_string()) {
-        Ok(mut client) => match client.initialize() {
+        Ok(client) => match client.initialize().await {
             Ok(_) => {
                 tokio::time::sleep(Duration::from_millis(500)).await;
 
                 println!("  Querying logs with query: 'level:ERROR OR level:error'");
-                match client.query_logs("level:ERROR OR level:error", 20) {
+                match client.query_logs("level:ERROR OR level:error", 20).await {
                     Ok(result) => {
                         println!("✓ Error query successful!");
 
@@ -176,13 +176,13 @@ async fn test_query_logs_with_specific_search() {
     let queries = vec!["exception", "timeout", "failed", "success"];
 
     match zeteo::mcp::McpClient::new(command, &args, &env, SERVER_NAME.to_string()) {
-        Ok(mut client) => match client.initialize() {
+        Ok(client) => match client.initialize().await {
             Ok(_) => {
                 tokio::time::sleep(Duration::from_millis(500)).await;
 
                 for query in queries {
                     println!("  Testing query: '{}'", query);
-                    match client.query_logs(query, 5) {
+                    match client.query_logs(query, 5).await {
                         Ok(result) => {
                             if let Some(logs) = result.get("logs") {
                                 if let Some(logs_array) = logs.as_array() {
@@ -223,13 +223,13 @@ async fn test_query_logs_different_limits() {
     let limits = vec![1, 5, 10, 20, 50];
 
     match zeteo::mcp::McpClient::new(command, &args, &env, SERVER_NAME.to_string()) {
-        Ok(mut client) => match client.initialize() {
+        Ok(client) => match client.initialize().await {
             Ok(_) => {
                 tokio::time::sleep(Duration::from_millis(500)).await;
 
                 for limit in limits {
                     println!("  Testing with maxResults: {}", limit);
-                    match client.query_logs("*", limit) {
+                    match client.query_logs("*", limit).await {
                         Ok(result) => {
                             if let Some(logs) = result.get("logs") {
                                 if let Some(logs_array) = logs.as_array() {
@@ -276,19 +276,30 @@ async fn test_log_explorer_integration() {
             command: "npx".to_string(),
             args: vec!["-y".to_string(), "otel-mcp-server".to_string()],
             env: create_openobserve_config(),
+            url: None,
+            headers: HashMap::new(),
+            bearer_token_env: None,
         },
     );
 
     let config = zeteo::config::Config {
         servers,
         backends: std::collections::HashMap::new(),
+        providers: std::collections::HashMap::new(),
+        default_provider: None,
+        pricing: HashMap::new(),
+        tool_concurrency: None,
+        sessions: None,
     };
 
     match config.save() {
         Ok(_) => {
             println!("✓ Saved OpenObserve config");
 
-            match zeteo::logs::LogExplorer::new(SERVER_NAME.to_string()).with_mcp_client() {
+            match zeteo::logs::LogExplorer::new(SERVER_NAME.to_string())
+                .with_mcp_client()
+                .await
+            {
                 Ok(explorer) => {
                     println!("✓ LogExplorer created and MCP client initialized");
 
@@ -336,11 +347,11 @@ async fn test_error_handling_invalid_credentials() {
     let args = vec!["-y".to_string(), "otel-mcp-server".to_string()];
 
     match zeteo::mcp::McpClient::new(command, &args, &env, SERVER_NAME.to_string()) {
-        Ok(mut client) => match client.initialize() {
+        Ok(client) => match client.initialize().await {
             Ok(_) => {
                 tokio::time::sleep(Duration::from_millis(500)).await;
 
-                match client.query_logs("*", 10) {
+                match client.query_logs("*", 10).await {
                     Ok(_) => {
                         println!("  ⚠ Query succeeded with invalid credentials (unexpected)");
                     }
@@ -369,7 +380,7 @@ async fn test_concurrent_queries() {
     let args = vec!["-y".to_string(), "otel-mcp-server".to_string()];
 
     match zeteo::mcp::McpClient::new(command, &args, &env, SERVER_NAME.to_string()) {
-        Ok(mut client) => match client.initialize() {
+        Ok(client) => match client.initialize().await {
             Ok(_) => {
                 tokio::time::sleep(Duration::from_millis(500)).await;
 
@@ -378,7 +389,7 @@ async fn test_concurrent_queries() {
                 println!("  Running {} queries sequentially...", queries.len());
 
                 for query in queries {
-                    match client.query_logs(query, 5) {
+                    match client.query_logs(query, 5).await {
                         Ok(result) => {
                             if let Some(logs) = result.get("logs") {
                                 if let Some(logs_array) = logs.as_array() {